@@ -0,0 +1,86 @@
+use hal1::delay::DelayNs;
+
+use crate::clock::is_due;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Periodically searches for alarmed devices and reports them, instead of polling every
+/// device on the bus.
+///
+/// Devices are registered to resolve the addresses found by the alarm search to their
+/// index. Like the [`SensorManager`](crate::SensorManager), the monitor is driven from a
+/// superloop by calling [`AlarmMonitor::poll`] with a millisecond tick.
+pub struct AlarmMonitor<const N: usize> {
+    devices: [Option<Device>; N],
+    interval_ms: u32,
+    next_search: Option<u32>,
+}
+
+impl<const N: usize> AlarmMonitor<N> {
+    /// Creates a monitor that searches for alarmed devices once every `interval_ms`
+    pub fn new(interval_ms: u32) -> Self {
+        AlarmMonitor {
+            devices: [(); N].map(|_| None),
+            interval_ms,
+            next_search: None,
+        }
+    }
+
+    /// Registers a device and returns its index, or gives it back if the monitor is full
+    pub fn register(&mut self, device: Device) -> Result<usize, Device> {
+        match self.devices.iter().position(Option::is_none) {
+            Some(index) => {
+                self.devices[index] = Some(device);
+                Ok(index)
+            }
+            None => Err(device),
+        }
+    }
+
+    /// Removes the device at the given index
+    pub fn unregister(&mut self, index: usize) -> Option<Device> {
+        self.devices.get_mut(index).and_then(Option::take)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Device> {
+        self.devices.get(index).and_then(Option::as_ref)
+    }
+
+    /// Returns the index of the given device, if registered
+    pub fn index_of(&self, device: &Device) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|registered| registered.as_ref() == Some(device))
+    }
+
+    /// Searches for alarmed devices once the interval elapsed and calls `on_alarm` for
+    /// each of them, together with its index or `None` if it is not registered. `now_ms`
+    /// is a free running millisecond tick, wrapping around is handled.
+    ///
+    /// Returns the number of alarmed devices found, `0` if no search was due.
+    pub fn poll<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        now_ms: u32,
+        mut on_alarm: impl FnMut(Option<usize>, &Device),
+    ) -> Result<usize, Error<O::Error>> {
+        if !self.next_search.is_none_or(|at| is_due(now_ms, at)) {
+            return Ok(0);
+        }
+        self.next_search = Some(now_ms.wrapping_add(self.interval_ms));
+
+        let mut search = DeviceSearch::new();
+        let mut count = 0;
+        while let Some(device) = wire.search_next_alarmed(&mut search, delay)? {
+            on_alarm(self.index_of(&device), &device);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,719 @@
+use embedded_hal_async::delay::DelayNs as AsyncDelayNs;
+use hal1::delay::DelayNs;
+
+use crate::compute_partial_crc8;
+use crate::crc::Crc16;
+use crate::Command;
+use crate::DefaultTiming;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::NoGuard;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::ResetInfo;
+use crate::ScratchpadCrc;
+use crate::ScratchpadDevice;
+use crate::SearchPass;
+use crate::SearchState;
+use crate::SearchStep;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use crate::Triplet;
+use crate::ADDRESS_BITS;
+use crate::ADDRESS_BYTES;
+
+/// A bus for async executors, which awaits the reset pulse and the recovery time of each
+/// slot instead of blocking, e.g. to run the bus from an Embassy task.
+///
+/// The timing critical phases of the slots, which last a few microseconds, are still
+/// performed with the blocking `DelayNs`, since an executor can not resume a task
+/// precisely enough. The delay provider therefore has to implement both.
+#[derive(Debug)]
+pub struct AsyncOneWire<
+    ODO: OpenDrainOutput,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
+    wire: OneWire<ODO, T, G>,
+}
+
+impl<E, ODO, T, G> AsyncOneWire<ODO, T, G>
+where
+    E: core::fmt::Debug,
+    ODO: OpenDrainOutput<Error = E>,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    pub fn new(wire: OneWire<ODO, T, G>) -> Self {
+        AsyncOneWire { wire }
+    }
+
+    pub fn into_inner(self) -> OneWire<ODO, T, G> {
+        self.wire
+    }
+
+    pub fn wire(&self) -> &OneWire<ODO, T, G> {
+        &self.wire
+    }
+
+    /// Gives access to the blocking bus, e.g. for the drivers of the devices
+    pub fn wire_mut(&mut self) -> &mut OneWire<ODO, T, G> {
+        &mut self.wire
+    }
+
+    /// Same as [`OneWire::reset`]
+    pub async fn reset<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<bool, Error<E>> {
+        let result = async {
+            if let Some(master) = self.wire.bus_master()? {
+                return master.reset();
+            }
+            self.wire.begin_reset_pulse(delay)?;
+            AsyncDelayNs::delay_ns(delay, self.wire.active_timing().reset_low_ns()).await;
+            self.wire.set_input()?;
+            let val = self.wire.sample_presence(delay)?;
+            AsyncDelayNs::delay_ns(delay, self.wire.active_timing().reset_recovery_ns()).await;
+            Ok(val)
+        }
+        .await;
+        self.wire.stats.record_reset(matches!(result, Ok(true)));
+        result
+    }
+
+    /// Same as [`OneWire::reset_with_info`], the presence pulse is measured with the
+    /// blocking delay
+    pub async fn reset_with_info<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<ResetInfo, Error<E>> {
+        let result = async {
+            if let Some(master) = self.wire.bus_master()? {
+                return master.reset().map(|presence| ResetInfo {
+                    presence,
+                    presence_start_us: None,
+                    presence_duration_us: 0,
+                });
+            }
+            self.wire.begin_reset_pulse(delay)?;
+            AsyncDelayNs::delay_ns(delay, self.wire.active_timing().reset_low_ns()).await;
+            self.wire.set_input()?;
+            Ok(self.wire.measure_presence(delay)?)
+        }
+        .await;
+        self.wire
+            .stats
+            .record_reset(matches!(result, Ok(ResetInfo { presence: true, .. })));
+        result
+    }
+
+    /// Same as [`OneWire::select`]
+    pub async fn select<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+    ) -> Result<(), Error<E>> {
+        self.write_byte_slots(delay, Command::SelectRom as u8, false)
+            .await?;
+        self.write_bytes_held(delay, &device.address).await?;
+        if !self.wire.parasite_mode {
+            self.wire.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`OneWire::skip`]
+    pub async fn skip<D: DelayNs + AsyncDelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        let parasite_mode = self.wire.parasite_mode;
+        self.write_byte(delay, Command::SkipRom as u8, parasite_mode)
+            .await?;
+        Ok(())
+    }
+
+    /// Same as [`OneWire::select_or_skip`]
+    pub async fn select_or_skip<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: Option<&Device>,
+    ) -> Result<(), Error<E>> {
+        match device {
+            Some(device) => self.select(delay, device).await,
+            None => self.skip(delay).await,
+        }
+    }
+
+    /// Same as [`OneWire::overdrive_select`]
+    pub async fn overdrive_select<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+    ) -> Result<(), Error<E>> {
+        self.wire.check_overdrive_support()?;
+        self.write_byte_slots(delay, Command::OverdriveMatchRom as u8, false)
+            .await?;
+        self.wire.overdrive = true;
+        self.write_bytes_held(delay, &device.address).await?;
+        if !self.wire.parasite_mode {
+            self.wire.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`OneWire::overdrive_skip`]
+    pub async fn overdrive_skip<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        self.wire.check_overdrive_support()?;
+        let parasite_mode = self.wire.parasite_mode;
+        self.write_byte(delay, Command::OverdriveSkipRom as u8, parasite_mode)
+            .await?;
+        self.wire.overdrive = true;
+        Ok(())
+    }
+
+    /// Same as [`OneWire::enter_overdrive`]
+    pub async fn enter_overdrive<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<bool, Error<E>> {
+        self.wire.overdrive = false;
+        self.wire.begin_transaction()?;
+        let result = async {
+            if !self.reset(delay).await? {
+                return Ok(false);
+            }
+            self.overdrive_skip(delay).await?;
+            Ok(true)
+        }
+        .await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::exit_overdrive`]
+    pub async fn exit_overdrive<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<bool, Error<E>> {
+        self.wire.overdrive = false;
+        self.wire.begin_transaction()?;
+        let result = self.reset(delay).await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::continue_write`]
+    pub async fn continue_write<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
+        self.write_bytes(delay, write).await?;
+        Ok(())
+    }
+
+    /// Same as [`OneWire::continue_read`]
+    pub async fn continue_read<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.read_bytes(delay, read).await?;
+        Ok(())
+    }
+
+    pub async fn read_bytes<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        dst: &mut [u8],
+    ) -> Result<(), E> {
+        for d in dst {
+            *d = self.read_byte(delay).await?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`OneWire::read_bytes_crc8`]
+    pub async fn read_bytes_crc8<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        dst: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.read_bytes(delay, dst).await?;
+        let crc8 = self.read_byte(delay).await?;
+        let computed = (self.wire.crc8)(0u8, dst);
+        if computed != crc8 {
+            self.wire
+                .stats
+                .check_crc(Err(Error::CrcMismatch(computed, crc8)))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn write_bytes<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        bytes: &[u8],
+    ) -> Result<(), E> {
+        self.write_bytes_held(delay, bytes).await?;
+        if !self.wire.parasite_mode {
+            self.wire.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`OneWire::touch_byte`]
+    pub async fn touch_byte<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        byte: u8,
+    ) -> Result<u8, E> {
+        if byte == 0xFF {
+            return self.read_byte(delay).await;
+        }
+        self.wire.stats.record_byte();
+        let mut sampled = 0_u8;
+        for i in 0..8 {
+            let mask = 1 << i;
+            if byte & mask == 0 {
+                self.write_bit(delay, false).await?;
+            } else if self.read_bit(delay).await? {
+                sampled |= mask;
+            }
+        }
+        if !self.wire.parasite_mode {
+            self.wire.disable_parasite_mode()?;
+        }
+        Ok(sampled)
+    }
+
+    /// Same as [`OneWire::touch_block`]
+    pub async fn touch_block<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        block: &mut [u8],
+    ) -> Result<(), E> {
+        for byte in block {
+            *byte = self.touch_byte(delay, *byte).await?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`OneWire::reset_select_write_read`]
+    pub async fn reset_select_write_read<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.reset_select_or_skip_write_read(delay, Some(device), write, read)
+            .await
+    }
+
+    /// Same as [`OneWire::reset_select_read_only`]
+    pub async fn reset_select_read_only<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.reset_select_or_skip_write_read(delay, Some(device), &[], read)
+            .await
+    }
+
+    /// Same as [`OneWire::reset_select_write_only`]
+    pub async fn reset_select_write_only<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
+        self.reset_select_or_skip_write_read(delay, Some(device), write, &mut [])
+            .await
+    }
+
+    /// Same as [`OneWire::reset_skip_write_only`]
+    pub async fn reset_skip_write_only<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
+        self.reset_select_or_skip_write_read(delay, None, write, &mut [])
+            .await
+    }
+
+    /// Same as [`OneWire::reset_select_or_skip_write_read`]
+    pub async fn reset_select_or_skip_write_read<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: Option<&Device>,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = async {
+            self.reset(delay).await?;
+            self.select_or_skip(delay, device).await?;
+            if !write.is_empty() {
+                self.write_bytes(delay, write).await?;
+            }
+            self.read_bytes(delay, read).await?;
+            Ok(())
+        }
+        .await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`ScratchpadDevice::read_scratchpad`]
+    pub async fn read_scratchpad<S: ScratchpadDevice, D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        device: &S,
+        delay: &mut D,
+        dst: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        let command = S::read_scratchpad_command();
+        let dst = &mut dst[..S::scratchpad_len()];
+        self.wire.begin_transaction()?;
+        let result = async {
+            self.reset(delay).await?;
+            self.select_or_skip(delay, device.target()).await?;
+            self.write_bytes(delay, &[command]).await?;
+            match S::scratchpad_crc() {
+                ScratchpadCrc::None => Ok(self.read_bytes(delay, dst).await?),
+                ScratchpadCrc::Crc8 => self.read_bytes_crc8(delay, dst).await,
+                ScratchpadCrc::Crc16 => {
+                    self.read_bytes(delay, dst).await?;
+                    let mut crc16 = [0u8; 2];
+                    self.read_bytes(delay, &mut crc16).await?;
+                    let mut crc = Crc16::new();
+                    crc.update(&[command]);
+                    crc.update(dst);
+                    self.wire.stats.check_crc(crc.verify_inverted(crc16))
+                }
+            }
+        }
+        .await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::search_next`]
+    pub async fn search_next<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        search: &mut DeviceSearch,
+        delay: &mut D,
+    ) -> Result<Option<Device>, Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = self.search(search, delay, Command::SearchNext).await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::search_next_alarmed`]
+    pub async fn search_next_alarmed<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        search: &mut DeviceSearch,
+        delay: &mut D,
+    ) -> Result<Option<Device>, Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = self.search(search, delay, Command::SearchNextAlarmed).await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::read_rom`]
+    pub async fn read_rom<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<Option<Device>, Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = async {
+            if !self.reset(delay).await? {
+                return Ok(None);
+            }
+            let parasite_mode = self.wire.parasite_mode;
+            self.write_byte(delay, Command::ReadRom as u8, parasite_mode)
+                .await?;
+            let mut address = [0u8; ADDRESS_BYTES as usize];
+            self.read_bytes_crc8(delay, &mut address[..7]).await?;
+            address[7] = compute_partial_crc8(0, &address[..7]);
+            Ok(Some(Device { address }))
+        }
+        .await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Same as [`OneWire::verify`]
+    pub async fn verify<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        device: &Device,
+    ) -> Result<bool, Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = async {
+            if !self.reset(delay).await? {
+                return Ok(false);
+            }
+            self.write_byte(delay, Command::SearchNext as u8, false)
+                .await?;
+            for i in 0..ADDRESS_BITS {
+                let bit0 = self.read_bit(delay).await?;
+                let bit1 = self.read_bit(delay).await?;
+                let bit = DeviceSearch::is_bit_set(&device.address, i);
+                // bit0 is only low if a device with a 0 bit is left, bit1 if one with a 1 bit
+                if (bit && bit1) || (!bit && bit0) {
+                    return Ok(false);
+                }
+                self.write_bit(delay, bit).await?;
+            }
+            Ok(true)
+        }
+        .await;
+        self.wire.end_transaction(result)
+    }
+
+    /// Drives the same search passes as the blocking search
+    async fn search<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        rom: &mut DeviceSearch,
+        delay: &mut D,
+        cmd: Command,
+    ) -> Result<Option<Device>, Error<E>> {
+        if SearchState::End == rom.state {
+            return Ok(None);
+        }
+
+        if !self.reset(delay).await? {
+            return Ok(None);
+        }
+
+        self.write_byte(delay, cmd as u8, false).await?;
+
+        let mut pass = SearchPass::new(rom);
+        let mut triplet = None;
+        loop {
+            match pass.next(rom, triplet) {
+                SearchStep::Triplet(direction) => {
+                    triplet = Some(self.triplet(delay, direction).await?)
+                }
+                SearchStep::Done(device) => return Ok(device),
+            }
+        }
+    }
+
+    /// Same as [`OneWire::triplet`]
+    async fn triplet<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        direction: bool,
+    ) -> Result<Triplet, E> {
+        if let Some(master) = self.wire.bus_master()? {
+            return master.triplet(direction);
+        }
+        let bit = self.read_bit(delay).await?;
+        let complement = self.read_bit(delay).await?;
+        let triplet = Triplet::new(bit, complement, direction);
+        self.write_bit(delay, triplet.direction).await?;
+        Ok(triplet)
+    }
+
+    async fn read_byte<D: DelayNs + AsyncDelayNs>(&mut self, delay: &mut D) -> Result<u8, E> {
+        self.wire.stats.record_byte();
+        if let Some(master) = self.wire.bus_master()? {
+            return master.read_byte();
+        }
+        let mut byte = 0_u8;
+        for _ in 0..8 {
+            byte >>= 1;
+            if self.read_bit(delay).await? {
+                byte |= 0x80;
+            }
+        }
+        Ok(byte)
+    }
+
+    async fn write_byte<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        byte: u8,
+        parasite_mode: bool,
+    ) -> Result<(), E> {
+        let strong_pull_up = parasite_mode && self.wire.parasite_mode;
+        self.write_byte_slots(delay, byte, strong_pull_up).await?;
+        if !parasite_mode {
+            self.wire.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Same as the blocking `write_bytes_held`
+    async fn write_bytes_held<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        bytes: &[u8],
+    ) -> Result<(), E> {
+        if let Some((last, bytes)) = bytes.split_last() {
+            for b in bytes {
+                self.write_byte_slots(delay, *b, false).await?;
+            }
+            self.write_byte(delay, *last, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Same as the blocking `write_byte_slots`
+    async fn write_byte_slots<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        mut byte: u8,
+        strong_pull_up: bool,
+    ) -> Result<(), E> {
+        self.wire.stats.record_byte();
+        if let Some(master) = self.wire.bus_master()? {
+            return master.write_byte(byte, strong_pull_up);
+        }
+        for _ in 0..8 {
+            self.write_bit(delay, (byte & 0x01) == 0x01).await?;
+            byte >>= 1;
+        }
+        if strong_pull_up {
+            self.wire.power_on()?;
+        }
+        Ok(())
+    }
+
+    async fn read_bit<D: DelayNs + AsyncDelayNs>(&mut self, delay: &mut D) -> Result<bool, E> {
+        if let Some(master) = self.wire.bus_master()? {
+            return master.read_bit();
+        }
+        let val = self.wire.read_slot(delay)?;
+        AsyncDelayNs::delay_ns(delay, self.wire.active_timing().read_recovery_ns()).await;
+        self.wire.check_released()?;
+        Ok(val)
+    }
+
+    async fn write_bit<D: DelayNs + AsyncDelayNs>(
+        &mut self,
+        delay: &mut D,
+        high: bool,
+    ) -> Result<(), E> {
+        if let Some(master) = self.wire.bus_master()? {
+            return master.write_bit(high);
+        }
+        let recovery = self.wire.write_slot(delay, high)?;
+        AsyncDelayNs::delay_ns(delay, recovery).await;
+        self.wire.check_released()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{block_on, AddressGenerator, NoDelay, SearchBus};
+    use crate::{BusMaster, MasterPin};
+    use core::convert::Infallible;
+
+    /// A master answering Read ROM byte by byte, which must not be read bit by bit
+    struct ReadRom {
+        address: [u8; 8],
+        read: usize,
+    }
+
+    impl BusMaster for ReadRom {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+            self.read = 0;
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            panic!("bytes are read through the master")
+        }
+
+        fn write_bit(&mut self, _high: bool) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn read_byte(&mut self) -> Result<u8, Infallible> {
+            self.read += 1;
+            Ok(self.address[self.read - 1])
+        }
+
+        fn write_byte(&mut self, _byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reset_and_search() {
+        let devices = [
+            Device {
+                address: [0x28, 0x01, 0, 0, 0, 0, 0, 0],
+            },
+            Device {
+                address: [0x10, 0x01, 0, 0, 0, 0, 0, 0],
+            },
+            Device {
+                address: [0x28, 0x02, 0, 0, 0, 0, 0, 0],
+            },
+        ];
+        let mut wire = AsyncOneWire::new(OneWire::new(MasterPin(SearchBus::new(&devices)), false));
+        let mut delay = NoDelay;
+        assert!(block_on(wire.reset(&mut delay)).unwrap());
+
+        let mut search = DeviceSearch::new_for_family(0x28);
+        let mut found = [false; 3];
+        while let Some(device) = block_on(wire.search_next(&mut search, &mut delay)).unwrap() {
+            let index = devices.iter().position(|d| *d == device).unwrap();
+            assert!(!found[index]);
+            found[index] = true;
+        }
+        assert_eq!(found, [true, false, true]);
+
+        let mut search = DeviceSearch::new();
+        let mut count = 0;
+        while block_on(wire.search_next(&mut search, &mut delay))
+            .unwrap()
+            .is_some()
+        {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_read_rom_through_master() {
+        let device = AddressGenerator::new(7).next_address();
+        let mut wire = AsyncOneWire::new(OneWire::new(
+            MasterPin(ReadRom {
+                address: device.address,
+                read: 0,
+            }),
+            false,
+        ));
+        let mut delay = NoDelay;
+        assert_eq!(block_on(wire.read_rom(&mut delay)).unwrap(), Some(device));
+        let info = block_on(wire.reset_with_info(&mut delay)).unwrap();
+        assert!(info.presence);
+        assert!(matches!(
+            block_on(wire.enter_overdrive(&mut delay)),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn test_verify() {
+        let devices = [
+            AddressGenerator::new(1).next_address(),
+            AddressGenerator::new(2).next_address(),
+        ];
+        let mut wire = AsyncOneWire::new(OneWire::new(
+            MasterPin(SearchBus::new(&devices[..1])),
+            false,
+        ));
+        let mut delay = NoDelay;
+        assert!(block_on(wire.verify(&mut delay, &devices[0])).unwrap());
+        assert!(!block_on(wire.verify(&mut delay, &devices[1])).unwrap());
+    }
+}
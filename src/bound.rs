@@ -0,0 +1,178 @@
+use hal1::delay::DelayNs;
+
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::ResetInfo;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use crate::Triplet;
+use crate::{DefaultTiming, NoGuard};
+
+/// A [`OneWire`] bus bundled with the delay provider it uses, so none of its methods take
+/// a delay.
+///
+/// Devices and sensors still take the bus and the delay separately, [`BoundOneWire::parts`]
+/// hands out both. Use [`OneWire`] directly if the delay provider is shared with other
+/// drivers.
+pub struct BoundOneWire<
+    ODO: OpenDrainOutput,
+    D,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
+    wire: OneWire<ODO, T, G>,
+    delay: D,
+}
+
+impl<E, ODO, D, T, G> BoundOneWire<ODO, D, T, G>
+where
+    E: core::fmt::Debug,
+    ODO: OpenDrainOutput<Error = E>,
+    D: DelayNs,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    pub fn new(wire: OneWire<ODO, T, G>, delay: D) -> Self {
+        BoundOneWire { wire, delay }
+    }
+
+    pub fn into_parts(self) -> (OneWire<ODO, T, G>, D) {
+        (self.wire, self.delay)
+    }
+
+    pub fn wire(&self) -> &OneWire<ODO, T, G> {
+        &self.wire
+    }
+
+    pub fn wire_mut(&mut self) -> &mut OneWire<ODO, T, G> {
+        &mut self.wire
+    }
+
+    pub fn delay_mut(&mut self) -> &mut D {
+        &mut self.delay
+    }
+
+    /// Borrows the bus and the delay at once, e.g. to pass them on to a sensor
+    pub fn parts(&mut self) -> (&mut OneWire<ODO, T, G>, &mut D) {
+        (&mut self.wire, &mut self.delay)
+    }
+
+    /// See [`OneWire::transaction`]
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        self.wire.begin_transaction()?;
+        let result = f(self);
+        self.wire.end_transaction(result)
+    }
+
+    pub fn reset(&mut self) -> Result<bool, Error<E>> {
+        self.wire.reset(&mut self.delay)
+    }
+
+    pub fn reset_with_info(&mut self) -> Result<ResetInfo, Error<E>> {
+        self.wire.reset_with_info(&mut self.delay)
+    }
+
+    pub fn select(&mut self, device: &Device) -> Result<(), Error<E>> {
+        self.wire.select(&mut self.delay, device)
+    }
+
+    pub fn skip(&mut self) -> Result<(), Error<E>> {
+        self.wire.skip(&mut self.delay)
+    }
+
+    pub fn select_or_skip(&mut self, device: Option<&Device>) -> Result<(), Error<E>> {
+        self.wire.select_or_skip(&mut self.delay, device)
+    }
+
+    pub fn read_bytes(&mut self, dst: &mut [u8]) -> Result<(), E> {
+        self.wire.read_bytes(&mut self.delay, dst)
+    }
+
+    pub fn read_bytes_crc8(&mut self, dst: &mut [u8]) -> Result<(), Error<E>> {
+        self.wire.read_bytes_crc8(&mut self.delay, dst)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.wire.write_bytes(&mut self.delay, bytes)
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, E> {
+        self.wire.read_bit(&mut self.delay)
+    }
+
+    pub fn write_bit(&mut self, high: bool) -> Result<(), E> {
+        self.wire.write_bit(&mut self.delay, high)
+    }
+
+    /// See [`OneWire::triplet`]
+    pub fn triplet(&mut self, direction: bool) -> Result<Triplet, E> {
+        self.wire.triplet(&mut self.delay, direction)
+    }
+
+    /// See [`OneWire::touch_byte`]
+    pub fn touch_byte(&mut self, byte: u8) -> Result<u8, E> {
+        self.wire.touch_byte(&mut self.delay, byte)
+    }
+
+    /// See [`OneWire::touch_block`]
+    pub fn touch_block(&mut self, block: &mut [u8]) -> Result<(), E> {
+        self.wire.touch_block(&mut self.delay, block)
+    }
+
+    pub fn reset_select_write_read(
+        &mut self,
+        device: &Device,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.wire
+            .reset_select_write_read(&mut self.delay, device, write, read)
+    }
+
+    pub fn reset_select_read_only(
+        &mut self,
+        device: &Device,
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.wire
+            .reset_select_read_only(&mut self.delay, device, read)
+    }
+
+    pub fn reset_select_write_only(
+        &mut self,
+        device: &Device,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
+        self.wire
+            .reset_select_write_only(&mut self.delay, device, write)
+    }
+
+    pub fn reset_skip_write_only(&mut self, write: &[u8]) -> Result<(), Error<E>> {
+        self.wire.reset_skip_write_only(&mut self.delay, write)
+    }
+
+    pub fn search_next(&mut self, search: &mut DeviceSearch) -> Result<Option<Device>, Error<E>> {
+        self.wire.search_next(search, &mut self.delay)
+    }
+
+    pub fn search_next_alarmed(
+        &mut self,
+        search: &mut DeviceSearch,
+    ) -> Result<Option<Device>, Error<E>> {
+        self.wire.search_next_alarmed(search, &mut self.delay)
+    }
+
+    /// Iterates over the devices found by the given search
+    pub fn devices(
+        &mut self,
+        search: DeviceSearch,
+    ) -> impl Iterator<Item = Result<Device, Error<E>>> + '_ {
+        search.into_iter(&mut self.wire, &mut self.delay)
+    }
+}
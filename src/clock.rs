@@ -0,0 +1,65 @@
+use hal1::delay::DelayNs;
+
+/// A free running millisecond time source provided by the application, e.g. backed by
+/// a SysTick counter or an RTC.
+///
+/// The tick is allowed to wrap around, consumers compare ticks with wrapping arithmetic.
+pub trait Clock {
+    /// returns the current tick in milliseconds
+    fn now_ms(&mut self) -> u32;
+}
+
+impl<F: FnMut() -> u32> Clock for F {
+    fn now_ms(&mut self) -> u32 {
+        self()
+    }
+}
+
+/// Whether the wrapping tick `now` reached `at`
+pub(crate) fn is_due(now: u32, at: u32) -> bool {
+    now.wrapping_sub(at) < 0x8000_0000
+}
+
+/// A free running microsecond counter provided by the application, e.g. the cycle counter
+/// divided by the core clock in MHz.
+///
+/// It is used to measure the actual duration of the bus timing, which needs a much higher
+/// resolution than a [`Clock`]. The counter is allowed to wrap around.
+pub trait TimestampSource {
+    /// returns the current counter value in microseconds
+    fn now_us(&mut self) -> u32;
+}
+
+impl<F: FnMut() -> u32> TimestampSource for F {
+    fn now_us(&mut self) -> u32 {
+        self()
+    }
+}
+
+/// Called for waits of a millisecond or longer, like conversions or copying a scratchpad
+/// into EEPROM, which would otherwise spin the microsecond delay.
+///
+/// RTOS users can sleep the task or enter a low power mode instead, e.g. with a closure
+/// that calls the sleep function of their scheduler. [`Spin`] keeps spinning the delay.
+pub trait Idle {
+    /// Waits at least `ms` milliseconds, `delay` may be used to spin
+    fn idle(&mut self, ms: u16, delay: &mut dyn DelayNs);
+}
+
+impl<F: FnMut(u16)> Idle for F {
+    fn idle(&mut self, ms: u16, _delay: &mut dyn DelayNs) {
+        self(ms)
+    }
+}
+
+/// Waits by spinning the delay
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spin;
+
+impl Idle for Spin {
+    fn idle(&mut self, ms: u16, delay: &mut dyn DelayNs) {
+        for _ in 0..ms {
+            delay.delay_us(1000);
+        }
+    }
+}
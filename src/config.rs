@@ -0,0 +1,187 @@
+use core::fmt::Debug;
+use hal1::delay::DelayNs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Crc16;
+use crate::EepromDevice;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Size of the header preceding the serialized configuration
+pub const HEADER_SIZE: u16 = 8;
+
+/// Identifies a stored configuration, so erased or foreign memory is not mistaken for it
+const MAGIC: [u8; 2] = *b"1W";
+
+#[derive(Debug)]
+pub enum ConfigError<E: Sized + Debug> {
+    Wire(Error<E>),
+    /// The region holds no configuration, e.g. because it has never been written
+    Empty,
+    /// The configuration does not fit into the region or the given buffer
+    TooLarge,
+    /// The configuration was stored with another version, which the migration rejected
+    UnsupportedVersion(u8),
+    Serialization(postcard::Error),
+}
+
+impl<E: Sized + Debug> From<Error<E>> for ConfigError<E> {
+    fn from(e: Error<E>) -> Self {
+        ConfigError::Wire(e)
+    }
+}
+
+impl<E: Sized + Debug> From<postcard::Error> for ConfigError<E> {
+    fn from(e: postcard::Error) -> Self {
+        ConfigError::Serialization(e)
+    }
+}
+
+/// A configuration as returned by [`ConfigStore::load`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loaded<C> {
+    pub value: C,
+    /// Whether the value has been migrated from an older version and should be stored again
+    pub migrated: bool,
+}
+
+/// Stores a serializable configuration in a region of a 1-Wire EEPROM, so
+/// field-replaceable modules can carry their own settings.
+///
+/// The configuration is serialized with postcard, preceded by a header with a magic,
+/// the version of the configuration, the length of the payload and a CRC16 over both.
+pub struct ConfigStore<'a, D> {
+    device: &'a D,
+    start: u16,
+    len: u16,
+}
+
+impl<'a, D: EepromDevice> ConfigStore<'a, D> {
+    /// Uses the `len` bytes starting at `start`, including the header. Panics if the
+    /// region exceeds the memory or can not even hold the header.
+    pub fn new(device: &'a D, start: u16, len: u16) -> Self {
+        assert!(len >= HEADER_SIZE);
+        assert!(u32::from(start) + u32::from(len) <= u32::from(D::memory_size()));
+        ConfigStore { device, start, len }
+    }
+
+    /// Serializes `config` into `buf`, which has to provide room for the header too, and
+    /// writes it with the given version
+    pub fn store<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard, C: Serialize>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        version: u8,
+        config: &C,
+        buf: &mut [u8],
+    ) -> Result<(), ConfigError<O::Error>> {
+        let header_size = usize::from(HEADER_SIZE);
+        if buf.len() < header_size {
+            return Err(ConfigError::TooLarge);
+        }
+        let (header, payload) = buf.split_at_mut(header_size);
+        let len = postcard::to_slice(config, payload)
+            .map_err(|e| match e {
+                postcard::Error::SerializeBufferFull => ConfigError::TooLarge,
+                e => ConfigError::Serialization(e),
+            })?
+            .len();
+        if len + header_size > usize::from(self.len) {
+            return Err(ConfigError::TooLarge);
+        }
+
+        encode_header(header, version, &payload[..len]);
+        self.device
+            .write_memory(wire, delay, idle, self.start, &buf[..header_size + len])?;
+        Ok(())
+    }
+
+    /// Reads the stored configuration into `buf` and deserializes it. A configuration
+    /// stored with another version is handed to `migrate` together with its raw payload,
+    /// which returns `None` if it can not convert it.
+    pub fn load<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard, C: DeserializeOwned>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        version: u8,
+        buf: &mut [u8],
+        migrate: impl FnOnce(u8, &[u8]) -> Option<C>,
+    ) -> Result<Loaded<C>, ConfigError<O::Error>> {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        self.device
+            .read_memory(wire, delay, self.start, &mut header)?;
+        if header[..2] != MAGIC {
+            return Err(ConfigError::Empty);
+        }
+        let stored_version = header[2];
+        let len = u16::from_le_bytes([header[4], header[5]]);
+        if len > self.len - HEADER_SIZE {
+            return Err(ConfigError::Empty);
+        }
+        let payload = buf
+            .get_mut(..usize::from(len))
+            .ok_or(ConfigError::TooLarge)?;
+        self.device
+            .read_memory(wire, delay, self.start + HEADER_SIZE, payload)?;
+
+        let mut crc = Crc16::new();
+        crc.update(&header[..6]);
+        crc.update(payload);
+        crc.verify_inverted([header[6], header[7]])?;
+
+        if stored_version == version {
+            Ok(Loaded {
+                value: postcard::from_bytes(payload)?,
+                migrated: false,
+            })
+        } else {
+            match migrate(stored_version, payload) {
+                Some(value) => Ok(Loaded {
+                    value,
+                    migrated: true,
+                }),
+                None => Err(ConfigError::UnsupportedVersion(stored_version)),
+            }
+        }
+    }
+}
+
+fn encode_header(header: &mut [u8], version: u8, payload: &[u8]) {
+    header[..2].copy_from_slice(&MAGIC);
+    header[2] = version;
+    header[3] = 0;
+    header[4..6].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+    let mut crc = Crc16::new();
+    crc.update(&header[..6]);
+    crc.update(payload);
+    header[6..8].copy_from_slice(&(!crc.finish()).to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        let mut buf = [0u8; 32];
+        let (header, payload) = buf.split_at_mut(HEADER_SIZE as usize);
+        let len = postcard::to_slice(&(300u16, -5i8), payload).unwrap().len();
+        encode_header(header, 3, &payload[..len]);
+
+        assert_eq!(&header[..6], &[b'1', b'W', 3, 0, len as u8, 0]);
+        let mut crc = Crc16::new();
+        crc.update(&buf[..6]);
+        crc.update(&buf[8..8 + len]);
+        assert!(crc.verify_inverted::<()>([buf[6], buf[7]]).is_ok());
+        assert_eq!(
+            postcard::from_bytes::<(u16, i8)>(&buf[8..8 + len]).unwrap(),
+            (300, -5)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use core::fmt::Debug;
+
+use crate::Device;
+use crate::Error;
+use crate::ADDRESS_BYTES;
+
+pub fn ensure_correct_rcr8<E: Debug>(
+    device: &Device,
+    data: &[u8],
+    crc8: u8,
+) -> Result<(), Error<E>> {
+    let computed = compute_crc8(device, data);
+    if computed != crc8 {
+        Err(Error::CrcMismatch(computed, crc8))
+    } else {
+        Ok(())
+    }
+}
+
+pub const fn compute_crc8(device: &Device, data: &[u8]) -> u8 {
+    let crc = compute_partial_crc8(0u8, &device.address);
+    compute_partial_crc8(crc, data)
+}
+
+/// Returns whether the last byte of the address is the CRC8 of the preceding bytes.
+///
+/// Being a `const fn`, it allows to verify known addresses at compile time:
+///
+/// ```
+/// const SENSOR: [u8; 8] = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2];
+/// const _: () = assert!(onewire::crc::is_valid_address(&SENSOR));
+/// ```
+pub const fn is_valid_address(address: &[u8; ADDRESS_BYTES as usize]) -> bool {
+    compute_partial_crc8(0u8, address) == 0x00
+}
+
+/// Continues the Dallas/Maxim CRC8 computation of `crc` over `data`.
+///
+/// With the `crc-table` feature enabled, a 256 byte lookup table is used instead of
+/// the bitwise computation, which trades flash size for speed on large transfers.
+#[cfg(not(feature = "crc-table"))]
+pub const fn compute_partial_crc8(crc: u8, data: &[u8]) -> u8 {
+    let mut crc = crc;
+    let mut i = 0;
+    while i < data.len() {
+        crc = crc8_byte(crc, data[i]);
+        i += 1;
+    }
+    crc
+}
+
+/// Continues the Dallas/Maxim CRC8 computation of `crc` over `data`.
+///
+/// With the `crc-table` feature enabled, a 256 byte lookup table is used instead of
+/// the bitwise computation, which trades flash size for speed on large transfers.
+#[cfg(feature = "crc-table")]
+pub const fn compute_partial_crc8(crc: u8, data: &[u8]) -> u8 {
+    let mut crc = crc;
+    let mut i = 0;
+    while i < data.len() {
+        crc = CRC8_TABLE[(crc ^ data[i]) as usize];
+        i += 1;
+    }
+    crc
+}
+
+const fn crc8_byte(crc: u8, byte: u8) -> u8 {
+    let mut crc = crc;
+    let mut byte = byte;
+    let mut i = 0;
+    while i < 8 {
+        let mix = (crc ^ byte) & 0x01;
+        crc >>= 1;
+        if mix != 0x00 {
+            crc ^= 0x8C;
+        }
+        byte >>= 1;
+        i += 1;
+    }
+    crc
+}
+
+#[cfg(feature = "crc-table")]
+const CRC8_TABLE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = crc8_byte(0, i as u8);
+        i += 1;
+    }
+    table
+};
+
+/// Computes the Dallas/Maxim CRC8, allowing platforms with a CRC peripheral to offload
+/// the computation from the CPU, see [`OneWire::with_crc8`](crate::OneWire::with_crc8).
+/// [`SoftwareCrc8`] is the portable default. The bus keeps no provider instance, so the
+/// peripheral is accessed through a static.
+pub trait Crc8Provider {
+    /// continues the CRC8 computation of `crc` over `data`
+    fn crc8(crc: u8, data: &[u8]) -> u8;
+}
+
+/// Computes the CRC8 on the CPU with [`compute_partial_crc8`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SoftwareCrc8;
+
+impl Crc8Provider for SoftwareCrc8 {
+    fn crc8(crc: u8, data: &[u8]) -> u8 {
+        compute_partial_crc8(crc, data)
+    }
+}
+
+/// Same as [`ensure_correct_rcr8`], but computes the CRC8 with the given provider
+pub fn ensure_correct_crc8_with<P: Crc8Provider, E: Debug>(
+    device: &Device,
+    data: &[u8],
+    crc8: u8,
+) -> Result<(), Error<E>> {
+    let computed = P::crc8(P::crc8(0u8, &device.address), data);
+    if computed != crc8 {
+        Err(Error::CrcMismatch(computed, crc8))
+    } else {
+        Ok(())
+    }
+}
+
+/// Verifies the CRC16 as transmitted by devices like the DS2408, DS2423 or DS2431: inverted
+/// and least significant byte first. `data` has to contain all bytes covered by the CRC,
+/// which usually includes the command and address bytes sent to the device.
+pub fn ensure_correct_crc16<E: Debug>(
+    data: &[u8],
+    inverted_crc16: [u8; 2],
+) -> Result<(), Error<E>> {
+    let computed = compute_partial_crc16(0u16, data);
+    let expected = !u16::from_le_bytes(inverted_crc16);
+    if computed != expected {
+        Err(Error::Crc16Mismatch(computed, expected))
+    } else {
+        Ok(())
+    }
+}
+
+/// Continues the 1-Wire CRC16 computation (polynomial x^16 + x^15 + x^2 + 1) of `crc` over `data`
+pub const fn compute_partial_crc16(crc: u16, data: &[u8]) -> u16 {
+    let mut crc = crc;
+    let mut i = 0;
+    while i < data.len() {
+        let mut byte = data[i];
+        let mut bit = 0;
+        while bit < 8 {
+            let mix = (crc ^ byte as u16) & 0x0001;
+            crc >>= 1;
+            if mix != 0x0000 {
+                crc ^= 0xA001;
+            }
+            byte >>= 1;
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Incremental CRC8, so data read in pieces can be validated without buffering it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Crc8 {
+    crc: u8,
+}
+
+impl Crc8 {
+    pub const fn new() -> Self {
+        Crc8 { crc: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc = compute_partial_crc8(self.crc, data);
+    }
+
+    pub const fn finish(&self) -> u8 {
+        self.crc
+    }
+}
+
+/// Incremental 1-Wire CRC16, so data read in pieces can be validated without buffering it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Crc16 {
+    crc: u16,
+}
+
+impl Crc16 {
+    pub const fn new() -> Self {
+        Crc16 { crc: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc = compute_partial_crc16(self.crc, data);
+    }
+
+    pub const fn finish(&self) -> u16 {
+        self.crc
+    }
+
+    /// Compares the CRC with one as transmitted by the devices, see [`ensure_correct_crc16`]
+    pub fn verify_inverted<E: Debug>(&self, inverted_crc16: [u8; 2]) -> Result<(), Error<E>> {
+        let expected = !u16::from_le_bytes(inverted_crc16);
+        if self.crc != expected {
+            Err(Error::Crc16Mismatch(self.crc, expected))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc8_rom() {
+        // example from Maxim application note 27
+        let rom = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(compute_partial_crc8(0, &rom), 0xA2);
+        assert_eq!(compute_partial_crc8(0xA2, &[0xA2]), 0x00);
+        assert!(is_valid_address(&[
+            0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2
+        ]));
+        assert!(!is_valid_address(&[
+            0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA3
+        ]));
+    }
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(compute_partial_crc16(0, b"123456789"), 0xBB3D);
+        let inverted = (!0xBB3Du16).to_le_bytes();
+        assert!(ensure_correct_crc16::<()>(b"123456789", inverted).is_ok());
+        assert!(ensure_correct_crc16::<()>(b"123456780", inverted).is_err());
+    }
+
+    #[test]
+    fn test_crc8_matches_bitwise() {
+        for crc in 0..=255u8 {
+            for byte in 0..=255u8 {
+                assert_eq!(compute_partial_crc8(crc, &[byte]), crc8_byte(crc, byte));
+            }
+        }
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut crc8 = Crc8::new();
+        crc8.update(&[0x02, 0x1C, 0xB8]);
+        crc8.update(&[0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(crc8.finish(), 0xA2);
+
+        let mut crc16 = Crc16::new();
+        crc16.update(b"1234");
+        crc16.update(b"56789");
+        assert_eq!(crc16.finish(), 0xBB3D);
+        assert!(crc16
+            .verify_inverted::<()>((!0xBB3Du16).to_le_bytes())
+            .is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+use core::marker::PhantomData;
+use hal1::delay::DelayNs;
+
+use crate::compute_partial_crc8;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// A device driver that can be constructed from the address of a found device, see
+/// [`OneWire::discover`]
+pub trait Discoverable: Sized {
+    /// Family codes of the devices supported by the driver
+    fn family_codes() -> &'static [u8];
+
+    /// Creates the driver for the given device, `None` if the device is not supported
+    fn from_device(device: Device) -> Option<Self>;
+}
+
+/// A driver that can operate the only device on a bus without knowing its address, by
+/// addressing it with Skip ROM instead of Match ROM.
+///
+/// Single sensor products can skip both the search and the addressing this way. It is
+/// only valid if there is exactly one device on the bus, otherwise all devices respond
+/// at once. The address of the created driver only holds the family code, use
+/// [`OneWire::read_rom`] to learn the actual address.
+pub trait SingleDrop {
+    /// Creates the driver for the only device on the bus
+    fn single_drop() -> Self;
+}
+
+/// Iterator over the devices of the families supported by `D`, created by
+/// [`OneWire::discover`]
+pub struct Discover<'a, D, ODO: OpenDrainOutput, Delay, T: TimingProfile, G: TimingGuard> {
+    wire: &'a mut OneWire<ODO, T, G>,
+    delay: &'a mut Delay,
+    families: &'static [u8],
+    search: Option<DeviceSearch>,
+    _device: PhantomData<D>,
+}
+
+impl<'a, D, ODO, Delay, T, G> Discover<'a, D, ODO, Delay, T, G>
+where
+    D: Discoverable,
+    ODO: OpenDrainOutput,
+    Delay: DelayNs,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    pub(crate) fn new(wire: &'a mut OneWire<ODO, T, G>, delay: &'a mut Delay) -> Self {
+        Discover {
+            wire,
+            delay,
+            families: D::family_codes(),
+            search: None,
+            _device: PhantomData,
+        }
+    }
+}
+
+impl<'a, D, ODO, Delay, T, G> Iterator for Discover<'a, D, ODO, Delay, T, G>
+where
+    D: Discoverable,
+    ODO: OpenDrainOutput,
+    Delay: DelayNs,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    type Item = Result<D, Error<ODO::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let search = match &mut self.search {
+                Some(search) => search,
+                None => {
+                    let (family, rest) = self.families.split_first()?;
+                    self.families = rest;
+                    self.search.insert(DeviceSearch::new_for_family(*family))
+                }
+            };
+            match self.wire.search_next(search, &mut *self.delay) {
+                Ok(Some(device)) if !device.is_crc_valid() => {
+                    let computed = compute_partial_crc8(0, &device.address[..7]);
+                    return Some(Err(Error::CrcMismatch(computed, device.address[7])));
+                }
+                Ok(Some(device)) => {
+                    if let Some(device) = D::from_device(device) {
+                        return Some(Ok(device));
+                    }
+                }
+                Ok(None) => self.search = None,
+                Err(e) => {
+                    self.search = None;
+                    self.families = &[];
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Addresses a device by its position among the devices of a family, for products that
+/// are configured by position like "the 3rd DS18B20" instead of by address.
+///
+/// The devices are ordered the way the ROM search finds them, which is by address with
+/// the bits compared starting at the least significant bit of the family code. The order
+/// only depends on the addresses, not on the wiring or the power up order. Since adding
+/// or removing a device shifts the positions, the number of devices of the family at
+/// configuration time is kept to detect stale positions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceIndex {
+    pub family: u8,
+    /// Zero based position of the device
+    pub index: usize,
+    /// Number of devices of the family on the bus when the position was configured
+    pub count: usize,
+}
+
+/// Result of [`OneWire::resolve_index`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedIndex {
+    Found(Device),
+    /// The number of devices of the family changed, so the position might refer to a
+    /// different device than configured. Contains the device currently at the position,
+    /// if any.
+    Stale {
+        count: usize,
+        device: Option<Device>,
+    },
+}
@@ -1,11 +1,24 @@
 use byteorder::ByteOrder;
 use byteorder::LittleEndian;
 use core::fmt::Debug;
-use hal::blocking::delay::DelayUs;
+#[cfg(feature = "async")]
+use embedded_hal_async::delay::DelayNs as AsyncDelayNs;
+use hal1::delay::DelayNs;
 
+#[cfg(feature = "async")]
+use crate::asynch::AsyncOneWire;
+
+use crate::discover::{Discoverable, SingleDrop};
+use crate::label::AsDevice;
+use crate::scratchpad::{ScratchpadCrc, ScratchpadDevice};
+use crate::sensor::AlarmSensor;
+#[cfg(feature = "async")]
+use crate::sensor::AsyncSensor;
 use crate::Error;
 use crate::OneWire;
 use crate::Sensor;
+use crate::TimingGuard;
+use crate::TimingProfile;
 use crate::{Device, OpenDrainOutput};
 use core::convert::Infallible;
 
@@ -31,6 +44,28 @@ pub enum MeasureResolution {
 }
 
 impl MeasureResolution {
+    /// Parses the configuration register (byte 4 of the scratchpad), only the resolution
+    /// bits R0 and R1 are considered
+    pub fn from_config_register(config: u8) -> Self {
+        match (config >> 5) & 0b11 {
+            0b00 => MeasureResolution::TC8,
+            0b01 => MeasureResolution::TC4,
+            0b10 => MeasureResolution::TC2,
+            _ => MeasureResolution::TC,
+        }
+    }
+
+    /// The value of the configuration register selecting this resolution
+    pub fn config_register(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Number of significant bits of the temperature, 9 to 12
+    pub fn bits(&self) -> u8 {
+        9 + ((*self as u8 >> 5) & 0b11)
+    }
+
+    /// Maximum conversion time in milliseconds
     pub fn time_ms(&self) -> u16 {
         match self {
             MeasureResolution::TC8 => 94,
@@ -44,6 +79,8 @@ impl MeasureResolution {
 pub struct DS18B20 {
     device: Device,
     resolution: MeasureResolution,
+    single_drop: bool,
+    offset: i16,
 }
 
 impl DS18B20 {
@@ -54,6 +91,8 @@ impl DS18B20 {
             Ok(DS18B20 {
                 device,
                 resolution: MeasureResolution::TC,
+                single_drop: false,
+                offset: 0,
             })
         }
     }
@@ -66,70 +105,353 @@ impl DS18B20 {
         DS18B20 {
             device,
             resolution: MeasureResolution::TC,
+            single_drop: false,
+            offset: 0,
+        }
+    }
+
+    /// Sets the calibration offset in 1/16 degrees celsius, which
+    /// [`Sensor::read_measurement`] adds to the measured temperature, e.g. to correct the
+    /// deviation found in an ice bath
+    pub fn with_offset(mut self, offset: i16) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn offset(&self) -> i16 {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: i16) {
+        self.offset = offset;
+    }
+
+    /// Persists the calibration offset in the EEPROM of the device, using the bytes of
+    /// the alarm thresholds. Returns the milliseconds required to wait until the EEPROM
+    /// is written.
+    ///
+    /// The offset is encoded as thresholds outside of the measurement range, TH at 126°C
+    /// and TL between -128°C and -56°C, so the sensor never meets the alarm condition
+    /// and stays out of the Alarm Search. This limits the stored offset to
+    /// ±[`MAX_STORED_OFFSET`].
+    ///
+    /// Returns `None` without writing anything if the offset is outside of that range or
+    /// the alarm is in use, so the thresholds aren't destroyed. Disable it with
+    /// [`AlarmSensor::clear_alarm`] first. A previously stored offset is replaced.
+    pub fn store_offset<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<Option<u16>, Error<O::Error>> {
+        let [high, low] = match encode_offset(self.offset) {
+            Some(thresholds) => thresholds,
+            None => return Ok(None),
+        };
+        let scratchpad = self.scratchpad(wire, delay)?;
+        let cleared = (scratchpad[2] as i8, scratchpad[3] as i8) == (i8::MAX, i8::MIN);
+        if !cleared && decode_offset(scratchpad[2], scratchpad[3]).is_none() {
+            return Ok(None);
+        }
+        self.write_scratchpad(wire, delay, &[high, low, scratchpad[4]])?;
+        self.copy_scratchpad(wire, delay).map(Some)
+    }
+
+    /// Loads the calibration offset stored with [`DS18B20::store_offset`]. Returns `None`
+    /// and keeps the current offset if the thresholds hold no stored offset, e.g. when
+    /// they are cleared, in use or still have their factory values.
+    pub fn load_offset<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<Option<i16>, Error<O::Error>> {
+        let scratchpad = self.scratchpad(wire, delay)?;
+        let offset = decode_offset(scratchpad[2], scratchpad[3]);
+        if let Some(offset) = offset {
+            self.offset = offset;
         }
+        Ok(offset)
     }
 
-    pub fn measure_temperature<O: OpenDrainOutput>(
+    pub fn measure_temperature<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
     ) -> Result<MeasureResolution, Error<O::Error>> {
-        wire.reset_select_write_only(delay, &self.device, &[Command::Convert as u8])?;
+        wire.reset_select_or_skip_write_only(delay, self.target(), &[Command::Convert as u8])?;
         Ok(self.resolution)
     }
 
-    pub fn read_temperature<O: OpenDrainOutput>(
+    pub fn read_temperature<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
     ) -> Result<u16, Error<O::Error>> {
-        let mut scratchpad = [0u8; 9];
-        wire.reset_select_write_read(
-            delay,
-            &self.device,
-            &[Command::ReadScratchpad as u8],
-            &mut scratchpad[..],
-        )?;
-        super::ensure_correct_rcr8(&self.device, &scratchpad[..8], scratchpad[8])?;
+        let scratchpad = self.scratchpad(wire, delay)?;
         Ok(DS18B20::read_temperature_from_scratchpad(&scratchpad))
     }
 
-    fn read_temperature_from_scratchpad(scratchpad: &[u8]) -> u16 {
+    fn scratchpad<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<[u8; 8], Error<O::Error>> {
+        let mut scratchpad = [0u8; 8];
+        self.read_scratchpad(wire, delay, &mut scratchpad)?;
+        Ok(scratchpad)
+    }
+
+    /// Writes the alarm thresholds into the scratchpad, the configuration register is
+    /// written back unchanged
+    fn write_config<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        high: i8,
+        low: i8,
+    ) -> Result<(), Error<O::Error>> {
+        let scratchpad = self.scratchpad(wire, delay)?;
+        self.write_scratchpad(wire, delay, &[high as u8, low as u8, scratchpad[4]])
+    }
+
+    /// Converts a raw temperature into degrees celsius, applying the calibration offset
+    pub(crate) fn to_celsius(&self, raw: u16) -> f32 {
+        (raw as i16).saturating_add(self.offset) as f32 / 16_f32
+    }
+
+    /// Extracts the raw temperature from a scratchpad read through other means
+    pub fn read_temperature_from_scratchpad(scratchpad: &[u8]) -> u16 {
         LittleEndian::read_u16(&scratchpad[0..2])
     }
 }
 
+impl AsDevice for DS18B20 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS18B20 {
+    fn single_drop() -> Self {
+        DS18B20 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            resolution: MeasureResolution::TC,
+            single_drop: true,
+            offset: 0,
+        }
+    }
+}
+
+impl Discoverable for DS18B20 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS18B20::new(device).ok()
+    }
+}
+
 impl Sensor for DS18B20 {
+    type Measurement = f32;
+
     fn family_code() -> u8 {
         FAMILY_CODE
     }
 
-    fn start_measurement<O: OpenDrainOutput>(
+    fn start_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
     ) -> Result<u16, Error<O::Error>> {
         Ok(self.measure_temperature(wire, delay)?.time_ms())
     }
 
-    fn read_measurement<O: OpenDrainOutput>(
+    fn start_measurement_all<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>> {
+        wire.reset_skip_write_only(delay, &[Command::Convert as u8])?;
+        // other sensors might be configured to use the highest resolution
+        Ok(MeasureResolution::TC.time_ms())
+    }
+
+    fn read_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
     ) -> Result<f32, Error<O::Error>> {
         self.read_temperature(wire, delay)
-            .map(|t| t as i16 as f32 / 16_f32)
+            .map(|t| self.to_celsius(t))
     }
 
-    fn read_measurement_raw<O: OpenDrainOutput>(
+    fn read_measurement_raw<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
     ) -> Result<u16, Error<O::Error>> {
         self.read_temperature(wire, delay)
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncSensor for DS18B20 {
+    async fn start_measurement_async<O, T, G, D>(
+        &self,
+        wire: &mut AsyncOneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<u16, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        let command = [Command::Convert as u8];
+        match self.target() {
+            Some(device) => {
+                wire.reset_select_write_only(delay, device, &command)
+                    .await?
+            }
+            None => wire.reset_skip_write_only(delay, &command).await?,
+        }
+        Ok(self.resolution.time_ms())
+    }
+
+    async fn read_measurement_async<O, T, G, D>(
+        &self,
+        wire: &mut AsyncOneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<f32, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        let mut scratchpad = [0u8; 8];
+        wire.read_scratchpad(self, delay, &mut scratchpad).await?;
+        Ok(self.to_celsius(DS18B20::read_temperature_from_scratchpad(&scratchpad)))
+    }
+}
+
+impl ScratchpadDevice for DS18B20 {
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+
+    fn scratchpad_len() -> usize {
+        8
+    }
+
+    fn scratchpad_crc() -> ScratchpadCrc {
+        ScratchpadCrc::Crc8
+    }
+
+    fn read_scratchpad_command() -> u8 {
+        Command::ReadScratchpad as u8
+    }
+
+    fn write_scratchpad_command() -> u8 {
+        Command::WriteScratchpad as u8
+    }
+
+    fn copy_scratchpad_command() -> u8 {
+        Command::CopyScratchpad as u8
+    }
+
+    fn copy_scratchpad_time_ms() -> u16 {
+        10
+    }
+}
+
+/// The thresholds are whole degrees celsius, the device is alarmed if the last measured
+/// temperature is lower than or equal to the low threshold or higher than or equal to the
+/// high threshold. The thresholds are only stored in the scratchpad and are lost on
+/// power loss.
+impl AlarmSensor for DS18B20 {
+    type Threshold = i8;
+
+    fn set_alarm_thresholds<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        low: i8,
+        high: i8,
+    ) -> Result<(), Error<O::Error>> {
+        self.write_config(wire, delay, high, low)
+    }
+
+    fn read_alarm_thresholds<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(i8, i8), Error<O::Error>> {
+        let scratchpad = self.scratchpad(wire, delay)?;
+        Ok((scratchpad[3] as i8, scratchpad[2] as i8))
+    }
+
+    fn is_alarmed<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<bool, Error<O::Error>> {
+        let scratchpad = self.scratchpad(wire, delay)?;
+        Ok(is_alarm_condition(&scratchpad))
+    }
+
+    fn clear_alarm<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<O::Error>> {
+        self.write_config(wire, delay, i8::MAX, i8::MIN)
+    }
+}
+
+/// The largest calibration offset in 1/16 degrees celsius that
+/// [`DS18B20::store_offset`] can persist
+pub const MAX_STORED_OFFSET: i16 = 36;
+
+/// TH of a stored offset, above the highest temperature the sensor measures
+const OFFSET_MARKER: u8 = 126;
+
+/// TL of a stored offset of zero, the range around it stays below -55°C
+const OFFSET_ZERO: i16 = -92;
+
+/// Encodes the offset as `[TH, TL]` that never meet the alarm condition
+fn encode_offset(offset: i16) -> Option<[u8; 2]> {
+    if offset.abs() > MAX_STORED_OFFSET {
+        return None;
+    }
+    Some([OFFSET_MARKER, (OFFSET_ZERO + offset) as i8 as u8])
+}
+
+fn decode_offset(high: u8, low: u8) -> Option<i16> {
+    let offset = i16::from(low as i8) - OFFSET_ZERO;
+    if high != OFFSET_MARKER || offset.abs() > MAX_STORED_OFFSET {
+        return None;
+    }
+    Some(offset)
+}
+
+/// Whether the temperature in the scratchpad meets the alarm condition. Like the device,
+/// this compares the whole degrees of bits 11 to 4, which are rounded towards negative
+/// infinity, against the thresholds.
+fn is_alarm_condition(scratchpad: &[u8; 8]) -> bool {
+    let degrees = DS18B20::read_temperature_from_scratchpad(scratchpad) as i16 >> 4;
+    degrees <= i16::from(scratchpad[3] as i8) || degrees >= i16::from(scratchpad[2] as i8)
+}
+
 /// Split raw u16 value to two parts: integer and fraction N
 /// Original value may be calculated as: integer + fraction/10000
 pub fn split_temp(temperature: u16) -> (i16, i16) {
@@ -143,7 +465,10 @@ pub fn split_temp(temperature: u16) -> (i16, i16) {
 
 #[cfg(test)]
 mod tests {
-    use super::split_temp;
+    use super::{
+        decode_offset, encode_offset, is_alarm_condition, split_temp, MeasureResolution,
+        MAX_STORED_OFFSET,
+    };
     #[test]
     fn test_temp_conv() {
         assert_eq!(split_temp(0x07d0), (125, 0));
@@ -157,4 +482,74 @@ mod tests {
         assert_eq!(split_temp(0xFE6F), (-25, -625)); // -25.0625
         assert_eq!(split_temp(0xFC90), (-55, 0)); // -55
     }
+
+    #[test]
+    fn test_alarm_condition() {
+        // -10.125 is compared as -11 by the device
+        let [lsb, msb] = 0xFF5Eu16.to_le_bytes();
+        assert!(is_alarm_condition(&[
+            lsb,
+            msb,
+            20,
+            -11i8 as u8,
+            0x7F,
+            0xFF,
+            0,
+            0
+        ]));
+        assert!(!is_alarm_condition(&[
+            lsb,
+            msb,
+            20,
+            -12i8 as u8,
+            0x7F,
+            0xFF,
+            0,
+            0
+        ]));
+        // 25.0625 is compared as 25
+        let [lsb, msb] = 0x0191u16.to_le_bytes();
+        assert!(is_alarm_condition(&[lsb, msb, 25, 0, 0x7F, 0xFF, 0, 0]));
+        assert!(!is_alarm_condition(&[lsb, msb, 26, 0, 0x7F, 0xFF, 0, 0]));
+    }
+
+    #[test]
+    fn test_offset_encoding() {
+        for offset in -MAX_STORED_OFFSET..=MAX_STORED_OFFSET {
+            let [high, low] = encode_offset(offset).unwrap();
+            assert_eq!(decode_offset(high, low), Some(offset));
+            // the sensor never alarms and never answers an Alarm Search
+            for temperature in [0x07D0u16, 0xFC90] {
+                let [lsb, msb] = temperature.to_le_bytes();
+                assert!(!is_alarm_condition(&[
+                    lsb, msb, high, low, 0x7F, 0xFF, 0, 0
+                ]));
+            }
+        }
+        assert_eq!(encode_offset(MAX_STORED_OFFSET + 1), None);
+        assert_eq!(encode_offset(-MAX_STORED_OFFSET - 1), None);
+        // cleared and factory thresholds
+        assert_eq!(decode_offset(0x7F, 0x80), None);
+        assert_eq!(decode_offset(0x4B, 0x46), None);
+    }
+
+    #[test]
+    fn test_config_register() {
+        for resolution in [
+            MeasureResolution::TC8,
+            MeasureResolution::TC4,
+            MeasureResolution::TC2,
+            MeasureResolution::TC,
+        ] {
+            let config = resolution.config_register();
+            assert_eq!(
+                MeasureResolution::from_config_register(config) as u8,
+                config
+            );
+        }
+        // the reserved bits are irrelevant
+        assert_eq!(MeasureResolution::from_config_register(0x40).bits(), 11);
+        assert_eq!(MeasureResolution::TC8.bits(), 9);
+        assert_eq!(MeasureResolution::TC.bits(), 12);
+    }
 }
@@ -0,0 +1,409 @@
+use hal1::delay::DelayNs;
+
+use crate::discover::{Discoverable, SingleDrop};
+use crate::eeprom::EepromDevice;
+use crate::label::AsDevice;
+use crate::memory::MemoryDevice;
+use crate::Device;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use core::convert::Infallible;
+
+pub const FAMILY_CODE: u8 = 0x21;
+
+#[repr(u8)]
+pub enum Command {
+    WriteScratchpad = 0x0F,
+    ReadScratchpad = 0xAA,
+    CopyScratchpad = 0x55,
+    ReadMemory = 0xF0,
+    ReadMemoryCrc = 0xA5,
+    ClearMemory = 0x3C,
+    ConvertTemperature = 0x44,
+}
+
+/// Target address of the real-time clock registers
+pub const RTC: u16 = 0x200;
+/// Target address of the low temperature alarm threshold, followed by the high one
+pub const TEMPERATURE_ALARMS: u16 = 0x20B;
+/// Target address of the sample rate register, writing a non-zero value starts a mission
+pub const SAMPLE_RATE: u16 = 0x20D;
+/// Target address of the control register
+pub const CONTROL: u16 = 0x20E;
+/// Target address of the mission start delay in minutes
+pub const MISSION_START_DELAY: u16 = 0x212;
+/// Target address of the status register
+pub const STATUS: u16 = 0x214;
+/// Target address of the mission samples counter
+pub const MISSION_SAMPLES: u16 = 0x21A;
+/// Target address of the temperature histogram
+pub const HISTOGRAM: u16 = 0x800;
+/// Target address of the temperature datalog
+pub const DATALOG: u16 = 0x1000;
+
+/// Number of bins of the temperature histogram
+pub const HISTOGRAM_BINS: usize = 63;
+/// Size of the datalog in samples
+pub const DATALOG_SIZE: u16 = 2048;
+
+const CONTROL_EOSC: u8 = 0x80;
+const CONTROL_EMCLR: u8 = 0x40;
+const CONTROL_RO: u8 = 0x08;
+const STATUS_MIP: u8 = 0x20;
+
+/// Date and time of the real-time clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    /// 1 to 7
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    fn from_registers(registers: &[u8; 7]) -> Self {
+        let century = if registers[5] & 0x80 != 0 { 2000 } else { 1900 };
+        DateTime {
+            second: from_bcd(registers[0] & 0x7F),
+            minute: from_bcd(registers[1] & 0x7F),
+            // the 12 hour mode is not supported, it's never set by this driver
+            hour: from_bcd(registers[2] & 0x3F),
+            weekday: registers[3] & 0x07,
+            day: from_bcd(registers[4] & 0x3F),
+            month: from_bcd(registers[5] & 0x1F),
+            year: century + u16::from(from_bcd(registers[6])),
+        }
+    }
+
+    fn to_registers(self) -> [u8; 7] {
+        let century = if self.year >= 2000 { 0x80 } else { 0x00 };
+        [
+            to_bcd(self.second),
+            to_bcd(self.minute),
+            to_bcd(self.hour),
+            self.weekday,
+            to_bcd(self.day),
+            to_bcd(self.month) | century,
+            to_bcd((self.year % 100) as u8),
+        ]
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Configuration of a mission, see [`DS1921::start_mission`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mission {
+    /// Minutes between two samples, 1 to 255
+    pub sample_rate_minutes: u8,
+    /// Minutes until the first sample
+    pub start_delay_minutes: u16,
+    /// Whether the datalog wraps around when full instead of stopping to log
+    pub rollover: bool,
+    /// Raw temperatures at or below which the low alarm is raised, see [`raw_to_celsius`]
+    pub low_alarm: u8,
+    /// Raw temperatures at or above which the high alarm is raised
+    pub high_alarm: u8,
+}
+
+/// Converts a raw temperature of the DS1921G, as stored in the datalog, into degrees
+/// celsius
+pub fn raw_to_celsius(raw: u8) -> f32 {
+    f32::from(raw) / 2.0 - 40.0
+}
+
+/// Thermochron temperature logger iButton
+pub struct DS1921 {
+    device: Device,
+    single_drop: bool,
+}
+
+impl DS1921 {
+    pub fn new(device: Device) -> Result<DS1921, Error<Infallible>> {
+        if device.address[0] != FAMILY_CODE {
+            Err(Error::FamilyCodeMismatch(FAMILY_CODE, device.address[0]))
+        } else {
+            Ok(DS1921 {
+                device,
+                single_drop: false,
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// This is marked as unsafe because it does not check whether the given address
+    /// is compatible with a DS1921 device. It assumes so.
+    pub unsafe fn new_forced(device: Device) -> DS1921 {
+        DS1921 {
+            device,
+            single_drop: false,
+        }
+    }
+
+    pub fn read_clock<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<DateTime, Error<O::Error>> {
+        let mut registers = [0u8; 7];
+        self.read_memory(wire, delay, RTC, &mut registers)?;
+        Ok(DateTime::from_registers(&registers))
+    }
+
+    /// Sets the real-time clock and starts its oscillator
+    pub fn set_clock<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        time: &DateTime,
+    ) -> Result<(), Error<O::Error>> {
+        self.write_memory(wire, delay, idle, RTC, &time.to_registers())?;
+        let control = self.read_register(wire, delay, CONTROL)?;
+        self.write_memory(wire, delay, idle, CONTROL, &[control & !CONTROL_EOSC])
+    }
+
+    pub fn read_status<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u8, Error<O::Error>> {
+        self.read_register(wire, delay, STATUS)
+    }
+
+    pub fn is_mission_in_progress<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<bool, Error<O::Error>> {
+        Ok(self.read_status(wire, delay)? & STATUS_MIP != 0)
+    }
+
+    /// Clears the data of the previous mission and starts a new one. The real-time clock
+    /// has to be running. Fails with [`Error::Debug`] if a mission is in progress.
+    pub fn start_mission<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        mission: &Mission,
+    ) -> Result<(), Error<O::Error>> {
+        let status = self.read_status(wire, delay)?;
+        if status & STATUS_MIP != 0 {
+            return Err(Error::Debug(Some(status)));
+        }
+
+        let mut control = self.read_register(wire, delay, CONTROL)? & !CONTROL_RO;
+        control |= CONTROL_EMCLR;
+        if mission.rollover {
+            control |= CONTROL_RO;
+        }
+        self.write_memory(wire, delay, idle, CONTROL, &[control])?;
+        wire.reset_select_or_skip_write_only(delay, self.target(), &[Command::ClearMemory as u8])?;
+        delay.delay_us(500);
+
+        self.write_memory(
+            wire,
+            delay,
+            idle,
+            TEMPERATURE_ALARMS,
+            &[mission.low_alarm, mission.high_alarm],
+        )?;
+        self.write_memory(
+            wire,
+            delay,
+            idle,
+            MISSION_START_DELAY,
+            &mission.start_delay_minutes.to_le_bytes(),
+        )?;
+        self.write_memory(
+            wire,
+            delay,
+            idle,
+            SAMPLE_RATE,
+            &[mission.sample_rate_minutes],
+        )
+    }
+
+    /// Stops the mission in progress
+    pub fn stop_mission<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+    ) -> Result<(), Error<O::Error>> {
+        let status = self.read_status(wire, delay)?;
+        self.write_memory(wire, delay, idle, STATUS, &[status & !STATUS_MIP])
+    }
+
+    /// Number of samples taken during the current or last mission
+    pub fn mission_samples<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u32, Error<O::Error>> {
+        let mut counter = [0u8; 4];
+        self.read_memory(wire, delay, MISSION_SAMPLES, &mut counter[..3])?;
+        Ok(u32::from_le_bytes(counter))
+    }
+
+    /// Reads the sample counts of the temperature histogram, validating the CRC16
+    pub fn read_histogram<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<[u16; HISTOGRAM_BINS], Error<O::Error>> {
+        let mut histogram = [0u16; HISTOGRAM_BINS];
+        let mut page = [0u8; 32];
+        self.read_memory_chunked(
+            wire,
+            delay,
+            HISTOGRAM,
+            (HISTOGRAM_BINS * 2) as u16,
+            &mut page,
+            |address, data| {
+                let first = usize::from(address - HISTOGRAM) / 2;
+                for (bin, bytes) in histogram[first..].iter_mut().zip(data.chunks_exact(2)) {
+                    *bin = u16::from_le_bytes([bytes[0], bytes[1]]);
+                }
+            },
+        )?;
+        Ok(histogram)
+    }
+
+    /// Downloads the first `len` samples of the datalog page by page, validating the
+    /// CRC16 of each page. `f` receives the index of the first sample and the raw samples,
+    /// see [`raw_to_celsius`].
+    pub fn read_datalog<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        len: u16,
+        mut f: impl FnMut(u16, &[u8]),
+    ) -> Result<(), Error<O::Error>> {
+        let mut page = [0u8; 32];
+        self.read_memory_chunked(
+            wire,
+            delay,
+            DATALOG,
+            len.min(DATALOG_SIZE),
+            &mut page,
+            |address, samples| f(address - DATALOG, samples),
+        )
+    }
+
+    fn read_register<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        address: u16,
+    ) -> Result<u8, Error<O::Error>> {
+        let mut register = [0u8; 1];
+        self.read_memory(wire, delay, address, &mut register)?;
+        Ok(register[0])
+    }
+}
+
+impl AsDevice for DS1921 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS1921 {
+    fn single_drop() -> Self {
+        DS1921 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            single_drop: true,
+        }
+    }
+}
+
+impl Discoverable for DS1921 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS1921::new(device).ok()
+    }
+}
+
+impl MemoryDevice for DS1921 {
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+
+    fn memory_size() -> u16 {
+        DATALOG + DATALOG_SIZE
+    }
+
+    fn page_size() -> u16 {
+        32
+    }
+
+    fn read_memory_command() -> u8 {
+        Command::ReadMemory as u8
+    }
+
+    fn read_memory_crc16_command() -> Option<u8> {
+        Some(Command::ReadMemoryCrc as u8)
+    }
+}
+
+/// The memory is static RAM, so copying the scratchpad takes no time
+impl EepromDevice for DS1921 {
+    fn scratchpad_size() -> u16 {
+        32
+    }
+
+    fn copy_time_ms() -> u16 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtc_registers() {
+        let time = DateTime {
+            year: 2024,
+            month: 12,
+            day: 31,
+            weekday: 2,
+            hour: 23,
+            minute: 59,
+            second: 58,
+        };
+        let registers = time.to_registers();
+        assert_eq!(registers, [0x58, 0x59, 0x23, 0x02, 0x31, 0x92, 0x24]);
+        assert_eq!(DateTime::from_registers(&registers), time);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::BusMaster;
+use crate::Error;
+use crate::OpenDrainOutput;
+
+/// Status register reads until the master gives up waiting for an operation
+const MAX_POLLS: u32 = 100_000;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    Command = 0,
+    Data = 1,
+    Interrupt = 2,
+    ClockDivisor = 4,
+    Control = 5,
+}
+
+const COMMAND_RESET: u8 = 0x01;
+
+const INTERRUPT_PRESENCE_DETECT: u8 = 0x01;
+/// Cleared if a presence pulse has been detected
+const INTERRUPT_PRESENCE_RESULT: u8 = 0x02;
+const INTERRUPT_RECEIVE_FULL: u8 = 0x10;
+const INTERRUPT_SHORT: u8 = 0x40;
+
+const CONTROL_STRONG_PULL_UP: u8 = 0x08;
+const CONTROL_STRONG_PULL_UP_SUPPLY: u8 = 0x10;
+const CONTROL_BIT_MODE: u8 = 0x20;
+const CONTROL_OVERDRIVE: u8 = 0x40;
+
+/// Access to the registers of a memory mapped peripheral
+pub trait Registers {
+    fn read_register(&mut self, offset: u8) -> u8;
+
+    fn write_register(&mut self, offset: u8, value: u8);
+}
+
+/// Registers mapped at a base address, each `stride` bytes apart
+#[derive(Debug)]
+pub struct Mmio {
+    base: *mut u8,
+    stride: usize,
+}
+
+impl Mmio {
+    /// # Safety
+    /// `base` must point to the registers of the peripheral, which must not be accessed
+    /// otherwise while this exists
+    pub unsafe fn new(base: *mut u8, stride: usize) -> Self {
+        Mmio { base, stride }
+    }
+}
+
+impl Registers for Mmio {
+    fn read_register(&mut self, offset: u8) -> u8 {
+        unsafe {
+            self.base
+                .add(usize::from(offset) * self.stride)
+                .read_volatile()
+        }
+    }
+
+    fn write_register(&mut self, offset: u8, value: u8) {
+        unsafe {
+            self.base
+                .add(usize::from(offset) * self.stride)
+                .write_volatile(value)
+        }
+    }
+}
+
+/// The master didn't finish an operation in time, e.g. because it is not clocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout;
+
+/// The DS1WM synthesizable 1-Wire master found in some SoCs and FPGA designs, which
+/// generates the resets and time slots in hardware. It is used as the pin of a
+/// [`OneWire`](crate::OneWire), which then forwards its transfers, see [`BusMaster`].
+///
+/// The interrupts of the master are not used, its interrupt register is polled.
+#[derive(Debug)]
+pub struct DS1WM<R> {
+    registers: R,
+}
+
+impl<R: Registers> DS1WM<R> {
+    pub fn new(registers: R) -> Self {
+        DS1WM { registers }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.registers
+    }
+
+    /// Sets the clock divisor register, which has to match the clock of the master as
+    /// listed in its datasheet before the bus can be used
+    pub fn set_clock_divisor(&mut self, divisor: u8) {
+        self.registers
+            .write_register(Register::ClockDivisor as u8, divisor);
+    }
+
+    fn update_control(&mut self, mask: u8, enabled: bool) {
+        let control = self.registers.read_register(Register::Control as u8);
+        let updated = if enabled {
+            control | mask
+        } else {
+            control & !mask
+        };
+        if updated != control {
+            self.registers
+                .write_register(Register::Control as u8, updated);
+        }
+    }
+
+    /// Polls the interrupt register until one of the flags is set, returns the register
+    fn wait_for(&mut self, flags: u8) -> Result<u8, Timeout> {
+        for _ in 0..MAX_POLLS {
+            let interrupt = self.registers.read_register(Register::Interrupt as u8);
+            if interrupt & flags != 0 {
+                return Ok(interrupt);
+            }
+        }
+        Err(Timeout)
+    }
+
+    /// Sends the byte, or a single bit in bit mode, and returns what has been received.
+    /// The line is actively powered afterwards with `strong_pull_up`.
+    fn transfer(&mut self, value: u8, strong_pull_up: bool) -> Result<u8, Timeout> {
+        self.update_control(
+            CONTROL_STRONG_PULL_UP | CONTROL_STRONG_PULL_UP_SUPPLY,
+            strong_pull_up,
+        );
+        self.registers.write_register(Register::Data as u8, value);
+        self.wait_for(INTERRUPT_RECEIVE_FULL)?;
+        Ok(self.registers.read_register(Register::Data as u8))
+    }
+
+    fn transfer_bit(&mut self, high: bool) -> Result<bool, Timeout> {
+        self.update_control(CONTROL_BIT_MODE, true);
+        let result = self.transfer(u8::from(high), false);
+        self.update_control(CONTROL_BIT_MODE, false);
+        Ok(result? & 0x01 == 0x01)
+    }
+}
+
+impl<R: Registers> BusMaster for DS1WM<R> {
+    type Error = Timeout;
+
+    fn reset(&mut self) -> Result<bool, Error<Self::Error>> {
+        // a strong pull-up of a previous write ends with the reset
+        self.update_control(
+            CONTROL_STRONG_PULL_UP | CONTROL_STRONG_PULL_UP_SUPPLY,
+            false,
+        );
+        self.registers
+            .write_register(Register::Command as u8, COMMAND_RESET);
+        let interrupt = self.wait_for(INTERRUPT_PRESENCE_DETECT)?;
+        if interrupt & INTERRUPT_SHORT != 0 {
+            return Err(Error::WireNotHigh);
+        }
+        Ok(interrupt & INTERRUPT_PRESENCE_RESULT == 0)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        self.transfer_bit(true)
+    }
+
+    fn write_bit(&mut self, high: bool) -> Result<(), Self::Error> {
+        self.transfer_bit(high)?;
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.transfer(0xFF, false)
+    }
+
+    fn write_byte(&mut self, byte: u8, strong_pull_up: bool) -> Result<(), Self::Error> {
+        self.transfer(byte, strong_pull_up)?;
+        Ok(())
+    }
+
+    fn supports_overdrive(&self) -> bool {
+        true
+    }
+
+    fn set_overdrive(&mut self, overdrive: bool) -> Result<(), Self::Error> {
+        self.update_control(CONTROL_OVERDRIVE, overdrive);
+        Ok(())
+    }
+}
+
+/// The master only acts as a bus master, the pin methods are not used by the bus
+impl<R: Registers> OpenDrainOutput for DS1WM<R> {
+    type Error = Timeout;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::NoDelay;
+    use crate::OneWire;
+
+    /// Simulates a master with a device answering the presence and echoing the slots
+    #[derive(Default)]
+    struct Simulated {
+        interrupt: u8,
+        control: u8,
+        data: u8,
+        strong_pull_ups: usize,
+    }
+
+    impl Registers for Simulated {
+        fn read_register(&mut self, offset: u8) -> u8 {
+            match offset {
+                1 => {
+                    self.interrupt &= !INTERRUPT_RECEIVE_FULL;
+                    self.data
+                }
+                2 => self.interrupt,
+                5 => self.control,
+                _ => unreachable!(),
+            }
+        }
+
+        fn write_register(&mut self, offset: u8, value: u8) {
+            match offset {
+                0 => self.interrupt = INTERRUPT_PRESENCE_DETECT,
+                1 => {
+                    self.data = value;
+                    self.interrupt |= INTERRUPT_RECEIVE_FULL;
+                    if self.control & CONTROL_STRONG_PULL_UP != 0 {
+                        self.strong_pull_ups += 1;
+                    }
+                }
+                5 => self.control = value,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_transfers_through_master() {
+        let mut wire = OneWire::new(DS1WM::new(Simulated::default()), true);
+        let mut delay = NoDelay;
+        assert!(wire.reset(&mut delay).unwrap());
+        let mut read = [0u8; 2];
+        wire.reset_select_or_skip_write_read(&mut delay, None, &[0x44], &mut read)
+            .unwrap();
+        assert_eq!(read, [0xFF, 0xFF]);
+        // skip rom and the command have been written in parasite mode
+        assert_eq!(wire.output().registers.strong_pull_ups, 2);
+    }
+}
@@ -0,0 +1,232 @@
+use hal1::delay::DelayNs;
+
+use crate::discover::{Discoverable, SingleDrop};
+use crate::label::AsDevice;
+use crate::memory::MemoryDevice;
+use crate::Error;
+use crate::OneWire;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use crate::{Device, OpenDrainOutput};
+use core::convert::Infallible;
+
+pub const FAMILY_CODE: u8 = 0x29;
+
+#[repr(u8)]
+pub enum Command {
+    ReadPioRegisters = 0xF0,
+    ChannelAccessRead = 0xF5,
+    ChannelAccessWrite = 0x5A,
+    WriteConditionalSearchRegister = 0xCC,
+    ResetActivityLatches = 0xC3,
+}
+
+/// Target address of the PIO logic state register, the first readable register
+pub const PIO_LOGIC_STATE: u16 = 0x88;
+/// Target address of the conditional search channel selection mask register
+pub const CONDITIONAL_SEARCH_MASK: u16 = 0x8B;
+/// Target address of the control/status register
+pub const CONTROL_STATUS: u16 = 0x8D;
+
+const CONTROL_PLS: u8 = 0x01;
+const CONTROL_CT: u8 = 0x02;
+const CONTROL_ROS: u8 = 0x04;
+const CONTROL_PORL: u8 = 0x08;
+
+/// What the conditional search of a [`DS2408`] compares against its polarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConditionalSource {
+    /// The current logic state of the pins
+    PinState,
+    /// The activity latches, which record any change of the pins since they were last
+    /// reset with [`DS2408::reset_activity_latches`]
+    ActivityLatch,
+}
+
+/// Configuration of the conditional search, see [`DS2408::set_conditional_search`].
+///
+/// A device responds to an alarm search if the selected channels match their polarity,
+/// either any of them or all of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConditionalSearch {
+    pub source: ConditionalSource,
+    /// Channels taking part in the conditional search, one bit per channel
+    pub mask: u8,
+    /// The state of each selected channel that matches, one bit per channel
+    pub polarity: u8,
+    /// Whether all selected channels need to match instead of any of them
+    pub all: bool,
+}
+
+impl ConditionalSearch {
+    /// Responds to any change of the selected channels
+    pub fn on_activity(mask: u8) -> Self {
+        ConditionalSearch {
+            source: ConditionalSource::ActivityLatch,
+            mask,
+            polarity: mask,
+            all: false,
+        }
+    }
+}
+
+/// 8-channel addressable switch, whose conditional search allows to detect input
+/// changes with an alarm search instead of polling every device
+pub struct DS2408 {
+    device: Device,
+    single_drop: bool,
+}
+
+impl DS2408 {
+    pub fn new(device: Device) -> Result<DS2408, Error<Infallible>> {
+        if device.address[0] != FAMILY_CODE {
+            Err(Error::FamilyCodeMismatch(FAMILY_CODE, device.address[0]))
+        } else {
+            Ok(DS2408 {
+                device,
+                single_drop: false,
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// This is marked as unsafe because it does not check whether the given address
+    /// is compatible with a DS2408 device. It assumes so.
+    pub unsafe fn new_forced(device: Device) -> DS2408 {
+        DS2408 {
+            device,
+            single_drop: false,
+        }
+    }
+
+    /// Reads the registers from the PIO logic state up to the control/status register
+    /// (0x88-0x8D) and validates their CRC16
+    pub fn read_registers<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<[u8; 6], Error<O::Error>> {
+        let mut registers = [0u8; 6];
+        let mut page = [0u8; 8];
+        self.read_memory_chunked(
+            wire,
+            delay,
+            PIO_LOGIC_STATE,
+            registers.len() as u16,
+            &mut page,
+            |_, data| registers.copy_from_slice(data),
+        )?;
+        Ok(registers)
+    }
+
+    /// Configures the conditional search. The reset pin mode and the power on reset
+    /// latch in the control/status register are preserved.
+    pub fn set_conditional_search<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        config: &ConditionalSearch,
+    ) -> Result<(), Error<O::Error>> {
+        let registers = self.read_registers(wire, delay)?;
+        // writing 0 to the power on reset latch would clear it
+        let mut control = (registers[5] & CONTROL_ROS) | CONTROL_PORL;
+        if config.source == ConditionalSource::ActivityLatch {
+            control |= CONTROL_PLS;
+        }
+        if config.all {
+            control |= CONTROL_CT;
+        }
+        let [low, high] = CONDITIONAL_SEARCH_MASK.to_le_bytes();
+        wire.reset_select_or_skip_write_only(
+            delay,
+            self.target(),
+            &[
+                Command::WriteConditionalSearchRegister as u8,
+                low,
+                high,
+                config.mask,
+                config.polarity,
+                control,
+            ],
+        )
+    }
+
+    /// Resets the activity latches of all channels, re-arming a conditional search on
+    /// [`ConditionalSource::ActivityLatch`]
+    pub fn reset_activity_latches<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<O::Error>> {
+        let mut confirmation = [0u8; 1];
+        wire.reset_select_or_skip_write_read(
+            delay,
+            self.target(),
+            &[Command::ResetActivityLatches as u8],
+            &mut confirmation,
+        )?;
+        if confirmation[0] != 0xAA {
+            return Err(Error::Debug(Some(confirmation[0])));
+        }
+        Ok(())
+    }
+}
+
+impl AsDevice for DS2408 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS2408 {
+    fn single_drop() -> Self {
+        DS2408 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            single_drop: true,
+        }
+    }
+}
+
+impl Discoverable for DS2408 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS2408::new(device).ok()
+    }
+}
+
+/// The registers are read with the Read PIO Registers command, which ends with a CRC16
+impl MemoryDevice for DS2408 {
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+
+    fn memory_size() -> u16 {
+        0x90
+    }
+
+    fn page_size() -> u16 {
+        8
+    }
+
+    fn read_memory_command() -> u8 {
+        Command::ReadPioRegisters as u8
+    }
+
+    fn read_memory_crc16_command() -> Option<u8> {
+        Some(Command::ReadPioRegisters as u8)
+    }
+}
@@ -0,0 +1,275 @@
+use hal1::delay::DelayNs;
+
+use crate::discover::{Discoverable, SingleDrop};
+use crate::label::AsDevice;
+use crate::Command as RomCommand;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use crate::{Device, OpenDrainOutput};
+use core::convert::Infallible;
+
+pub const FAMILY_CODE: u8 = 0x1F;
+
+#[repr(u8)]
+pub enum Command {
+    AllLinesOff = 0x66,
+    DischargeLines = 0x99,
+    DirectOnMain = 0xA5,
+    SmartOnMain = 0xCC,
+    SmartOnAuxiliary = 0x33,
+    StatusReadWrite = 0x5A,
+}
+
+/// Milliseconds the lines are discharged by [`DS2409::discharge_lines`]
+pub const DISCHARGE_TIME_MS: u16 = 100;
+
+/// The two outputs of a [`DS2409`], each connecting a branch of the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branch {
+    Main,
+    Auxiliary,
+}
+
+/// Clears the event flags of both branches when set in the control byte
+const CONTROL_CLEAR_EVENTS: u8 = 0x20;
+
+const INFO_MAIN_OFF: u8 = 0x01;
+const INFO_MAIN_LEVEL: u8 = 0x02;
+const INFO_AUXILIARY_OFF: u8 = 0x04;
+const INFO_AUXILIARY_LEVEL: u8 = 0x08;
+const INFO_MAIN_EVENT: u8 = 0x10;
+const INFO_AUXILIARY_EVENT: u8 = 0x20;
+
+/// What switches the control output of a [`DS2409`], e.g. an LED marking the connected
+/// branch or the supply of the branch
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlOutput {
+    /// On while the main branch is connected by a smart-on command, the default
+    AutoMain = 0x00,
+    /// On while the auxiliary branch is connected by a smart-on command
+    AutoAuxiliary = 0x40,
+    /// Switched off manually
+    Off = 0x80,
+    /// Switched on manually
+    On = 0xC0,
+}
+
+/// The info byte of a [`DS2409`], see [`DS2409::status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status(pub u8);
+
+impl Status {
+    /// Whether the branch is connected to the trunk
+    pub fn is_connected(&self, branch: Branch) -> bool {
+        let off = match branch {
+            Branch::Main => INFO_MAIN_OFF,
+            Branch::Auxiliary => INFO_AUXILIARY_OFF,
+        };
+        self.0 & off == 0
+    }
+
+    /// The level of the line of the branch, low while it is shorted or a device on it
+    /// pulls it low
+    pub fn level(&self, branch: Branch) -> bool {
+        let level = match branch {
+            Branch::Main => INFO_MAIN_LEVEL,
+            Branch::Auxiliary => INFO_AUXILIARY_LEVEL,
+        };
+        self.0 & level != 0
+    }
+
+    /// Whether a falling edge occurred on the disconnected branch since the event flags
+    /// were last cleared, e.g. a new device announcing itself with a presence pulse
+    pub fn event(&self, branch: Branch) -> bool {
+        let event = match branch {
+            Branch::Main => INFO_MAIN_EVENT,
+            Branch::Auxiliary => INFO_AUXILIARY_EVENT,
+        };
+        self.0 & event != 0
+    }
+}
+
+/// MicroLAN coupler, which connects one of two branches to the trunk of the network.
+///
+/// The devices behind a coupler are only visible while their branch is connected, which
+/// allows to segment large networks and to isolate misbehaving branches.
+pub struct DS2409 {
+    device: Device,
+    single_drop: bool,
+}
+
+impl DS2409 {
+    pub fn new(device: Device) -> Result<DS2409, Error<Infallible>> {
+        if device.address[0] != FAMILY_CODE {
+            Err(Error::FamilyCodeMismatch(FAMILY_CODE, device.address[0]))
+        } else {
+            Ok(DS2409 {
+                device,
+                single_drop: false,
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// This is marked as unsafe because it does not check whether the given address
+    /// is compatible with a DS2409 device. It assumes so.
+    pub unsafe fn new_forced(device: Device) -> DS2409 {
+        DS2409 {
+            device,
+            single_drop: false,
+        }
+    }
+
+    /// Disconnects both branches
+    pub fn all_lines_off<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<O::Error>> {
+        self.confirmed_command(wire, delay, Command::AllLinesOff as u8)
+    }
+
+    /// Disconnects and discharges both branches, which resets parasitically powered
+    /// devices stuck on them, then turns the discharge off again
+    pub fn discharge_lines<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+    ) -> Result<(), Error<O::Error>> {
+        self.confirmed_command(wire, delay, Command::DischargeLines as u8)?;
+        idle.idle(DISCHARGE_TIME_MS, delay);
+        self.all_lines_off(wire, delay)
+    }
+
+    /// Connects the main branch without resetting it
+    pub fn direct_on_main<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<O::Error>> {
+        self.confirmed_command(wire, delay, Command::DirectOnMain as u8)
+    }
+
+    /// Connects the given branch after resetting it. Returns whether any device on the
+    /// branch answered the reset with a presence pulse.
+    pub fn smart_on<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        branch: Branch,
+    ) -> Result<bool, Error<O::Error>> {
+        let command = match branch {
+            Branch::Main => Command::SmartOnMain,
+            Branch::Auxiliary => Command::SmartOnAuxiliary,
+        } as u8;
+        // the first byte is the reset stimulus, during which the branch is reset and a
+        // presence pulse pulls the read slots low
+        let mut response = [0u8; 2];
+        wire.reset_select_or_skip_write_read(delay, self.target(), &[command], &mut response)?;
+        if response[1] != command {
+            return Err(Error::Debug(Some(response[1])));
+        }
+        Ok(response[0] != 0xFF)
+    }
+
+    /// Configures the control output, optionally clears the event flags and reads the
+    /// info byte afterwards
+    pub fn status<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        control_output: ControlOutput,
+        clear_events: bool,
+    ) -> Result<Status, Error<O::Error>> {
+        let mut control = control_output as u8;
+        if clear_events {
+            control |= CONTROL_CLEAR_EVENTS;
+        }
+        // the info byte is repeated for as long as it is read
+        let mut info = [0u8; 2];
+        wire.reset_select_or_skip_write_read(
+            delay,
+            self.target(),
+            &[Command::StatusReadWrite as u8, control],
+            &mut info,
+        )?;
+        if info[0] != info[1] {
+            return Err(Error::Debug(Some(info[1])));
+        }
+        Ok(Status(info[0]))
+    }
+
+    /// Searches the devices on the given branch only, without the devices on the trunk.
+    /// Each pass connects the branch like [`DS2409::smart_on`] and leaves it connected.
+    pub fn search_branch<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        branch: Branch,
+        search: &mut DeviceSearch,
+    ) -> Result<Option<Device>, Error<O::Error>> {
+        wire.transaction(|wire| {
+            // the devices on the trunk wait for a reset after the coupler has been
+            // selected, so only the freshly reset branch takes part in the search
+            wire.search_with(search, delay, RomCommand::SearchNext, |wire, delay| {
+                self.smart_on(wire, delay, branch)
+            })
+        })
+    }
+
+    fn confirmed_command<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        command: u8,
+    ) -> Result<(), Error<O::Error>> {
+        let mut confirmation = [0u8; 1];
+        wire.reset_select_or_skip_write_read(delay, self.target(), &[command], &mut confirmation)?;
+        if confirmation[0] != command {
+            return Err(Error::Debug(Some(confirmation[0])));
+        }
+        Ok(())
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+}
+
+impl AsDevice for DS2409 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS2409 {
+    fn single_drop() -> Self {
+        DS2409 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            single_drop: true,
+        }
+    }
+}
+
+impl Discoverable for DS2409 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS2409::new(device).ok()
+    }
+}
@@ -0,0 +1,109 @@
+use crate::discover::{Discoverable, SingleDrop};
+use crate::eeprom::EepromDevice;
+use crate::label::AsDevice;
+use crate::memory::MemoryDevice;
+use crate::Device;
+use crate::Error;
+use core::convert::Infallible;
+
+pub const FAMILY_CODE: u8 = 0x2D;
+
+/// Size of the user EEPROM in bytes, followed by the protection registers
+pub const EEPROM_SIZE: u16 = 128;
+
+/// 1024-bit EEPROM, written in rows of 8 bytes
+pub struct DS2431 {
+    device: Device,
+    single_drop: bool,
+}
+
+impl DS2431 {
+    pub fn new(device: Device) -> Result<DS2431, Error<Infallible>> {
+        if device.address[0] != FAMILY_CODE {
+            Err(Error::FamilyCodeMismatch(FAMILY_CODE, device.address[0]))
+        } else {
+            Ok(DS2431 {
+                device,
+                single_drop: false,
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// This is marked as unsafe because it does not check whether the given address
+    /// is compatible with a DS2431 device. It assumes so.
+    pub unsafe fn new_forced(device: Device) -> DS2431 {
+        DS2431 {
+            device,
+            single_drop: false,
+        }
+    }
+}
+
+impl AsDevice for DS2431 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS2431 {
+    fn single_drop() -> Self {
+        DS2431 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            single_drop: true,
+        }
+    }
+}
+
+impl Discoverable for DS2431 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS2431::new(device).ok()
+    }
+}
+
+impl MemoryDevice for DS2431 {
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+
+    fn memory_size() -> u16 {
+        0x90
+    }
+
+    fn page_size() -> u16 {
+        32
+    }
+}
+
+impl EepromDevice for DS2431 {
+    fn scratchpad_size() -> u16 {
+        8
+    }
+
+    fn full_scratchpad_only() -> bool {
+        true
+    }
+
+    fn scratchpad_crc16() -> bool {
+        true
+    }
+
+    fn copy_time_ms() -> u16 {
+        10
+    }
+}
@@ -0,0 +1,101 @@
+use crate::discover::{Discoverable, SingleDrop};
+use crate::eeprom::EepromDevice;
+use crate::label::AsDevice;
+use crate::memory::MemoryDevice;
+use crate::Device;
+use crate::Error;
+use core::convert::Infallible;
+
+pub const FAMILY_CODE: u8 = 0x23;
+
+/// Size of the EEPROM in bytes
+pub const EEPROM_SIZE: u16 = 512;
+
+/// 4096-bit EEPROM, written in pages of 32 bytes
+pub struct DS2433 {
+    device: Device,
+    single_drop: bool,
+}
+
+impl DS2433 {
+    pub fn new(device: Device) -> Result<DS2433, Error<Infallible>> {
+        if device.address[0] != FAMILY_CODE {
+            Err(Error::FamilyCodeMismatch(FAMILY_CODE, device.address[0]))
+        } else {
+            Ok(DS2433 {
+                device,
+                single_drop: false,
+            })
+        }
+    }
+
+    /// # Safety
+    ///
+    /// This is marked as unsafe because it does not check whether the given address
+    /// is compatible with a DS2433 device. It assumes so.
+    pub unsafe fn new_forced(device: Device) -> DS2433 {
+        DS2433 {
+            device,
+            single_drop: false,
+        }
+    }
+}
+
+impl AsDevice for DS2433 {
+    fn as_device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl SingleDrop for DS2433 {
+    fn single_drop() -> Self {
+        DS2433 {
+            device: Device {
+                address: [FAMILY_CODE, 0, 0, 0, 0, 0, 0, 0],
+            },
+            single_drop: true,
+        }
+    }
+}
+
+impl Discoverable for DS2433 {
+    fn family_codes() -> &'static [u8] {
+        &[FAMILY_CODE]
+    }
+
+    fn from_device(device: Device) -> Option<Self> {
+        DS2433::new(device).ok()
+    }
+}
+
+impl MemoryDevice for DS2433 {
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn target(&self) -> Option<&Device> {
+        if self.single_drop {
+            None
+        } else {
+            Some(&self.device)
+        }
+    }
+
+    fn memory_size() -> u16 {
+        EEPROM_SIZE
+    }
+
+    fn page_size() -> u16 {
+        32
+    }
+}
+
+impl EepromDevice for DS2433 {
+    fn scratchpad_size() -> u16 {
+        32
+    }
+
+    fn copy_time_ms() -> u16 {
+        5
+    }
+}
@@ -0,0 +1,466 @@
+use core::fmt::Debug;
+
+use hal::blocking::i2c::{Read, Write};
+
+use hal1::delay::DelayNs;
+
+use crate::BusMaster;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+use crate::Triplet;
+
+/// I2C address with both address pins low, each pin adds its bit
+pub const DEFAULT_ADDRESS: u8 = 0x18;
+
+/// Number of channels of the DS2482-800
+pub const CHANNELS: usize = 8;
+
+/// Channel Select codes of each channel and the values read back after selecting it
+const CHANNEL_CODES: [(u8, u8); CHANNELS] = [
+    (0xF0, 0xB8),
+    (0xE1, 0xB1),
+    (0xD2, 0xAA),
+    (0xC3, 0xA3),
+    (0xB4, 0x9C),
+    (0xA5, 0x95),
+    (0x96, 0x8E),
+    (0x87, 0x87),
+];
+
+/// Status register reads until the bridge gives up on a busy bus, far more than the
+/// slowest operation needs at 400kHz
+const MAX_POLLS: u16 = 1000;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    DeviceReset = 0xF0,
+    SetReadPointer = 0xE1,
+    WriteConfiguration = 0xD2,
+    ChannelSelect = 0xC3,
+    OneWireReset = 0xB4,
+    OneWireSingleBit = 0x87,
+    OneWireWriteByte = 0xA5,
+    OneWireReadByte = 0x96,
+    OneWireTriplet = 0x78,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Register {
+    Status = 0xF0,
+    ReadData = 0xE1,
+    /// Only available on the DS2484
+    PortConfiguration = 0xB4,
+}
+
+const STATUS_BUSY: u8 = 0x01;
+const STATUS_PRESENCE: u8 = 0x02;
+const STATUS_SHORT: u8 = 0x04;
+const STATUS_DEVICE_RESET: u8 = 0x10;
+const STATUS_SINGLE_BIT: u8 = 0x20;
+const STATUS_TRIPLET_SECOND_BIT: u8 = 0x40;
+const STATUS_BRANCH_DIRECTION: u8 = 0x80;
+
+const CONFIG_ACTIVE_PULL_UP: u8 = 0x01;
+const CONFIG_STRONG_PULL_UP: u8 = 0x04;
+const CONFIG_OVERDRIVE: u8 = 0x08;
+
+#[derive(Debug)]
+pub enum BridgeError<E> {
+    I2c(E),
+    /// The bridge has still been busy after polling its status
+    Busy,
+    /// An unexpected status or configuration has been read back
+    Unexpected(u8),
+    /// The channel is not below [`CHANNELS`]
+    InvalidChannel(u8),
+    /// The port parameter value of a DS2484 is not below 16, see
+    /// [`DS2484::adjust_port`](crate::DS2484::adjust_port)
+    InvalidPortValue(u8),
+}
+
+impl<E> From<E> for BridgeError<E> {
+    fn from(e: E) -> Self {
+        BridgeError::I2c(e)
+    }
+}
+
+/// The DS2482-100 and DS2482-800 I2C to 1-Wire bridges, which generate the resets and
+/// time slots in hardware. It is used as the pin of a [`OneWire`], which then forwards
+/// its transfers to the bridge, see [`BusMaster`]. The eight channels of the DS2482-800
+/// are switched with [`DS2482::set_channel`].
+///
+/// The bridge is not reset on creation, call [`DS2482::reset_device`] after power up.
+#[derive(Debug)]
+pub struct DS2482<I2C> {
+    i2c: I2C,
+    address: u8,
+    config: u8,
+    channel: u8,
+}
+
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> DS2482<I2C> {
+    /// Uses the bridge at the given 7 bit I2C address, see [`DEFAULT_ADDRESS`]
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        DS2482 {
+            i2c,
+            address,
+            config: 0,
+            channel: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+
+    /// Resets the bridge and the 1-Wire bus, which also clears the configuration and
+    /// selects the first channel
+    pub fn reset_device(&mut self) -> Result<(), BridgeError<E>> {
+        self.i2c
+            .write(self.address, &[Command::DeviceReset as u8])?;
+        let status = self.read_status()?;
+        if status & STATUS_DEVICE_RESET == 0 {
+            return Err(BridgeError::Unexpected(status));
+        }
+        self.config = 0;
+        self.channel = 0;
+        Ok(())
+    }
+
+    /// Connects the given channel of a DS2482-800 to the bridge, the following resets
+    /// and transfers only reach the devices of that channel. Fails with
+    /// [`BridgeError::InvalidChannel`] if the channel is not below [`CHANNELS`].
+    pub fn set_channel(&mut self, channel: u8) -> Result<(), BridgeError<E>> {
+        let (code, read_back) = *CHANNEL_CODES
+            .get(usize::from(channel))
+            .ok_or(BridgeError::InvalidChannel(channel))?;
+        self.i2c
+            .write(self.address, &[Command::ChannelSelect as u8, code])?;
+        // the read pointer now points to the channel selection register
+        let value = self.read_status()?;
+        if value != read_back {
+            return Err(BridgeError::Unexpected(value));
+        }
+        self.channel = channel;
+        Ok(())
+    }
+
+    /// The currently selected channel, always 0 on the DS2482-100
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Actively drives the line high during the recovery of the time slots instead of
+    /// relying on the pull-up resistor only, for long or heavily loaded buses
+    pub fn set_active_pull_up(&mut self, enabled: bool) -> Result<(), BridgeError<E>> {
+        self.update_config(CONFIG_ACTIVE_PULL_UP, enabled)
+    }
+
+    fn update_config(&mut self, mask: u8, enabled: bool) -> Result<(), BridgeError<E>> {
+        let config = if enabled {
+            self.config | mask
+        } else {
+            self.config & !mask
+        };
+        if config != self.config {
+            self.write_config(config)?;
+            self.config = config;
+        }
+        Ok(())
+    }
+
+    /// Reads the status register
+    pub fn status(&mut self) -> Result<u8, BridgeError<E>> {
+        self.set_read_pointer(Register::Status)?;
+        Ok(self.read_status()?)
+    }
+
+    fn write_config(&mut self, config: u8) -> Result<(), BridgeError<E>> {
+        // the upper nibble is the complement of the lower one to protect against glitches
+        self.i2c.write(
+            self.address,
+            &[Command::WriteConfiguration as u8, config | (!config << 4)],
+        )?;
+        // the read pointer now points to the configuration register
+        let read_back = self.read_status()?;
+        if read_back != config {
+            return Err(BridgeError::Unexpected(read_back));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_read_pointer(&mut self, register: Register) -> Result<(), E> {
+        self.i2c.write(
+            self.address,
+            &[Command::SetReadPointer as u8, register as u8],
+        )
+    }
+
+    /// Sends a command that doesn't involve the 1-Wire bus
+    pub(crate) fn write(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.i2c.write(self.address, bytes)
+    }
+
+    /// Reads from the register the read pointer points to
+    pub(crate) fn read(&mut self, buf: &mut [u8]) -> Result<(), E> {
+        self.i2c.read(self.address, buf)
+    }
+
+    /// Reads the register the read pointer points to
+    fn read_status(&mut self) -> Result<u8, E> {
+        let mut buf = [0u8; 1];
+        self.i2c.read(self.address, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Sends a 1-Wire command and waits until the bridge finished it, returns the status
+    fn run(&mut self, command: &[u8]) -> Result<u8, BridgeError<E>> {
+        self.i2c.write(self.address, command)?;
+        for _ in 0..MAX_POLLS {
+            let status = self.read_status()?;
+            if status & STATUS_BUSY == 0 {
+                return Ok(status);
+            }
+        }
+        Err(BridgeError::Busy)
+    }
+}
+
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> BusMaster for DS2482<I2C> {
+    type Error = BridgeError<E>;
+
+    fn reset(&mut self) -> Result<bool, Error<Self::Error>> {
+        let status = self.run(&[Command::OneWireReset as u8])?;
+        if status & STATUS_SHORT != 0 {
+            return Err(Error::WireNotHigh);
+        }
+        Ok(status & STATUS_PRESENCE != 0)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        let status = self.run(&[Command::OneWireSingleBit as u8, 0x80])?;
+        Ok(status & STATUS_SINGLE_BIT != 0)
+    }
+
+    fn write_bit(&mut self, high: bool) -> Result<(), Self::Error> {
+        let bit = if high { 0x80 } else { 0x00 };
+        self.run(&[Command::OneWireSingleBit as u8, bit])?;
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.run(&[Command::OneWireReadByte as u8])?;
+        self.set_read_pointer(Register::ReadData)?;
+        Ok(self.read_status()?)
+    }
+
+    fn write_byte(&mut self, byte: u8, strong_pull_up: bool) -> Result<(), Self::Error> {
+        if strong_pull_up {
+            // cleared by the bridge once the next transfer ends the strong pull-up
+            self.write_config(self.config | CONFIG_STRONG_PULL_UP)?;
+        }
+        self.run(&[Command::OneWireWriteByte as u8, byte])?;
+        Ok(())
+    }
+
+    fn triplet(&mut self, direction: bool) -> Result<Triplet, Self::Error> {
+        let direction = if direction { 0x80 } else { 0x00 };
+        let status = self.run(&[Command::OneWireTriplet as u8, direction])?;
+        Ok(Triplet {
+            bit: status & STATUS_SINGLE_BIT != 0,
+            complement: status & STATUS_TRIPLET_SECOND_BIT != 0,
+            direction: status & STATUS_BRANCH_DIRECTION != 0,
+        })
+    }
+
+    fn supports_overdrive(&self) -> bool {
+        true
+    }
+
+    fn set_overdrive(&mut self, overdrive: bool) -> Result<(), Self::Error> {
+        self.update_config(CONFIG_OVERDRIVE, overdrive)
+    }
+}
+
+/// The bridge only acts as a bus master, the pin methods are not used by the bus
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> OpenDrainOutput for DS2482<I2C> {
+    type Error = BridgeError<E>;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        Some(self)
+    }
+}
+
+/// The search state of each channel of a DS2482-800, so the device strings of the
+/// channels can be enumerated independently and interleaved with other transfers
+#[derive(Clone, Default)]
+pub struct ChannelSearch {
+    searches: [DeviceSearch; CHANNELS],
+}
+
+impl ChannelSearch {
+    pub fn new() -> Self {
+        ChannelSearch::default()
+    }
+
+    /// Selects the channel if necessary and finds its next device
+    pub fn search_next<E: Debug, I2C, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<DS2482<I2C>, T, G>,
+        delay: &mut impl DelayNs,
+        channel: u8,
+    ) -> Result<Option<Device>, Error<BridgeError<E>>>
+    where
+        I2C: Write<Error = E> + Read<Error = E>,
+    {
+        if wire.output().channel() != channel {
+            wire.output_mut().set_channel(channel)?;
+        }
+        wire.search_next(&mut self.searches[usize::from(channel)], delay)
+    }
+
+    /// Starts the search of the given channel over, channels not below [`CHANNELS`] are
+    /// ignored since they have no search to restart
+    pub fn restart(&mut self, channel: u8) {
+        if let Some(search) = self.searches.get_mut(usize::from(channel)) {
+            *search = DeviceSearch::new();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::NoDelay;
+    use core::convert::Infallible;
+
+    /// Simulates a bridge with a single device answering every read with 0x42
+    #[derive(Default)]
+    struct Bridge {
+        pointer: u8,
+        status: u8,
+        config: u8,
+        channel: u8,
+        written: [u8; 4],
+        written_len: usize,
+    }
+
+    impl Write for Bridge {
+        type Error = Infallible;
+
+        fn write(&mut self, _address: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            self.pointer = Register::Status as u8;
+            match bytes {
+                [0xE1, register] => self.pointer = *register,
+                [0xD2, config] => {
+                    assert_eq!(config >> 4, !config & 0x0F);
+                    self.config = config & 0x0F;
+                    self.pointer = 0xC3;
+                }
+                [0xC3, code] => {
+                    let channel = CHANNEL_CODES.iter().position(|(c, _)| c == code);
+                    self.channel = CHANNEL_CODES[channel.unwrap()].1;
+                    self.pointer = 0xD2;
+                }
+                [0xB4] => self.status = STATUS_PRESENCE,
+                [0x96] => self.status = 0,
+                [0xA5, byte] => {
+                    self.written[self.written_len] = *byte;
+                    self.written_len += 1;
+                    self.status = 0;
+                }
+                _ => panic!("unexpected command {:?}", bytes),
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for Bridge {
+        type Error = Infallible;
+
+        fn read(&mut self, _address: u8, buffer: &mut [u8]) -> Result<(), Infallible> {
+            buffer[0] = match self.pointer {
+                0xF0 => self.status,
+                0xE1 => 0x42,
+                0xC3 => self.config,
+                0xD2 => self.channel,
+                _ => unreachable!(),
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_transfers_through_bridge() {
+        let mut wire = OneWire::new(DS2482::new(Bridge::default(), DEFAULT_ADDRESS), true);
+        let mut delay = NoDelay;
+        let mut read = [0u8; 2];
+        wire.reset_select_or_skip_write_read(&mut delay, None, &[0xBE], &mut read)
+            .unwrap();
+        assert_eq!(read, [0x42, 0x42]);
+
+        let bridge = wire.output();
+        assert_eq!(&bridge.i2c.written[..bridge.i2c.written_len], &[0xCC, 0xBE]);
+        // the strong pull-up has been requested for the parasite powered bus
+        assert_eq!(bridge.i2c.config, CONFIG_STRONG_PULL_UP);
+    }
+
+    #[test]
+    fn test_set_channel() {
+        let mut bridge = DS2482::new(Bridge::default(), DEFAULT_ADDRESS);
+        bridge.set_channel(5).unwrap();
+        assert_eq!(bridge.channel(), 5);
+        assert_eq!(bridge.i2c.channel, 0x95);
+        assert!(matches!(
+            bridge.set_channel(8),
+            Err(BridgeError::InvalidChannel(8))
+        ));
+        assert_eq!(bridge.channel(), 5);
+    }
+
+    #[test]
+    fn test_channel_search_invalid_channel() {
+        let mut wire = OneWire::new(DS2482::new(Bridge::default(), DEFAULT_ADDRESS), false);
+        let mut searches = ChannelSearch::new();
+        searches.restart(8);
+        assert!(matches!(
+            searches.search_next(&mut wire, &mut NoDelay, 8),
+            Err(Error::PortError(BridgeError::InvalidChannel(8)))
+        ));
+    }
+
+    #[test]
+    fn test_overdrive() {
+        let mut wire = OneWire::new(DS2482::new(Bridge::default(), DEFAULT_ADDRESS), false);
+        let mut delay = NoDelay;
+        assert!(wire.enter_overdrive(&mut delay).unwrap());
+        assert_eq!(wire.output().i2c.config, 0);
+        wire.write_bytes(&mut delay, &[0xCC]).unwrap();
+        assert_eq!(wire.output().i2c.config, CONFIG_OVERDRIVE);
+        assert!(wire.exit_overdrive(&mut delay).unwrap());
+        assert_eq!(wire.output().i2c.config, 0);
+    }
+}
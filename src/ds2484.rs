@@ -0,0 +1,240 @@
+use core::fmt::Debug;
+
+use hal::blocking::i2c::{Read, Write};
+
+use crate::ds2482::Register;
+use crate::BridgeError;
+use crate::BusMaster;
+use crate::Error;
+use crate::OpenDrainOutput;
+use crate::Triplet;
+use crate::DS2482;
+
+/// I2C address of the DS2484, which has no address pins
+pub const ADDRESS: u8 = 0x18;
+
+const ADJUST_PORT: u8 = 0xC3;
+
+/// A timing parameter of the 1-Wire port, see the datasheet for the duration or
+/// resistance each of the 16 values stands for
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortParameter {
+    /// tRSTL, the duration of the reset pulse
+    ResetLow = 0b000,
+    /// tMSP, when the presence pulse is sampled
+    PresenceSample = 0b001,
+    /// tW0L, how long the line is held low for writing a 0
+    WriteZeroLow = 0b010,
+    /// tREC0, the recovery time after a write zero slot, the same for both speeds
+    Recovery = 0b011,
+    /// RWPU, the weak pull-up resistor, the same for both speeds
+    WeakPullUp = 0b100,
+}
+
+impl PortParameter {
+    /// Position of the value in the port configuration register
+    fn index(self, overdrive: bool) -> usize {
+        match self {
+            PortParameter::ResetLow
+            | PortParameter::PresenceSample
+            | PortParameter::WriteZeroLow => self as usize * 2 + usize::from(overdrive),
+            PortParameter::Recovery => 6,
+            PortParameter::WeakPullUp => 7,
+        }
+    }
+}
+
+/// The DS2484 single channel I2C to 1-Wire bridge, the successor of the DS2482-100 with
+/// an adjustable port timing to tune long or heavily loaded buses. Otherwise it is used
+/// like the [`DS2482`], as the pin of a [`OneWire`](crate::OneWire).
+#[derive(Debug)]
+pub struct DS2484<I2C> {
+    bridge: DS2482<I2C>,
+}
+
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> DS2484<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        DS2484 {
+            bridge: DS2482::new(i2c, ADDRESS),
+        }
+    }
+
+    pub fn into_inner(self) -> I2C {
+        self.bridge.into_inner()
+    }
+
+    /// Resets the bridge and the 1-Wire bus, which also restores the default port
+    /// configuration
+    pub fn reset_device(&mut self) -> Result<(), BridgeError<E>> {
+        self.bridge.reset_device()
+    }
+
+    /// See [`DS2482::set_active_pull_up`]
+    pub fn set_active_pull_up(&mut self, enabled: bool) -> Result<(), BridgeError<E>> {
+        self.bridge.set_active_pull_up(enabled)
+    }
+
+    /// Reads the status register
+    pub fn status(&mut self) -> Result<u8, BridgeError<E>> {
+        self.bridge.status()
+    }
+
+    /// Sets a timing parameter of the 1-Wire port at standard or overdrive speed to a
+    /// value of 0 to 15. Fails with [`BridgeError::InvalidPortValue`] if the value is out
+    /// of range.
+    pub fn adjust_port(
+        &mut self,
+        parameter: PortParameter,
+        overdrive: bool,
+        value: u8,
+    ) -> Result<(), BridgeError<E>> {
+        if value >= 16 {
+            return Err(BridgeError::InvalidPortValue(value));
+        }
+        let speed = if overdrive { 0x10 } else { 0x00 };
+        self.bridge
+            .write(&[ADJUST_PORT, (parameter as u8) << 5 | speed | value])?;
+        // the read pointer now points to the port configuration register
+        let config = self.read_port_config_at_pointer()?;
+        let read_back = config[parameter.index(overdrive)];
+        if read_back != value {
+            return Err(BridgeError::Unexpected(read_back));
+        }
+        Ok(())
+    }
+
+    /// Reads the values of all timing parameters: tRSTL, tMSP and tW0L at standard and
+    /// overdrive speed each, followed by tREC0 and RWPU
+    pub fn read_port_config(&mut self) -> Result<[u8; 8], BridgeError<E>> {
+        self.bridge.set_read_pointer(Register::PortConfiguration)?;
+        self.read_port_config_at_pointer()
+    }
+
+    fn read_port_config_at_pointer(&mut self) -> Result<[u8; 8], BridgeError<E>> {
+        let mut config = [0u8; 8];
+        self.bridge.read(&mut config)?;
+        for value in &mut config {
+            // the upper nibble is undefined
+            *value &= 0x0F;
+        }
+        Ok(config)
+    }
+}
+
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> BusMaster for DS2484<I2C> {
+    type Error = BridgeError<E>;
+
+    fn reset(&mut self) -> Result<bool, Error<Self::Error>> {
+        self.bridge.reset()
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        self.bridge.read_bit()
+    }
+
+    fn write_bit(&mut self, high: bool) -> Result<(), Self::Error> {
+        self.bridge.write_bit(high)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        self.bridge.read_byte()
+    }
+
+    fn write_byte(&mut self, byte: u8, strong_pull_up: bool) -> Result<(), Self::Error> {
+        self.bridge.write_byte(byte, strong_pull_up)
+    }
+
+    fn triplet(&mut self, direction: bool) -> Result<Triplet, Self::Error> {
+        self.bridge.triplet(direction)
+    }
+
+    fn supports_overdrive(&self) -> bool {
+        true
+    }
+
+    fn set_overdrive(&mut self, overdrive: bool) -> Result<(), Self::Error> {
+        self.bridge.set_overdrive(overdrive)
+    }
+}
+
+/// The bridge only acts as a bus master, the pin methods are not used by the bus
+impl<E: Debug, I2C: Write<Error = E> + Read<Error = E>> OpenDrainOutput for DS2484<I2C> {
+    type Error = BridgeError<E>;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    /// Simulates the port configuration register of a bridge
+    #[derive(Default)]
+    struct Port {
+        config: [u8; 8],
+    }
+
+    impl Write for Port {
+        type Error = Infallible;
+
+        fn write(&mut self, _address: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            if let [ADJUST_PORT, parameter] = bytes {
+                let index = match parameter >> 4 {
+                    selector if selector < 6 => usize::from(selector),
+                    6 | 7 => 6,
+                    _ => 7,
+                };
+                self.config[index] = parameter & 0x0F;
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for Port {
+        type Error = Infallible;
+
+        fn read(&mut self, _address: u8, buffer: &mut [u8]) -> Result<(), Infallible> {
+            // the undefined upper nibble is set
+            for (dst, value) in buffer.iter_mut().zip(&self.config) {
+                *dst = value | 0xF0;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_adjust_port() {
+        let mut bridge = DS2484::new(Port::default());
+        bridge
+            .adjust_port(PortParameter::WriteZeroLow, true, 9)
+            .unwrap();
+        bridge
+            .adjust_port(PortParameter::WeakPullUp, false, 3)
+            .unwrap();
+        assert_eq!(bridge.read_port_config().unwrap(), [0, 0, 0, 0, 0, 9, 0, 3]);
+        assert!(matches!(
+            bridge.adjust_port(PortParameter::ResetLow, false, 16),
+            Err(BridgeError::InvalidPortValue(16))
+        ));
+    }
+}
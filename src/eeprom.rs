@@ -0,0 +1,139 @@
+use hal1::delay::DelayNs;
+
+use crate::crc::Crc16;
+use crate::Error;
+use crate::Idle;
+use crate::MemoryDevice;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Largest scratchpad supported by [`EepromDevice::write_memory`]
+const MAX_SCRATCHPAD_SIZE: usize = 32;
+
+/// An EEPROM written through a scratchpad with a target address, like the DS2431 or the
+/// DS2433: the data is written into the scratchpad, read back to verify it and then
+/// copied into the memory.
+pub trait EepromDevice: MemoryDevice {
+    /// Size of the scratchpad in bytes, at most 32. Writes are split at its boundaries.
+    fn scratchpad_size() -> u16;
+
+    /// Whether the scratchpad can only be copied as a whole, in which case partial writes
+    /// read the remaining bytes from the memory first
+    fn full_scratchpad_only() -> bool {
+        false
+    }
+
+    /// Whether Read Scratchpad ends with an inverted CRC16 over the command and the
+    /// returned bytes
+    fn scratchpad_crc16() -> bool {
+        false
+    }
+
+    /// Milliseconds required to wait until copying the scratchpad finished
+    fn copy_time_ms() -> u16;
+
+    fn write_scratchpad_command() -> u8 {
+        0x0F
+    }
+
+    fn read_scratchpad_command() -> u8 {
+        0xAA
+    }
+
+    fn copy_scratchpad_command() -> u8 {
+        0x55
+    }
+
+    /// Writes `data` starting at the given target address, scratchpad by scratchpad. The
+    /// idle hook is called while each scratchpad is copied. Panics if the write exceeds
+    /// the memory. Verification failures are reported as [`Error::Debug`] with the E/S
+    /// byte or the copy status.
+    fn write_memory<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        address: u16,
+        data: &[u8],
+    ) -> Result<(), Error<O::Error>> {
+        let size = usize::from(Self::scratchpad_size());
+        assert!(size <= MAX_SCRATCHPAD_SIZE);
+        assert!(usize::from(address) + data.len() <= usize::from(Self::memory_size()));
+
+        let mut written = 0;
+        while written < data.len() {
+            let target = usize::from(address) + written;
+            let offset = target % size;
+            let len = (size - offset).min(data.len() - written);
+            let mut buf = [0u8; MAX_SCRATCHPAD_SIZE];
+            let (target, bytes) = if Self::full_scratchpad_only() && len < size {
+                let start = target - offset;
+                self.read_memory(wire, delay, start as u16, &mut buf[..size])?;
+                buf[offset..offset + len].copy_from_slice(&data[written..written + len]);
+                (start, &buf[..size])
+            } else {
+                buf[..len].copy_from_slice(&data[written..written + len]);
+                (target, &buf[..len])
+            };
+            self.write_scratchpad_and_copy(wire, delay, idle, target as u16, bytes)?;
+            written += len;
+        }
+        Ok(())
+    }
+
+    /// Writes the given bytes into the scratchpad, verifies them and copies them into
+    /// the memory
+    fn write_scratchpad_and_copy<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        address: u16,
+        bytes: &[u8],
+    ) -> Result<(), Error<O::Error>> {
+        let [low, high] = address.to_le_bytes();
+        wire.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, self.target())?;
+            wire.write_bytes(delay, &[Self::write_scratchpad_command(), low, high])?;
+            wire.write_bytes(delay, bytes)?;
+            Ok(())
+        })?;
+
+        let mut check = [0u8; 3 + MAX_SCRATCHPAD_SIZE + 2];
+        let len = 3 + bytes.len();
+        let crc_len = if Self::scratchpad_crc16() { 2 } else { 0 };
+        wire.reset_select_or_skip_write_read(
+            delay,
+            self.target(),
+            &[Self::read_scratchpad_command()],
+            &mut check[..len + crc_len],
+        )?;
+        if Self::scratchpad_crc16() {
+            let mut crc = Crc16::new();
+            crc.update(&[Self::read_scratchpad_command()]);
+            crc.update(&check[..len]);
+            wire.stats
+                .check_crc(crc.verify_inverted([check[len], check[len + 1]]))?;
+        }
+        let status = check[2];
+        if check[..2] != [low, high] || &check[3..len] != bytes {
+            return Err(Error::Debug(Some(status)));
+        }
+
+        wire.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, self.target())?;
+            wire.write_bytes(delay, &[Self::copy_scratchpad_command(), low, high, status])?;
+            idle.idle(Self::copy_time_ms(), delay);
+            let mut result = [0u8; 1];
+            wire.read_bytes(delay, &mut result)?;
+            if result[0] != 0xAA {
+                return Err(Error::Debug(Some(result[0])));
+            }
+            Ok(())
+        })
+    }
+}
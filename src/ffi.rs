@@ -0,0 +1,459 @@
+use core::convert::Infallible;
+use core::ffi::c_void;
+use hal1::delay::DelayNs;
+
+use crate::BusStats;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::SearchState;
+use crate::Sensor;
+use crate::Spin;
+use crate::{Device, DS18B20};
+
+pub const ONEWIRE_OK: i32 = 0;
+pub const ONEWIRE_ERR_WIRE_NOT_HIGH: i32 = -1;
+pub const ONEWIRE_ERR_CRC: i32 = -2;
+pub const ONEWIRE_ERR_FAMILY_CODE: i32 = -3;
+pub const ONEWIRE_ERR_COLLISION: i32 = -4;
+pub const ONEWIRE_ERR_OTHER: i32 = -5;
+pub const ONEWIRE_ERR_NULL: i32 = -6;
+
+/// A bus as seen from C: the pin and the delay are provided as callbacks, which receive
+/// the `context` pointer. It only describes the bus, the driver is set up from it with
+/// [`onewire_init`].
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OneWireBus {
+    pub context: *mut c_void,
+    /// Drives the line low
+    pub set_low: Option<extern "C" fn(context: *mut c_void)>,
+    /// Releases the line, which is pulled high by the pull-up
+    pub set_high: Option<extern "C" fn(context: *mut c_void)>,
+    /// Reads the level of the line
+    pub is_high: Option<extern "C" fn(context: *mut c_void) -> bool>,
+    pub delay_us: Option<extern "C" fn(context: *mut c_void, us: u16)>,
+    /// Whether the line is kept powered after writes for parasite powered devices
+    pub parasite_mode: bool,
+}
+
+/// The callbacks of a [`OneWireBus`], checked to be set
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OneWireCallbacks {
+    context: *mut c_void,
+    set_low: extern "C" fn(context: *mut c_void),
+    set_high: extern "C" fn(context: *mut c_void),
+    is_high: extern "C" fn(context: *mut c_void) -> bool,
+    delay_us: extern "C" fn(context: *mut c_void, us: u16),
+}
+
+/// The driver of a bus, which keeps the speed and the statistics of the bus between the
+/// calls. It is owned by the C side, which sets it up with [`onewire_init`], has to keep
+/// it alive and must not use it from several threads or interrupts at once. The fields
+/// are private to the driver.
+#[repr(C)]
+pub struct OneWireDriver {
+    callbacks: OneWireCallbacks,
+    parasite_mode: bool,
+    overdrive: bool,
+    stats: BusStats,
+}
+
+/// The state of a search, set it up with [`onewire_search_init`]. The fields are private
+/// to the search.
+#[repr(C)]
+pub struct OneWireSearch {
+    address: [u8; 8],
+    discrepancies: [u8; 8],
+    state: u8,
+    fixed_bits: u8,
+}
+
+impl OneWireSearch {
+    fn from_search(search: &DeviceSearch) -> Self {
+        OneWireSearch {
+            address: search.address,
+            discrepancies: search.discrepancies,
+            state: search.state as u8,
+            fixed_bits: search.fixed_bits,
+        }
+    }
+
+    fn to_search(&self) -> DeviceSearch {
+        DeviceSearch {
+            address: self.address,
+            discrepancies: self.discrepancies,
+            state: match self.state {
+                0 => SearchState::Initialized,
+                1 => SearchState::DeviceFound,
+                _ => SearchState::End,
+            },
+            fixed_bits: self.fixed_bits,
+        }
+    }
+}
+
+struct Pin(OneWireCallbacks);
+
+impl OpenDrainOutput for Pin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok((self.0.is_high)(self.0.context))
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!(self.0.is_high)(self.0.context))
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        (self.0.set_low)(self.0.context);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        (self.0.set_high)(self.0.context);
+        Ok(())
+    }
+}
+
+struct Delay(OneWireCallbacks);
+
+/// The callback takes whole microseconds, the durations are rounded up
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        let mut us = ns.div_ceil(1000);
+        while us > u32::from(u16::MAX) {
+            (self.0.delay_us)(self.0.context, u16::MAX);
+            us -= u32::from(u16::MAX);
+        }
+        (self.0.delay_us)(self.0.context, us as u16)
+    }
+}
+
+fn error_code(error: Error<Infallible>) -> i32 {
+    match error {
+        Error::WireNotHigh => ONEWIRE_ERR_WIRE_NOT_HIGH,
+        Error::CrcMismatch(..) | Error::Crc16Mismatch(..) => ONEWIRE_ERR_CRC,
+        Error::FamilyCodeMismatch(..) => ONEWIRE_ERR_FAMILY_CODE,
+        Error::Collision => ONEWIRE_ERR_COLLISION,
+        _ => ONEWIRE_ERR_OTHER,
+    }
+}
+
+/// Runs `f` with the bus of the driver and keeps its speed and statistics afterwards
+unsafe fn with_driver(
+    driver: *mut OneWireDriver,
+    f: impl FnOnce(&mut OneWire<Pin>, &mut Delay) -> Result<i32, Error<Infallible>>,
+) -> i32 {
+    let driver = match driver.as_mut() {
+        Some(driver) => driver,
+        None => return ONEWIRE_ERR_NULL,
+    };
+    let mut wire = OneWire::new(Pin(driver.callbacks), driver.parasite_mode);
+    wire.set_overdrive(driver.overdrive);
+    let result = f(&mut wire, &mut Delay(driver.callbacks)).unwrap_or_else(error_code);
+    driver.overdrive = wire.is_overdrive();
+    let stats = wire.take_stats();
+    driver.stats.add(&stats);
+    result
+}
+
+/// Sets up the driver for the given bus. Returns [`ONEWIRE_ERR_NULL`] if a callback of
+/// the bus is missing.
+///
+/// # Safety
+///
+/// `driver` has to point to a writable [`OneWireDriver`] and `bus` to a valid
+/// [`OneWireBus`].
+#[no_mangle]
+pub unsafe extern "C" fn onewire_init(driver: *mut OneWireDriver, bus: *const OneWireBus) -> i32 {
+    let bus = match bus.as_ref() {
+        Some(bus) => bus,
+        None => return ONEWIRE_ERR_NULL,
+    };
+    let callbacks = match (bus.set_low, bus.set_high, bus.is_high, bus.delay_us) {
+        (Some(set_low), Some(set_high), Some(is_high), Some(delay_us)) => OneWireCallbacks {
+            context: bus.context,
+            set_low,
+            set_high,
+            is_high,
+            delay_us,
+        },
+        _ => return ONEWIRE_ERR_NULL,
+    };
+    if driver.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    driver.write(OneWireDriver {
+        callbacks,
+        parasite_mode: bus.parasite_mode,
+        overdrive: false,
+        stats: BusStats::default(),
+    });
+    ONEWIRE_OK
+}
+
+/// Copies the statistics of the bus to `stats` and resets them, see [`BusStats`]
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`] and `stats` has to be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_take_stats(
+    driver: *mut OneWireDriver,
+    stats: *mut BusStats,
+) -> i32 {
+    let driver = match driver.as_mut() {
+        Some(driver) => driver,
+        None => return ONEWIRE_ERR_NULL,
+    };
+    if stats.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    stats.write(core::mem::take(&mut driver.stats));
+    ONEWIRE_OK
+}
+
+/// Resets the bus. Returns 1 if a device answered with a presence pulse, 0 if none did or
+/// a negative error code.
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`].
+#[no_mangle]
+pub unsafe extern "C" fn onewire_reset(driver: *mut OneWireDriver) -> i32 {
+    with_driver(driver, |wire, delay| Ok(i32::from(wire.reset(delay)?)))
+}
+
+/// Resets the bus and switches it and all devices capable of it to overdrive speed, see
+/// [`OneWire::enter_overdrive`]. Returns 1 if a device answered with a presence pulse, 0
+/// if none did or a negative error code.
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`].
+#[no_mangle]
+pub unsafe extern "C" fn onewire_enter_overdrive(driver: *mut OneWireDriver) -> i32 {
+    with_driver(driver, |wire, delay| {
+        Ok(i32::from(wire.enter_overdrive(delay)?))
+    })
+}
+
+/// Resets the bus at standard speed, which returns all devices to it. Returns 1 if a
+/// device answered with a presence pulse, 0 if none did or a negative error code.
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`].
+#[no_mangle]
+pub unsafe extern "C" fn onewire_exit_overdrive(driver: *mut OneWireDriver) -> i32 {
+    with_driver(driver, |wire, delay| {
+        Ok(i32::from(wire.exit_overdrive(delay)?))
+    })
+}
+
+/// Writes `len` bytes to the bus without a reset or addressing
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`] and `src` to `len`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_write_bytes(
+    driver: *mut OneWireDriver,
+    src: *const u8,
+    len: usize,
+) -> i32 {
+    if src.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    let src = core::slice::from_raw_parts(src, len);
+    with_driver(driver, |wire, delay| {
+        wire.write_bytes(delay, src)?;
+        Ok(ONEWIRE_OK)
+    })
+}
+
+/// Reads `len` bytes from the bus without a reset or addressing
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`] and `dst` to `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_read_bytes(
+    driver: *mut OneWireDriver,
+    dst: *mut u8,
+    len: usize,
+) -> i32 {
+    if dst.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    let dst = core::slice::from_raw_parts_mut(dst, len);
+    with_driver(driver, |wire, delay| {
+        wire.read_bytes(delay, dst)?;
+        Ok(ONEWIRE_OK)
+    })
+}
+
+/// Resets the bus and addresses the device with the given 8 byte address, or all devices
+/// with Skip ROM if `address` is null
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`] and `address` to 8
+/// readable bytes or be null.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_reset_select(
+    driver: *mut OneWireDriver,
+    address: *const u8,
+) -> i32 {
+    let device = address.as_ref().map(|_| read_device(address));
+    with_driver(driver, |wire, delay| {
+        wire.reset(delay)?;
+        wire.select_or_skip(delay, device.as_ref())?;
+        Ok(ONEWIRE_OK)
+    })
+}
+
+/// Prepares a search over all devices on the bus
+///
+/// # Safety
+///
+/// `search` has to point to a writable [`OneWireSearch`].
+#[no_mangle]
+pub unsafe extern "C" fn onewire_search_init(search: *mut OneWireSearch) -> i32 {
+    if search.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    search.write(OneWireSearch::from_search(&DeviceSearch::new()));
+    ONEWIRE_OK
+}
+
+/// Finds the next device and writes its 8 byte address. Returns 1 if a device has been
+/// found, 0 if the search is complete or a negative error code.
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`], `search` to a
+/// [`OneWireSearch`] initialized by [`onewire_search_init`] and `address` to 8 writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_search_next(
+    driver: *mut OneWireDriver,
+    search: *mut OneWireSearch,
+    address: *mut u8,
+) -> i32 {
+    let search = match search.as_mut() {
+        Some(search) => search,
+        None => return ONEWIRE_ERR_NULL,
+    };
+    if address.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    let mut state = search.to_search();
+    let result = with_driver(driver, |wire, delay| {
+        match wire.search_next(&mut state, delay)? {
+            Some(device) => {
+                core::ptr::copy_nonoverlapping(device.address.as_ptr(), address, 8);
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    });
+    *search = OneWireSearch::from_search(&state);
+    result
+}
+
+/// Measures the temperature of the DS18B20 with the given 8 byte address and writes it in
+/// 1/16 °C. Blocks with the delay callback until the conversion finished.
+///
+/// # Safety
+///
+/// `driver` has to point to a driver set up by [`onewire_init`], `address` to 8 readable
+/// bytes and `temperature` has to be writable.
+#[no_mangle]
+pub unsafe extern "C" fn onewire_ds18b20_measure(
+    driver: *mut OneWireDriver,
+    address: *const u8,
+    temperature: *mut i16,
+) -> i32 {
+    if address.is_null() || temperature.is_null() {
+        return ONEWIRE_ERR_NULL;
+    }
+    let sensor = match DS18B20::new(read_device(address)) {
+        Ok(sensor) => sensor,
+        Err(e) => return error_code(e),
+    };
+    with_driver(driver, |wire, delay| {
+        let wait_ms = sensor.start_measurement(wire, delay)?;
+        Spin.idle(wait_ms, delay);
+        temperature.write(sensor.read_measurement_raw(wire, delay)? as i16);
+        Ok(ONEWIRE_OK)
+    })
+}
+
+unsafe fn read_device(address: *const u8) -> Device {
+    let mut device = Device { address: [0u8; 8] };
+    core::ptr::copy_nonoverlapping(address, device.address.as_mut_ptr(), 8);
+    device
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn released(_context: *mut c_void) {}
+
+    extern "C" fn idle(_context: *mut c_void) -> bool {
+        true
+    }
+
+    extern "C" fn no_delay(_context: *mut c_void, _us: u16) {}
+
+    fn idle_bus() -> OneWireBus {
+        OneWireBus {
+            context: core::ptr::null_mut(),
+            set_low: Some(released),
+            set_high: Some(released),
+            is_high: Some(idle),
+            delay_us: Some(no_delay),
+            parasite_mode: false,
+        }
+    }
+
+    #[test]
+    fn test_missing_callback() {
+        let mut driver = core::mem::MaybeUninit::<OneWireDriver>::uninit();
+        let bus = OneWireBus {
+            delay_us: None,
+            ..idle_bus()
+        };
+        assert_eq!(
+            unsafe { onewire_init(driver.as_mut_ptr(), &bus) },
+            ONEWIRE_ERR_NULL
+        );
+    }
+
+    #[test]
+    fn test_driver_keeps_stats() {
+        let mut driver = core::mem::MaybeUninit::<OneWireDriver>::uninit();
+        let mut stats = BusStats::default();
+        unsafe {
+            assert_eq!(onewire_init(driver.as_mut_ptr(), &idle_bus()), ONEWIRE_OK);
+            assert_eq!(onewire_reset(driver.as_mut_ptr()), 0);
+            assert_eq!(onewire_reset(driver.as_mut_ptr()), 0);
+            assert_eq!(
+                onewire_take_stats(driver.as_mut_ptr(), &mut stats),
+                ONEWIRE_OK
+            );
+        }
+        assert_eq!(stats.resets, 2);
+        assert_eq!(stats.presence_failures, 2);
+    }
+}
@@ -0,0 +1,156 @@
+use hal1::delay::DelayNs;
+
+use crate::Device;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Interval between two Read ROM attempts of [`wait_for_key`]
+pub const KEY_POLL_INTERVAL_MS: u32 = 10;
+
+/// Number of consecutive identical reads [`wait_for_key`] requires to accept a key
+pub const KEY_CONFIRM_READS: u8 = 3;
+
+/// Waits for a key, e.g. an iButton fob, to touch the probe on the bus and returns its
+/// address, `None` if no key has been confirmed within `timeout_ms`.
+///
+/// The contact bounces while the key is pressed against the probe, which makes Read ROM
+/// return missing presence pulses and corrupt addresses. Failed CRC checks and shorted
+/// lines are therefore treated like an absent key, and an address is only accepted after
+/// [`KEY_CONFIRM_READS`] consecutive identical reads.
+pub fn wait_for_key<O, T, G>(
+    wire: &mut OneWire<O, T, G>,
+    delay: &mut impl DelayNs,
+    timeout_ms: u32,
+) -> Result<Option<Device>, Error<O::Error>>
+where
+    O: OpenDrainOutput,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    let mut candidate: Option<Device> = None;
+    let mut reads = 0;
+    let mut elapsed_ms = 0;
+    loop {
+        let device = match wire.read_rom(delay) {
+            Ok(device) => device,
+            Err(Error::CrcMismatch(..)) | Err(Error::WireNotHigh) => None,
+            Err(e) => return Err(e),
+        };
+        match device {
+            Some(device) if candidate.as_ref() == Some(&device) => reads += 1,
+            Some(device) => {
+                candidate = Some(device);
+                reads = 1;
+            }
+            None => {
+                candidate = None;
+                reads = 0;
+            }
+        }
+        if reads >= KEY_CONFIRM_READS {
+            return Ok(candidate);
+        }
+        if elapsed_ms >= timeout_ms {
+            return Ok(None);
+        }
+        delay.delay_ms(KEY_POLL_INTERVAL_MS);
+        elapsed_ms += KEY_POLL_INTERVAL_MS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_partial_crc8;
+    use crate::testing::NoDelay;
+    use crate::{BusMaster, MasterPin};
+    use core::convert::Infallible;
+
+    /// Answers each Read ROM with the next scripted address, `None` for no presence
+    struct Probe<'a> {
+        script: &'a [Option<[u8; 8]>],
+        address: Option<[u8; 8]>,
+        read: usize,
+    }
+
+    impl BusMaster for Probe<'_> {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+            let (address, rest) = self.script.split_first().unwrap_or((&None, &[]));
+            self.script = rest;
+            self.address = *address;
+            self.read = 0;
+            Ok(address.is_some())
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            Ok(true)
+        }
+
+        fn write_bit(&mut self, _high: bool) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn read_byte(&mut self) -> Result<u8, Infallible> {
+            let byte = self.address.map_or(0xFF, |address| address[self.read % 8]);
+            self.read += 1;
+            Ok(byte)
+        }
+
+        fn write_byte(&mut self, byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+            assert_eq!(byte, 0x33);
+            Ok(())
+        }
+    }
+
+    fn key(serial: u8) -> [u8; 8] {
+        let mut address = [0x01, serial, 0, 0, 0, 0, 0, 0];
+        address[7] = compute_partial_crc8(0, &address[..7]);
+        address
+    }
+
+    #[test]
+    fn test_bouncing_key() {
+        let mut corrupt = key(1);
+        corrupt[1] ^= 0x40;
+        let script = [
+            None,
+            Some(corrupt),
+            Some(key(1)),
+            None,
+            Some(key(1)),
+            Some(key(1)),
+            Some(key(1)),
+        ];
+        let mut wire = OneWire::new(
+            MasterPin(Probe {
+                script: &script,
+                address: None,
+                read: 0,
+            }),
+            false,
+        );
+        let device = wait_for_key(&mut wire, &mut NoDelay, 1000).unwrap();
+        assert_eq!(device, Some(Device { address: key(1) }));
+        assert!(wire.output.0.script.is_empty());
+    }
+
+    #[test]
+    fn test_no_key() {
+        let script = [Some(key(1)), Some(key(2)), Some(key(1))];
+        let mut wire = OneWire::new(
+            MasterPin(Probe {
+                script: &script,
+                address: None,
+                read: 0,
+            }),
+            false,
+        );
+        let device = wait_for_key(&mut wire, &mut NoDelay, 50).unwrap();
+        assert_eq!(device, None);
+    }
+}
@@ -0,0 +1,103 @@
+use core::fmt::{Display, Formatter};
+use core::ops::{Deref, DerefMut};
+
+use crate::Device;
+
+/// Gives access to the address of a device or of the driver wrapping it
+pub trait AsDevice {
+    fn as_device(&self) -> &Device;
+}
+
+impl AsDevice for Device {
+    fn as_device(&self) -> &Device {
+        self
+    }
+}
+
+/// Returns the name of the part with the given family code, if known
+pub fn family_name(family: u8) -> Option<&'static str> {
+    Some(match family {
+        0x01 => "DS2401",
+        0x10 => "DS18S20",
+        0x12 => "DS2406",
+        0x1D => "DS2423",
+        0x1F => "DS2409",
+        0x20 => "DS2450",
+        0x21 => "DS1921",
+        0x22 => "DS1822",
+        0x23 => "DS2433",
+        0x26 => "DS2438",
+        0x28 => "DS18B20",
+        0x29 => "DS2408",
+        0x2D => "DS2431",
+        0x3A => "DS2413",
+        0x43 => "DS28EC20",
+        _ => return None,
+    })
+}
+
+/// A device or driver together with a label, so higher layers don't need to keep names
+/// for their devices separately.
+///
+/// Without a label, the name of the part is used, see [`family_name`]. It dereferences to
+/// the wrapped device and displays as its name followed by its address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Labeled<D> {
+    device: D,
+    label: Option<&'static str>,
+}
+
+impl<D: AsDevice> Labeled<D> {
+    pub fn new(device: D) -> Self {
+        Labeled {
+            device,
+            label: None,
+        }
+    }
+
+    pub fn with_label(device: D, label: &'static str) -> Self {
+        Labeled {
+            device,
+            label: Some(label),
+        }
+    }
+
+    /// Returns the label, or the name of the part if there is none
+    pub fn name(&self) -> &'static str {
+        self.label
+            .or_else(|| family_name(self.device.as_device().family_code()))
+            .unwrap_or("unknown")
+    }
+
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    pub fn set_label(&mut self, label: Option<&'static str>) {
+        self.label = label;
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D> Deref for Labeled<D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        &self.device
+    }
+}
+
+impl<D> DerefMut for Labeled<D> {
+    fn deref_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+}
+
+impl<D: AsDevice> Display for Labeled<D> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{} ({})", self.name(), self.device.as_device())
+    }
+}
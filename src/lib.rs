@@ -3,16 +3,120 @@
 
 extern crate byteorder;
 extern crate embedded_hal as hal;
-
+extern crate embedded_hal_1 as hal1;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod alarm;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod bound;
+pub mod clock;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod crc;
+pub mod discover;
 pub mod ds18b20;
-
+pub mod ds1921;
+pub mod ds1wm;
+pub mod ds2408;
+pub mod ds2409;
+pub mod ds2431;
+pub mod ds2433;
+pub mod ds2482;
+pub mod ds2484;
+pub mod eeprom;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod ibutton;
+pub mod label;
+#[cfg(feature = "std")]
+pub mod linux;
+pub mod manager;
+pub mod master;
+pub mod memory;
+pub mod pipeline;
+pub mod plausibility;
+#[cfg(feature = "nb")]
+pub mod poll;
+pub mod power;
+pub mod pullup;
+pub mod ringlog;
+pub mod scan;
+pub mod scratchpad;
+pub mod sensor;
+pub mod shared;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod thermostat;
+pub mod timing;
+pub mod topology;
+pub mod uart;
+
+pub use crate::alarm::AlarmMonitor;
+#[cfg(feature = "async")]
+pub use crate::asynch::AsyncOneWire;
+pub use crate::bound::BoundOneWire;
+pub use crate::clock::{Clock, Idle, Spin, TimestampSource};
+#[cfg(feature = "config")]
+pub use crate::config::{ConfigError, ConfigStore};
+pub use crate::crc::{
+    compute_crc8, compute_partial_crc16, compute_partial_crc8, ensure_correct_crc16,
+    ensure_correct_rcr8, Crc16, Crc8, Crc8Provider, SoftwareCrc8,
+};
+pub use crate::discover::{DeviceIndex, Discover, Discoverable, ResolvedIndex, SingleDrop};
 pub use crate::ds18b20::DS18B20;
-
+pub use crate::ds1921::DS1921;
+pub use crate::ds1wm::DS1WM;
+pub use crate::ds2408::DS2408;
+pub use crate::ds2409::DS2409;
+pub use crate::ds2431::DS2431;
+pub use crate::ds2433::DS2433;
+pub use crate::ds2482::{BridgeError, ChannelSearch, DS2482};
+pub use crate::ds2484::{PortParameter, DS2484};
+pub use crate::eeprom::EepromDevice;
+pub use crate::ibutton::wait_for_key;
+pub use crate::label::{family_name, AsDevice, Labeled};
+#[cfg(feature = "std")]
+pub use crate::linux::{SysfsError, W1Sysfs};
+pub use crate::manager::SensorManager;
+pub use crate::master::{BusMaster, MasterPin};
+pub use crate::memory::{MemoryDevice, MemoryReader};
+pub use crate::pipeline::Pipeline;
+pub use crate::plausibility::{Implausible, PlausibilityCheck};
+#[cfg(feature = "nb")]
+pub use crate::poll::NbOneWire;
+pub use crate::power::PowerBudget;
+pub use crate::pullup::StrongPullUp;
+pub use crate::ringlog::{Record, RingLog};
+pub use crate::scan::{scan_and_read_all, ScanReport};
+pub use crate::scratchpad::{Presence, ScratchpadCrc, ScratchpadDevice};
+#[cfg(feature = "async")]
+pub use crate::sensor::AsyncSensor;
+pub use crate::sensor::{AlarmSensor, ConversionHandle, ErasedSensor, Median, MultiSensor, Sensor};
+pub use crate::shared::RefCellOneWire;
+#[cfg(feature = "critical-section")]
+pub use crate::shared::{SharedOneWire, SharedOneWireGuard};
+pub use crate::stats::BusStats;
+pub use crate::thermostat::{Thermostat, ThermostatMode};
+#[cfg(feature = "critical-section")]
+pub use crate::timing::CriticalSectionGuard;
+pub use crate::timing::{
+    CheckedDelay, ConstTiming, DefaultTiming, NoGuard, Overdrive, OvershootStats, PhaseWindow,
+    Relaxed, SlotCheck, SlotStats, SlotWindows, Standard, Timing, TimingGuard, TimingProfile,
+    UsDelay,
+};
+pub use crate::topology::{BranchPath, Hop};
+pub use crate::uart::{Uart, UartWire};
+
+use crate::timing::ActiveTiming;
+use core::convert::TryFrom;
 use core::fmt::Formatter;
 use core::fmt::{Debug, Display};
-use hal::blocking::delay::DelayUs;
 use hal::digital::v2::InputPin;
 use hal::digital::v2::OutputPin;
+use hal1::delay::DelayNs;
 
 pub const ADDRESS_BYTES: u8 = 8;
 pub const ADDRESS_BITS: u8 = ADDRESS_BYTES * 8;
@@ -20,7 +124,11 @@ pub const ADDRESS_BITS: u8 = ADDRESS_BYTES * 8;
 #[repr(u8)]
 pub enum Command {
     SelectRom = 0x55,
+    SkipRom = 0xCC,
+    OverdriveSkipRom = 0x3C,
+    OverdriveMatchRom = 0x69,
     SearchNext = 0xF0,
+    ReadRom = 0x33,
     SearchNextAlarmed = 0xEC,
 }
 
@@ -28,9 +136,19 @@ pub enum Command {
 pub enum Error<E: Sized + Debug> {
     WireNotHigh,
     CrcMismatch(u8, u8),
+    Crc16Mismatch(u16, u16),
     FamilyCodeMismatch(u8, u8),
     Debug(Option<u8>),
     PortError(E),
+    /// A reading has been rejected by a [`PlausibilityCheck`]
+    Implausible(Implausible),
+    /// The line has been held low at the end of a time slot, by another master or a short,
+    /// see [`OneWire::with_collision_detection`]
+    Collision,
+    /// The bus master of the pin does not support the operation, see
+    /// [`BusMaster::supports_overdrive`], or the operation is not possible within a
+    /// transaction
+    Unsupported,
 }
 
 impl<E: Sized + Debug> From<E> for Error<E> {
@@ -48,6 +166,43 @@ impl Device {
     pub fn family_code(&self) -> u8 {
         self.address[0]
     }
+
+    /// Returns whether the last address byte matches the CRC8 of the address
+    pub const fn is_crc_valid(&self) -> bool {
+        crc::is_valid_address(&self.address)
+    }
+
+    /// Returns the short id of the device, see [`ShortId`]
+    pub const fn short_id(&self) -> ShortId {
+        ShortId(compute_partial_crc16(0, &self.address))
+    }
+}
+
+/// A short identifier of a device for user interfaces or MQTT topics, displayed as four
+/// hex digits.
+///
+/// It is the CRC16 (polynomial 0xA001, initial value 0) over the eight address bytes, so
+/// it only depends on the address and remains stable across reboots. Being 16 bits, two
+/// devices may share the same short id, which [`find_short_id_collision`] detects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortId(pub u16);
+
+impl Display for ShortId {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{:04x}", self.0)
+    }
+}
+
+/// Returns the indices of the first two devices sharing the same short id
+pub fn find_short_id_collision(devices: &[Device]) -> Option<(usize, usize)> {
+    for (i, a) in devices.iter().enumerate() {
+        for (j, b) in devices.iter().enumerate().skip(i + 1) {
+            if a.short_id() == b.short_id() {
+                return Some((i, j));
+            }
+        }
+    }
+    None
 }
 
 impl core::str::FromStr for Device {
@@ -72,24 +227,74 @@ impl core::str::FromStr for Device {
     }
 }
 
+/// Details about the presence pulse observed during a reset, see [`OneWire::reset_with_info`]
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResetInfo {
+    /// Whether a presence pulse has been received
+    pub presence: bool,
+    /// Microseconds after the end of the reset at which the presence pulse was first seen
+    pub presence_start_us: Option<u16>,
+    /// Approximate duration of the presence pulse in microseconds, with the resolution of
+    /// the presence sample interval
+    pub presence_duration_us: u16,
+}
+
+/// Converts a measured duration for [`ResetInfo`], saturating at its range
+fn ns_to_us(ns: u32) -> u16 {
+    u16::try_from(ns / 1000).unwrap_or(u16::MAX)
+}
+
+/// The two bits read and the bit written for one address bit of a search, see
+/// [`OneWire::triplet`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Triplet {
+    /// The address bit, the AND of the bits of all devices taking part
+    pub bit: bool,
+    /// The complement, the AND of the inverted bits
+    pub complement: bool,
+    /// The direction written, the devices whose address bit differs drop out
+    pub direction: bool,
+}
+
+impl Triplet {
+    /// Takes the only direction the devices agree on, or `direction` if there are devices
+    /// with both values or none at all
+    pub fn new(bit: bool, complement: bool, direction: bool) -> Self {
+        Triplet {
+            bit,
+            complement,
+            direction: if bit != complement { bit } else { direction },
+        }
+    }
+
+    /// Whether no device took part
+    pub fn is_empty(&self) -> bool {
+        self.bit && self.complement
+    }
+
+    /// Whether devices with both values of the bit took part
+    pub fn is_discrepancy(&self) -> bool {
+        !self.bit && !self.complement
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(u8)]
 enum SearchState {
+    #[default]
     Initialized,
     DeviceFound,
     End,
 }
 
-impl Default for SearchState {
-    fn default() -> Self {
-        SearchState::Initialized
-    }
-}
-
 #[derive(Clone, Default)]
+#[repr(C)]
 pub struct DeviceSearch {
     address: [u8; 8],
     discrepancies: [u8; 8],
     state: SearchState,
+    /// Number of leading address bits the search is restricted to
+    fixed_bits: u8,
 }
 
 impl DeviceSearch {
@@ -97,9 +302,11 @@ impl DeviceSearch {
         DeviceSearch::default()
     }
 
+    /// Creates a search that only finds devices of the given family
     pub fn new_for_family(family: u8) -> DeviceSearch {
         let mut search = DeviceSearch::new();
         search.address[0] = family;
+        search.fixed_bits = 8;
         search
     }
 
@@ -181,11 +388,11 @@ impl DeviceSearch {
         result
     }
 
-    pub fn into_iter<'a, ODO: OpenDrainOutput>(
+    pub fn into_iter<'a, ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
         self,
-        wire: &'a mut OneWire<ODO>,
-        delay: &'a mut impl DelayUs<u16>,
-    ) -> DeviceSearchIter<'a, ODO, impl DelayUs<u16>> {
+        wire: &'a mut OneWire<ODO, T, G>,
+        delay: &'a mut impl DelayNs,
+    ) -> DeviceSearchIter<'a, ODO, impl DelayNs, T, G> {
         DeviceSearchIter {
             search: Some(self),
             wire,
@@ -194,13 +401,128 @@ impl DeviceSearch {
     }
 }
 
-pub struct DeviceSearchIter<'a, ODO: OpenDrainOutput, Delay: DelayUs<u16>> {
+/// What a bus has to do next during a [`SearchPass`]
+pub(crate) enum SearchStep {
+    /// Perform a triplet writing the given direction and pass its result to the next step
+    Triplet(bool),
+    /// The pass is over, with the device found if any
+    Done(Option<Device>),
+}
+
+/// The address bit by bit part of one pass of a [`DeviceSearch`], shared by the blocking
+/// and the async bus. After the reset and the search command, the bus performs the
+/// triplets the pass asks for until it is done.
+/// Heavily inspired by https://github.com/ntruchsess/arduino-OneWire/blob/85d1aae63ea4919c64151e03f7e24c2efbc40198/OneWire.cpp#L362
+pub(crate) struct SearchPass {
+    last_discrepancy: Option<u8>,
+    discrepancy_found: bool,
+    /// The address bit of the next triplet
+    bit: u8,
+}
+
+impl SearchPass {
+    pub(crate) fn new(rom: &DeviceSearch) -> Self {
+        SearchPass {
+            last_discrepancy: rom.last_discrepancy(),
+            discrepancy_found: false,
+            bit: 0,
+        }
+    }
+
+    /// Takes the result of the previous triplet, `None` for the first step, and returns
+    /// the next step
+    pub(crate) fn next(&mut self, rom: &mut DeviceSearch, triplet: Option<Triplet>) -> SearchStep {
+        match triplet {
+            Some(triplet) => {
+                if let Some(device) = self.update(rom, triplet) {
+                    return SearchStep::Done(device);
+                }
+            }
+            None => {
+                if self.last_discrepancy.is_none() && rom.state == SearchState::DeviceFound {
+                    // no discrepancy and device found, meaning the one found is the only one
+                    rom.state = SearchState::End;
+                    return SearchStep::Done(None);
+                }
+            }
+        }
+
+        let i = self.bit;
+        if i == ADDRESS_BITS {
+            if !self.discrepancy_found && rom.last_discrepancy().is_none() {
+                rom.state = SearchState::End;
+            } else {
+                rom.state = SearchState::DeviceFound;
+            }
+            return SearchStep::Done(Some(Device {
+                address: rom.address,
+            }));
+        }
+
+        SearchStep::Triplet(if self.is_previous_path(i) || i < rom.fixed_bits {
+            // walk previous path or restricted to the preset bit
+            rom.is_bit_set_in_address(i)
+        } else {
+            // be sure to go different path from before (go second path, thus writing 1),
+            // on a new path go first path by default (thus writing 0)
+            self.last_discrepancy == Some(i)
+        })
+    }
+
+    fn is_previous_path(&self, bit: u8) -> bool {
+        self.last_discrepancy.is_some_and(|last| bit < last)
+    }
+
+    /// Records the result of the triplet for the current bit, returns the result of the
+    /// pass if it ends here
+    fn update(&mut self, rom: &mut DeviceSearch, triplet: Triplet) -> Option<Option<Device>> {
+        let i = self.bit;
+        self.bit += 1;
+        if self.is_previous_path(i) {
+            if triplet.is_empty() {
+                // no device responded
+                return Some(None);
+            }
+        } else if i < rom.fixed_bits {
+            // no device left if none has the preset bit
+            if triplet.is_empty() || triplet.direction != rom.is_bit_set_in_address(i) {
+                rom.state = SearchState::End;
+                return Some(None);
+            }
+        } else if self.last_discrepancy == Some(i) {
+            rom.reset_bit_in_discrepancy(i);
+            rom.write_bit_in_address(i, triplet.direction);
+        } else {
+            if triplet.is_empty() {
+                // no response received
+                return Some(None);
+            }
+            if triplet.is_discrepancy() {
+                // addresses with 0 and 1
+                self.discrepancy_found = true;
+                rom.set_bit_in_discrepancy(i);
+            }
+            rom.write_bit_in_address(i, triplet.direction);
+        }
+        None
+    }
+}
+
+pub struct DeviceSearchIter<
+    'a,
+    ODO: OpenDrainOutput,
+    Delay: DelayNs,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
     search: Option<DeviceSearch>,
-    wire: &'a mut OneWire<ODO>,
+    wire: &'a mut OneWire<ODO, T, G>,
     delay: &'a mut Delay,
 }
 
-impl<'a, ODO: OpenDrainOutput, Delay: DelayUs<u16>> Iterator for DeviceSearchIter<'a, ODO, Delay> {
+impl<'a, ODO: OpenDrainOutput, Delay: DelayNs, T: TimingProfile, G: TimingGuard> Iterator
+    for DeviceSearchIter<'a, ODO, Delay, T, G>
+{
     type Item = Result<Device, Error<ODO::Error>>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -234,6 +556,40 @@ pub trait OpenDrainOutput {
     /// *NOTE* the actual electrical state of the pin may not actually be high, e.g. due to external
     /// electrical sources
     fn set_high(&mut self) -> Result<(), Self::Error>;
+
+    /// Called before the pin is used for a bus transaction, e.g. to switch a pin that is
+    /// shared with a button or an LED into open drain mode. Does nothing by default.
+    fn acquire(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called after a bus transaction finished, the counterpart of
+    /// [`OpenDrainOutput::acquire`]. Does nothing by default.
+    fn release(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the start of the recovery period of a slot once the line has been
+    /// released and no device holds it low, e.g. to briefly enable a FET to the supply
+    /// which sharpens the rising edge on heavily loaded buses. Must leave the line
+    /// released. Does nothing by default.
+    fn active_pull_up(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Returns the bus master that generates the resets and time slots, in which case the
+    /// bus forwards them instead of driving the pin, see [`BusMaster`]. `None` for plain
+    /// pins, the default.
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        None
+    }
+
+    /// Switches a strong pull-up that powers parasite devices, e.g. a MOSFET to the supply.
+    /// Enabled after writes on a bus in parasite mode and disabled before the next reset
+    /// or slot, see [`StrongPullUp`]. Does nothing by default.
+    fn strong_pull_up(&mut self, _enabled: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 impl<E: Debug, P: OutputPin<Error = E> + InputPin<Error = E>> OpenDrainOutput for P {
     type Error = E;
@@ -255,203 +611,762 @@ impl<E: Debug, P: OutputPin<Error = E> + InputPin<Error = E>> OpenDrainOutput fo
     }
 }
 
-pub struct OneWire<ODO: OpenDrainOutput> {
+pub struct OneWire<ODO: OpenDrainOutput, T: TimingProfile = DefaultTiming, G: TimingGuard = NoGuard>
+{
     output: ODO,
     parasite_mode: bool,
+    timing: T,
+    guard: G,
+    overdrive: bool,
+    transaction_depth: u8,
+    collision_detection: bool,
+    /// Whether a collision has been detected since it was last taken
+    collision: bool,
+    /// Whether the strong pull-up of the pin is enabled
+    powered: bool,
+    stats: BusStats,
+    /// Computes the CRC8 of the data read, see [`OneWire::with_crc8`]
+    crc8: fn(u8, &[u8]) -> u8,
+}
+
+impl<ODO: OpenDrainOutput, T: TimingProfile + Debug, G: TimingGuard> Debug for OneWire<ODO, T, G> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.debug_struct("OneWire")
+            .field("parasite_mode", &self.parasite_mode)
+            .field("timing", &self.timing)
+            .field("overdrive", &self.overdrive)
+            .field("collision_detection", &self.collision_detection)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
     pub fn new(output: ODO, parasite_mode: bool) -> Self {
+        OneWire::with_timing(output, parasite_mode, DefaultTiming::default())
+    }
+
+    /// Creates a bus that owns the given delay provider, so its methods don't take one,
+    /// see [`BoundOneWire`]
+    pub fn with_delay<D: DelayNs>(
+        output: ODO,
+        parasite_mode: bool,
+        delay: D,
+    ) -> BoundOneWire<ODO, D> {
+        BoundOneWire::new(OneWire::new(output, parasite_mode), delay)
+    }
+}
+
+impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>, T: TimingProfile> OneWire<ODO, T> {
+    /// Creates a bus using the given timing profile, which is either a zero sized
+    /// profile like [`Standard`] or a [`Timing`] configured at runtime
+    pub fn with_timing(output: ODO, parasite_mode: bool, timing: T) -> Self {
         OneWire {
             output,
             parasite_mode,
+            timing,
+            guard: NoGuard,
+            overdrive: false,
+            transaction_depth: 0,
+            collision_detection: false,
+            collision: false,
+            powered: false,
+            stats: BusStats::default(),
+            crc8: SoftwareCrc8::crc8,
+        }
+    }
+}
+
+impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>, T: TimingProfile, G: TimingGuard>
+    OneWire<ODO, T, G>
+{
+    /// Uses the given guard to protect the timing critical phases of the time slots,
+    /// e.g. by masking interrupts
+    pub fn with_guard<G2: TimingGuard>(self, guard: G2) -> OneWire<ODO, T, G2> {
+        OneWire {
+            output: self.output,
+            parasite_mode: self.parasite_mode,
+            timing: self.timing,
+            guard,
+            overdrive: self.overdrive,
+            transaction_depth: self.transaction_depth,
+            collision_detection: self.collision_detection,
+            collision: self.collision,
+            powered: self.powered,
+            stats: self.stats,
+            crc8: self.crc8,
+        }
+    }
+
+    /// Computes the CRC8 of the data read with the given provider, e.g. a CRC peripheral,
+    /// instead of on the CPU
+    pub fn with_crc8<P: Crc8Provider>(mut self) -> Self {
+        self.crc8 = P::crc8;
+        self
+    }
+
+    /// Checks that the line is released at the end of each time slot. Another master or a
+    /// short holding the line low fails the surrounding [`OneWire::transaction`] with
+    /// [`Error::Collision`]. Costs an additional pin read per bit.
+    pub fn with_collision_detection(mut self, enabled: bool) -> Self {
+        self.collision_detection = enabled;
+        self
+    }
+
+    /// Returns and clears whether a collision has been detected, for reads and writes
+    /// outside of a transaction
+    pub fn take_collision(&mut self) -> bool {
+        core::mem::take(&mut self.collision)
+    }
+
+    /// The counters collected since the bus was created or they were last taken
+    pub fn stats(&self) -> &BusStats {
+        &self.stats
+    }
+
+    /// Returns the counters collected so far and resets them, e.g. to report them
+    /// periodically
+    pub fn take_stats(&mut self) -> BusStats {
+        core::mem::take(&mut self.stats)
+    }
+
+    /// Counts an operation the application repeats after a failure, e.g. a sensor read
+    /// retried after a CRC mismatch, see [`BusStats::retries`]
+    pub fn record_retry(&mut self) {
+        self.stats.record_retry();
+    }
+
+    /// Runs `f` as a single bus transaction: the pin is acquired before and released
+    /// after it, see [`OpenDrainOutput::acquire`]. The transaction helpers like
+    /// [`OneWire::reset_select_write_read`] and the search already do this on their own,
+    /// manual sequences of resets, reads and writes should be wrapped. Nested
+    /// transactions only acquire and release the pin once.
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        self.begin_transaction()?;
+        let result = f(self);
+        self.end_transaction(result)
+    }
+
+    pub(crate) fn begin_transaction(&mut self) -> Result<(), E> {
+        if self.transaction_depth == 0 {
+            self.output.acquire()?;
+        }
+        self.transaction_depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn end_transaction<R>(
+        &mut self,
+        result: Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        self.transaction_depth -= 1;
+        if self.transaction_depth == 0 {
+            self.output.release()?;
+            if self.take_collision() {
+                // garbled data is explained by the collision, so it takes precedence
+                return Err(Error::Collision);
+            }
+        }
+        result
+    }
+
+    /// Gives access to the pin of the bus, e.g. to measure the line level
+    pub fn output(&self) -> &ODO {
+        &self.output
+    }
+
+    /// Gives mutable access to the pin of the bus for out-of-band operations like
+    /// configuring pull-ups. The line should be left released (high) afterwards.
+    pub fn output_mut(&mut self) -> &mut ODO {
+        &mut self.output
+    }
+
+    /// Returns the pin of the bus, the line is left in its current state
+    pub fn into_inner(self) -> ODO {
+        self.output
+    }
+
+    /// Hands the pin to `f` for another purpose between bus operations, e.g. to read a
+    /// button sharing the pin. The line is released before and after `f`, which should
+    /// not disturb the devices. Fails with [`Error::Unsupported`] within a
+    /// [`OneWire::transaction`], since the pin can't be handed out in the middle of one.
+    pub fn with_released_pin<R>(&mut self, f: impl FnOnce(&mut ODO) -> R) -> Result<R, Error<E>> {
+        if self.transaction_depth > 0 {
+            return Err(Error::Unsupported);
+        }
+        self.disable_parasite_mode()?;
+        let result = f(&mut self.output);
+        self.set_input()?;
+        Ok(result)
+    }
+
+    /// Whether the bus is kept powered after writes for parasite powered devices
+    pub fn parasite_mode(&self) -> bool {
+        self.parasite_mode
+    }
+
+    /// The active timing profile
+    pub fn timing(&self) -> &T {
+        &self.timing
+    }
+
+    /// The timing guard, e.g. to read the statistics of a [`SlotCheck`]
+    pub fn guard(&self) -> &G {
+        &self.guard
+    }
+
+    pub fn guard_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+
+    /// Whether the bus currently communicates at overdrive speed
+    pub fn is_overdrive(&self) -> bool {
+        self.overdrive
+    }
+
+    /// Switches the timing of the following resets and slots between standard and
+    /// overdrive speed. The devices need to be switched as well, see
+    /// [`OneWire::overdrive_select`] and [`OneWire::overdrive_skip`]. A reset at standard
+    /// speed returns all devices to standard speed. A [`BusMaster`] is switched before
+    /// its next reset or slot, masters not capable of overdrive stay at standard speed.
+    pub fn set_overdrive(&mut self, overdrive: bool) {
+        self.overdrive = overdrive;
+    }
+
+    /// Returns the bus master of the pin if it has one, switched to the current speed
+    fn bus_master(&mut self) -> Result<Option<&mut dyn BusMaster<Error = E>>, E> {
+        let overdrive = self.overdrive;
+        match self.output.bus_master() {
+            Some(master) => {
+                master.set_overdrive(overdrive)?;
+                Ok(Some(master))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn active_timing(&self) -> ActiveTiming<'_, T> {
+        ActiveTiming {
+            timing: &self.timing,
+            overdrive: self.overdrive,
         }
     }
 
     pub fn reset_select_write_read(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
         device: &Device,
         write: &[u8],
         read: &mut [u8],
     ) -> Result<(), Error<E>> {
-        self.reset(delay)?;
-        self.select(delay, device)?;
-        self.write_bytes(delay, write)?;
-        self.read_bytes(delay, read)?;
-        Ok(())
+        self.reset_select_or_skip_write_read(delay, Some(device), write, read)
     }
 
     pub fn reset_select_read_only(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
         device: &Device,
         read: &mut [u8],
     ) -> Result<(), Error<E>> {
-        self.reset(delay)?;
-        self.select(delay, device)?;
-        self.select(delay, device)?;
-        self.read_bytes(delay, read)?;
-        Ok(())
+        self.reset_select_or_skip_read_only(delay, Some(device), read)
     }
 
     pub fn reset_select_write_only(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
         device: &Device,
         write: &[u8],
     ) -> Result<(), Error<E>> {
-        self.reset(delay)?;
-        self.select(delay, device)?;
-        self.select(delay, device)?;
+        self.reset_select_or_skip_write_only(delay, Some(device), write)
+    }
+
+    /// Same as [`OneWire::reset_select_write_read`], but skips the addressing if no device is given
+    pub fn reset_select_or_skip_write_read(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: Option<&Device>,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, device)?;
+            wire.write_bytes(delay, write)?;
+            wire.read_bytes(delay, read)?;
+            Ok(())
+        })
+    }
+
+    /// Same as [`OneWire::reset_select_read_only`], but skips the addressing if no device is given
+    pub fn reset_select_or_skip_read_only(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: Option<&Device>,
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, device)?;
+            wire.read_bytes(delay, read)?;
+            Ok(())
+        })
+    }
+
+    /// Same as [`OneWire::reset_select_write_only`], but skips the addressing if no device is given
+    pub fn reset_select_or_skip_write_only(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: Option<&Device>,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
+        self.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, device)?;
+            wire.write_bytes(delay, write)?;
+            Ok(())
+        })
+    }
+
+    /// Writes further bytes within the currently open transaction, without a reset or a
+    /// ROM command. Needed by protocols which require data to follow previous reads, like
+    /// the authorization bytes of the DS2431 copy scratchpad command.
+    pub fn continue_write(
+        &mut self,
+        delay: &mut impl DelayNs,
+        write: &[u8],
+    ) -> Result<(), Error<E>> {
         self.write_bytes(delay, write)?;
         Ok(())
     }
 
-    pub fn select(
+    /// Reads further bytes within the currently open transaction, without a reset or a
+    /// ROM command
+    pub fn continue_read(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
-        device: &Device,
+        delay: &mut impl DelayNs,
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.read_bytes(delay, read)?;
+        Ok(())
+    }
+
+    /// Selects the given device with Match ROM, or addresses all devices with Skip ROM if
+    /// no device is given
+    pub fn select_or_skip(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: Option<&Device>,
+    ) -> Result<(), Error<E>> {
+        match device {
+            Some(device) => self.select(delay, device),
+            None => self.skip(delay),
+        }
+    }
+
+    /// Addresses all devices on the bus at once, e.g. to start a conversion on every sensor
+    pub fn reset_skip_write_only(
+        &mut self,
+        delay: &mut impl DelayNs,
+        write: &[u8],
     ) -> Result<(), Error<E>> {
+        self.reset_select_or_skip_write_only(delay, None, write)
+    }
+
+    /// Addresses all devices on the bus without sending their address.
+    /// Reading after skipping is only valid if there is a single device on the bus.
+    pub fn skip(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
         let parasite_mode = self.parasite_mode;
-        self.write_command(delay, Command::SelectRom, parasite_mode)?; // select
-        for i in 0..device.address.len() {
-            let last = i == device.address.len() - 1;
-            self.write_byte(delay, device.address[i], parasite_mode && last)?;
+        self.write_command(delay, Command::SkipRom, parasite_mode)?;
+        Ok(())
+    }
+
+    pub fn select(&mut self, delay: &mut impl DelayNs, device: &Device) -> Result<(), Error<E>> {
+        self.write_byte_slots(delay, Command::SelectRom as u8, false)?; // select
+        self.write_bytes_held(delay, &device.address)?;
+        if !self.parasite_mode {
+            self.disable_parasite_mode()?;
         }
         Ok(())
     }
 
+    /// Sends Overdrive Match ROM at the current speed, which switches the given device to
+    /// overdrive speed, and continues at overdrive speed with its address. Devices not
+    /// capable of overdrive don't support this command.
+    pub fn overdrive_select(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: &Device,
+    ) -> Result<(), Error<E>> {
+        self.check_overdrive_support()?;
+        self.write_byte_slots(delay, Command::OverdriveMatchRom as u8, false)?;
+        self.overdrive = true;
+        self.write_bytes_held(delay, &device.address)?;
+        if !self.parasite_mode {
+            self.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Sends Overdrive Skip ROM at the current speed, which switches all overdrive capable
+    /// devices to overdrive speed, and continues at overdrive speed
+    pub fn overdrive_skip(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
+        self.check_overdrive_support()?;
+        let parasite_mode = self.parasite_mode;
+        self.write_command(delay, Command::OverdriveSkipRom, parasite_mode)?;
+        self.overdrive = true;
+        Ok(())
+    }
+
+    /// Fails with [`Error::Unsupported`] if the bus master of the pin can't follow the
+    /// devices to overdrive speed
+    fn check_overdrive_support(&mut self) -> Result<(), Error<E>> {
+        match self.output.bus_master() {
+            Some(master) if !master.supports_overdrive() => Err(Error::Unsupported),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resets the bus at standard speed and switches all overdrive capable devices and the
+    /// bus to overdrive speed, returns whether a presence pulse has been received.
+    ///
+    /// The following resets, searches and transfers run at overdrive speed, which cuts
+    /// the time to enumerate large overdrive only segments. Devices not capable of
+    /// overdrive don't respond until [`OneWire::exit_overdrive`].
+    pub fn enter_overdrive(&mut self, delay: &mut impl DelayNs) -> Result<bool, Error<E>> {
+        self.overdrive = false;
+        self.transaction(|wire| {
+            if !wire.reset(delay)? {
+                return Ok(false);
+            }
+            wire.overdrive_skip(delay)?;
+            Ok(true)
+        })
+    }
+
+    /// Resets the bus at standard speed, which returns all devices to standard speed,
+    /// returns whether a presence pulse has been received
+    pub fn exit_overdrive(&mut self, delay: &mut impl DelayNs) -> Result<bool, Error<E>> {
+        self.overdrive = false;
+        self.transaction(|wire| wire.reset(delay))
+    }
+
+    /// Resets the bus and selects the device at standard speed, but transfers the data at
+    /// overdrive speed, for buses where only some devices are capable of overdrive. The
+    /// bus is back at standard speed afterwards, the next reset returns the device to
+    /// standard speed as well.
+    pub fn reset_overdrive_select_write_read(
+        &mut self,
+        delay: &mut impl DelayNs,
+        device: &Device,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.overdrive = false;
+        let result = self.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.overdrive_select(delay, device)?;
+            wire.write_bytes(delay, write)?;
+            wire.read_bytes(delay, read)?;
+            Ok(())
+        });
+        self.overdrive = false;
+        result
+    }
+
+    /// Finds the next device, at overdrive speed if the bus has been switched to it with
+    /// [`OneWire::enter_overdrive`]
     pub fn search_next(
         &mut self,
         search: &mut DeviceSearch,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
     ) -> Result<Option<Device>, Error<E>> {
-        self.search(search, delay, Command::SearchNext)
+        self.transaction(|wire| wire.search(search, delay, Command::SearchNext))
     }
 
     pub fn search_next_alarmed(
         &mut self,
         search: &mut DeviceSearch,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
     ) -> Result<Option<Device>, Error<E>> {
-        self.search(search, delay, Command::SearchNextAlarmed)
+        self.transaction(|wire| wire.search(search, delay, Command::SearchNextAlarmed))
+    }
+
+    /// Searches the bus for the devices supported by `D`, only visiting the families it
+    /// supports, and creates a driver for each of them. Devices with an invalid address
+    /// CRC are reported as errors.
+    pub fn discover<'a, D: Discoverable, Delay: DelayNs>(
+        &'a mut self,
+        delay: &'a mut Delay,
+    ) -> Discover<'a, D, ODO, Delay, T, G> {
+        Discover::new(self, delay)
+    }
+
+    /// Returns the driver for the first device found that is supported by `D`, see
+    /// [`OneWire::discover`]
+    pub fn search_first_of<D: Discoverable>(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> Result<Option<D>, Error<E>> {
+        self.discover(delay).next().transpose()
+    }
+
+    /// Looks up the device at the given position among the devices of its family, see
+    /// [`DeviceIndex`]. Searches the whole family to detect stale positions.
+    pub fn resolve_index(
+        &mut self,
+        delay: &mut impl DelayNs,
+        index: &DeviceIndex,
+    ) -> Result<ResolvedIndex, Error<E>> {
+        let mut search = DeviceSearch::new_for_family(index.family);
+        let mut count = 0;
+        let mut found = None;
+        while let Some(device) = self.search_next(&mut search, delay)? {
+            if !device.is_crc_valid() {
+                let computed = compute_partial_crc8(0, &device.address[..7]);
+                return self
+                    .stats
+                    .check_crc(Err(Error::CrcMismatch(computed, device.address[7])));
+            }
+            if count == index.index {
+                found = Some(device);
+            }
+            count += 1;
+        }
+        match found {
+            Some(device) if count == index.count => Ok(ResolvedIndex::Found(device)),
+            device => Ok(ResolvedIndex::Stale { count, device }),
+        }
+    }
+
+    /// Reads the address of the only device on the bus with Read ROM, returns `None` if no
+    /// device is present. With more than one device on the bus, the address is garbled and
+    /// the CRC check fails.
+    pub fn read_rom(&mut self, delay: &mut impl DelayNs) -> Result<Option<Device>, Error<E>> {
+        self.transaction(|wire| {
+            if !wire.reset(delay)? {
+                return Ok(None);
+            }
+            let parasite_mode = wire.parasite_mode;
+            wire.write_command(delay, Command::ReadRom, parasite_mode)?;
+            let mut address = [0u8; ADDRESS_BYTES as usize];
+            wire.read_bytes_crc8(delay, &mut address[..7])?;
+            address[7] = compute_partial_crc8(0, &address[..7]);
+            Ok(Some(Device { address }))
+        })
+    }
+
+    /// Checks whether the given device is present on the bus by walking its address with
+    /// a search, without searching the whole bus
+    pub fn verify(&mut self, delay: &mut impl DelayNs, device: &Device) -> Result<bool, Error<E>> {
+        self.transaction(|wire| {
+            if !wire.reset(delay)? {
+                return Ok(false);
+            }
+            wire.write_byte(delay, Command::SearchNext as u8, false)?;
+            for i in 0..ADDRESS_BITS {
+                let bit0 = wire.read_bit(delay)?;
+                let bit1 = wire.read_bit(delay)?;
+                let bit = DeviceSearch::is_bit_set(&device.address, i);
+                // bit0 is only low if a device with a 0 bit is left, bit1 if one with a 1 bit
+                if (bit && bit1) || (!bit && bit0) {
+                    return Ok(false);
+                }
+                wire.write_bit(delay, bit)?;
+            }
+            Ok(true)
+        })
+    }
+
+    /// Releases the bus before it is powered down, e.g. between duty cycles. Remember the
+    /// known devices to check them with [`OneWire::resume`] afterwards.
+    pub fn suspend(&mut self) -> Result<(), Error<E>> {
+        self.power_off()?;
+        self.set_input()?;
+        Ok(())
+    }
+
+    /// Resets the bus after it has been powered up again and verifies that each of the
+    /// `known` devices still responds, `missing` is called for those that don't.
+    /// Returns the number of missing devices.
+    pub fn resume(
+        &mut self,
+        delay: &mut impl DelayNs,
+        known: &[Device],
+        mut missing: impl FnMut(&Device),
+    ) -> Result<usize, Error<E>> {
+        self.transaction(|wire| {
+            wire.reset(delay)?;
+            let mut count = 0;
+            for device in known {
+                if !wire.verify(delay, device)? {
+                    missing(device);
+                    count += 1;
+                }
+            }
+            Ok(count)
+        })
     }
 
-    /// Heavily inspired by https://github.com/ntruchsess/arduino-OneWire/blob/85d1aae63ea4919c64151e03f7e24c2efbc40198/OneWire.cpp#L362
     fn search(
         &mut self,
         rom: &mut DeviceSearch,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
         cmd: Command,
+    ) -> Result<Option<Device>, Error<E>> {
+        self.search_with(rom, delay, cmd, |wire, delay| wire.reset(delay))
+    }
+
+    /// Same as [`OneWire::search_next`], but starts each pass with `start` instead of a reset,
+    /// which returns whether any device is present. This allows to search the branch of
+    /// a coupler, which is reset by the coupler itself.
+    pub(crate) fn search_with<Delay: DelayNs>(
+        &mut self,
+        rom: &mut DeviceSearch,
+        delay: &mut Delay,
+        cmd: Command,
+        start: impl FnOnce(&mut Self, &mut Delay) -> Result<bool, Error<E>>,
     ) -> Result<Option<Device>, Error<E>> {
         if SearchState::End == rom.state {
             return Ok(None);
         }
 
-        let mut discrepancy_found = false;
-        let last_discrepancy = rom.last_discrepancy();
-
-        if !self.reset(delay)? {
+        if !start(self, delay)? {
             return Ok(None);
         }
 
         self.write_byte(delay, cmd as u8, false)?;
 
-        if let Some(last_discrepancy) = last_discrepancy {
-            // walk previous path
-            for i in 0..last_discrepancy {
-                let bit0 = self.read_bit(delay)?;
-                let bit1 = self.read_bit(delay)?;
-
-                if bit0 && bit1 {
-                    // no device responded
-                    return Ok(None);
-                } else {
-                    let bit = rom.is_bit_set_in_address(i);
-                    // rom.write_bit_in_address(i, bit0);
-                    // rom.write_bit_in_discrepancy(i, bit);
-                    self.write_bit(delay, bit)?;
-                }
-            }
-        } else {
-            // no discrepancy and device found, meaning the one found is the only one
-            if rom.state == SearchState::DeviceFound {
-                rom.state = SearchState::End;
-                return Ok(None);
+        let mut pass = SearchPass::new(rom);
+        let mut triplet = None;
+        loop {
+            match pass.next(rom, triplet) {
+                SearchStep::Triplet(direction) => triplet = Some(self.triplet(delay, direction)?),
+                SearchStep::Done(device) => return Ok(device),
             }
         }
+    }
 
-        for i in last_discrepancy.unwrap_or(0)..ADDRESS_BITS {
-            let bit0 = self.read_bit(delay)?; // normal bit
-            let bit1 = self.read_bit(delay)?; // complementar bit
+    /// Performs a reset and listens for a presence pulse
+    /// Returns Err(WireNotHigh) if the wire seems to be shortened,
+    /// Ok(true) if presence pulse has been received and Ok(false)
+    /// if no other device was detected but the wire seems to be ok
+    pub fn reset(&mut self, delay: &mut impl DelayNs) -> Result<bool, Error<E>> {
+        let result = self.reset_and_sample(delay);
+        self.stats.record_reset(matches!(result, Ok(true)));
+        result
+    }
 
-            if last_discrepancy.eq(&Some(i)) {
-                // be sure to go different path from before (go second path, thus writing 1)
-                rom.reset_bit_in_discrepancy(i);
-                rom.set_bit_in_address(i);
-                self.write_bit(delay, true)?;
-            } else {
-                if bit0 && bit1 {
-                    // no response received
-                    return Ok(None);
-                }
+    fn reset_and_sample(&mut self, delay: &mut impl DelayNs) -> Result<bool, Error<E>> {
+        if let Some(master) = self.bus_master()? {
+            return master.reset();
+        }
+        self.reset_pulse(delay)?;
+        let val = self.sample_presence(delay)?;
+        delay.delay_ns(self.active_timing().reset_recovery_ns());
+        Ok(val)
+    }
 
-                if !bit0 && !bit1 {
-                    // addresses with 0 and 1
-                    // found new path, go first path by default (thus writing 0)
-                    discrepancy_found |= true;
-                    rom.set_bit_in_discrepancy(i);
-                    rom.reset_bit_in_address(i);
-                    self.write_bit(delay, false)?;
-                } else {
-                    // addresses only with bit0
-                    rom.write_bit_in_address(i, bit0);
-                    self.write_bit(delay, bit0)?;
-                }
+    /// Same as [`OneWire::reset`], but also measures when the presence pulse started
+    /// and how long it lasted. A healthy device pulls the line low 15-60us after the
+    /// reset for 60-240us, weak pull-ups show up as late or overly long pulses and a
+    /// pulse lasting until the end of the reset points to a shorted line.
+    pub fn reset_with_info(&mut self, delay: &mut impl DelayNs) -> Result<ResetInfo, Error<E>> {
+        let result = self.reset_and_measure(delay);
+        self.stats
+            .record_reset(matches!(result, Ok(ResetInfo { presence: true, .. })));
+        result
+    }
+
+    fn reset_and_measure(&mut self, delay: &mut impl DelayNs) -> Result<ResetInfo, Error<E>> {
+        if let Some(master) = self.bus_master()? {
+            // a bus master only reports whether there was a presence pulse
+            return master.reset().map(|presence| ResetInfo {
+                presence,
+                presence_start_us: None,
+                presence_duration_us: 0,
+            });
+        }
+        self.reset_pulse(delay)?;
+        Ok(self.measure_presence(delay)?)
+    }
+
+    /// Samples the presence pulse after the reset pulse has ended and measures it, until
+    /// the end of the recovery time
+    pub(crate) fn measure_presence(&mut self, delay: &mut impl DelayNs) -> Result<ResetInfo, E> {
+        let interval = self.active_timing().presence_sample_interval_ns();
+        let mut elapsed = 0u32;
+        let mut start = None;
+        let mut end = None;
+        for _ in 0..self.active_timing().presence_samples() {
+            delay.delay_ns(interval);
+            elapsed = elapsed.saturating_add(interval);
+            let low = !self.guarded(|wire| wire.read())?;
+            if low && start.is_none() {
+                start = Some(elapsed);
+            } else if !low && start.is_some() && end.is_none() {
+                end = Some(elapsed);
             }
         }
 
-        if !discrepancy_found && rom.last_discrepancy().is_none() {
-            rom.state = SearchState::End;
-        } else {
-            rom.state = SearchState::DeviceFound;
+        // keep sampling a pulse that is still active during the recovery time to measure its length
+        let mut recovery = self.active_timing().reset_recovery_ns();
+        while start.is_some() && end.is_none() && interval > 0 && recovery >= interval {
+            delay.delay_ns(interval);
+            elapsed = elapsed.saturating_add(interval);
+            recovery -= interval;
+            if self.read()? {
+                end = Some(elapsed);
+            }
         }
-        Ok(Some(Device {
-            address: rom.address,
-        }))
+        delay.delay_ns(recovery);
+
+        Ok(ResetInfo {
+            presence: start.is_some(),
+            presence_start_us: start.map(ns_to_us),
+            presence_duration_us: start.map_or(0, |start| ns_to_us(end.unwrap_or(elapsed) - start)),
+        })
     }
 
-    /// Performs a reset and listens for a presence pulse
-    /// Returns Err(WireNotHigh) if the wire seems to be shortened,
-    /// Ok(true) if presence pulse has been received and Ok(false)
-    /// if no other device was detected but the wire seems to be ok
-    pub fn reset(&mut self, delay: &mut impl DelayUs<u16>) -> Result<bool, Error<E>> {
-        // let mut cli = DisableInterrupts::new();
+    fn reset_pulse(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
+        self.begin_reset_pulse(delay)?;
+        delay.delay_ns(self.active_timing().reset_low_ns());
         self.set_input()?;
-        // drop(cli);
+        Ok(())
+    }
 
+    /// Pulls the line low to start a reset, once it is high
+    pub(crate) fn begin_reset_pulse(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
+        self.set_input()?;
         self.ensure_wire_high(delay)?;
-        // cli = DisableInterrupts::new();
+
         self.write_low()?;
         self.set_output()?;
+        Ok(())
+    }
 
-        // drop(cli);
-        delay.delay_us(480);
-        // cli = DisableInterrupts::new();
-        self.set_input()?;
-
+    /// Waits for a presence pulse after the reset pulse has ended. The guard is only held
+    /// for each sample, not for the whole presence window of up to a few hundred
+    /// microseconds.
+    pub(crate) fn sample_presence(&mut self, delay: &mut impl DelayNs) -> Result<bool, E> {
         let mut val = false;
-        for _ in 0..7 {
-            delay.delay_us(10);
-            val |= !self.read()?;
+        for _ in 0..self.active_timing().presence_samples() {
+            delay.delay_ns(self.active_timing().presence_sample_interval_ns());
+            val |= !self.guarded(|wire| wire.read())?;
         }
-        // drop(cli);
-        delay.delay_us(410);
         Ok(val)
     }
 
-    fn ensure_wire_high(&mut self, delay: &mut impl DelayUs<u16>) -> Result<(), Error<E>> {
+    /// Runs a timing critical phase of a time slot within the guard
+    fn guarded<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.guard.enter();
+        let result = f(self);
+        self.guard.exit();
+        result
+    }
+
+    fn ensure_wire_high(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
         for _ in 0..125 {
             if self.read()? {
                 return Ok(());
@@ -461,14 +1376,35 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
         Err(Error::WireNotHigh)
     }
 
-    pub fn read_bytes(&mut self, delay: &mut impl DelayUs<u16>, dst: &mut [u8]) -> Result<(), E> {
+    pub fn read_bytes(&mut self, delay: &mut impl DelayNs, dst: &mut [u8]) -> Result<(), E> {
         for d in dst {
             *d = self.read_byte(delay)?;
         }
         Ok(())
     }
 
-    fn read_byte(&mut self, delay: &mut impl DelayUs<u16>) -> Result<u8, E> {
+    /// Reads `dst.len()` bytes followed by their CRC8 and validates it
+    pub fn read_bytes_crc8(
+        &mut self,
+        delay: &mut impl DelayNs,
+        dst: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.read_bytes(delay, dst)?;
+        let crc8 = self.read_byte(delay)?;
+        let computed = (self.crc8)(0u8, dst);
+        if computed != crc8 {
+            self.stats
+                .check_crc(Err(Error::CrcMismatch(computed, crc8)))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_byte(&mut self, delay: &mut impl DelayNs) -> Result<u8, E> {
+        self.stats.record_byte();
+        if let Some(master) = self.bus_master()? {
+            return master.read_byte();
+        }
         let mut byte = 0_u8;
         for _ in 0..8 {
             byte >>= 1;
@@ -479,23 +1415,88 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
         Ok(byte)
     }
 
-    fn read_bit(&mut self, delay: &mut impl DelayUs<u16>) -> Result<bool, E> {
-        // let cli = DisableInterrupts::new();
-        self.set_output()?;
-        self.write_low()?;
-        delay.delay_us(3);
-        self.set_input()?;
-        delay.delay_us(2); // was 10
-        let val = self.read();
-        // drop(cli);
-        delay.delay_us(61); // was 53
-        val
+    /// Sends the byte and returns the byte sampled meanwhile, least significant bit first:
+    /// the 1 bits are sent as read slots, which a device may pull to 0, the 0 bits as
+    /// write slots that read back as 0. `touch_byte(0xFF)` reads a byte, other values
+    /// read back unchanged if no device interferes.
+    pub fn touch_byte(&mut self, delay: &mut impl DelayNs, byte: u8) -> Result<u8, E> {
+        if byte == 0xFF {
+            return self.read_byte(delay);
+        }
+        self.stats.record_byte();
+        let mut sampled = 0_u8;
+        for i in 0..8 {
+            let mask = 1 << i;
+            if byte & mask == 0 {
+                self.write_bit(delay, false)?;
+            } else if self.read_bit(delay)? {
+                sampled |= mask;
+            }
+        }
+        if !self.parasite_mode {
+            self.disable_parasite_mode()?;
+        }
+        Ok(sampled)
+    }
+
+    /// Sends the bytes of the block and overwrites each with the byte sampled meanwhile,
+    /// see [`OneWire::touch_byte`]
+    pub fn touch_block(&mut self, delay: &mut impl DelayNs, block: &mut [u8]) -> Result<(), E> {
+        for byte in block {
+            *byte = self.touch_byte(delay, *byte)?;
+        }
+        Ok(())
+    }
+
+    /// Reads an address bit and its complement during a search and writes the direction
+    /// to continue in, see [`Triplet::new`]. Bus masters with a triplet command do this
+    /// in a single operation.
+    pub fn triplet(&mut self, delay: &mut impl DelayNs, direction: bool) -> Result<Triplet, E> {
+        if let Some(master) = self.bus_master()? {
+            return master.triplet(direction);
+        }
+        let bit = self.read_bit(delay)?;
+        let complement = self.read_bit(delay)?;
+        let triplet = Triplet::new(bit, complement, direction);
+        self.write_bit(delay, triplet.direction)?;
+        Ok(triplet)
+    }
+
+    /// Generates a read slot and returns the sampled bit
+    pub fn read_bit(&mut self, delay: &mut impl DelayNs) -> Result<bool, E> {
+        if let Some(master) = self.bus_master()? {
+            return master.read_bit();
+        }
+        let val = self.read_slot(delay)?;
+        delay.delay_ns(self.active_timing().read_recovery_ns());
+        self.check_released()?;
+        Ok(val)
     }
 
-    pub fn write_bytes(&mut self, delay: &mut impl DelayUs<u16>, bytes: &[u8]) -> Result<(), E> {
-        for b in bytes {
-            self.write_byte(delay, *b, false)?;
+    /// The timing critical part of a read slot, up to the start of the recovery time
+    pub(crate) fn read_slot(&mut self, delay: &mut impl DelayNs) -> Result<bool, E> {
+        if let Some(master) = self.bus_master()? {
+            return master.read_bit();
+        }
+        let window = self.active_timing().slot_windows().read_sample_point;
+        self.guard.phase(window);
+        let val = self.guarded(|wire| {
+            wire.write_low()?;
+            delay.delay_ns(wire.active_timing().read_low_ns());
+            wire.set_input()?;
+            delay.delay_ns(wire.active_timing().read_sample_ns());
+            wire.read()
+        })?;
+        if val {
+            // a device sending a 0 may still hold the line low
+            self.output.active_pull_up()?;
         }
+        Ok(val)
+    }
+
+    pub fn write_bytes(&mut self, delay: &mut impl DelayNs, bytes: &[u8]) -> Result<(), E> {
+        // the line is released after each slot anyway, so only release it once for the whole transfer
+        self.write_bytes_held(delay, bytes)?;
         if !self.parasite_mode {
             self.disable_parasite_mode()?;
         }
@@ -504,7 +1505,7 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
 
     fn write_command(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
+        delay: &mut impl DelayNs,
         cmd: Command,
         parasite_mode: bool,
     ) -> Result<(), E> {
@@ -513,35 +1514,120 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
 
     fn write_byte(
         &mut self,
-        delay: &mut impl DelayUs<u16>,
-        mut byte: u8,
+        delay: &mut impl DelayNs,
+        byte: u8,
         parasite_mode: bool,
     ) -> Result<(), E> {
+        self.write_byte_slots(delay, byte, parasite_mode && self.parasite_mode)?;
+        if !parasite_mode {
+            self.disable_parasite_mode()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the bytes without releasing the line in between, in parasite mode the strong
+    /// pull-up is only enabled after the last one
+    fn write_bytes_held(&mut self, delay: &mut impl DelayNs, bytes: &[u8]) -> Result<(), E> {
+        if let Some((last, bytes)) = bytes.split_last() {
+            for b in bytes {
+                self.write_byte_slots(delay, *b, false)?;
+            }
+            self.write_byte(delay, *last, true)?;
+        }
+        Ok(())
+    }
+
+    /// Generates the write slots of a byte and enables the strong pull-up afterwards if
+    /// requested
+    fn write_byte_slots(
+        &mut self,
+        delay: &mut impl DelayNs,
+        mut byte: u8,
+        strong_pull_up: bool,
+    ) -> Result<(), E> {
+        self.stats.record_byte();
+        if let Some(master) = self.bus_master()? {
+            return master.write_byte(byte, strong_pull_up);
+        }
         for _ in 0..8 {
             self.write_bit(delay, (byte & 0x01) == 0x01)?;
             byte >>= 1;
         }
-        if !parasite_mode {
-            self.disable_parasite_mode()?;
+        if strong_pull_up {
+            self.power_on()?;
         }
         Ok(())
     }
 
-    fn write_bit(&mut self, delay: &mut impl DelayUs<u16>, high: bool) -> Result<(), E> {
-        // let cli = DisableInterrupts::new();
-        self.write_low()?;
-        self.set_output()?;
-        delay.delay_us(if high { 10 } else { 65 });
-        self.write_high()?;
-        // drop(cli);
-        delay.delay_us(if high { 55 } else { 5 });
+    /// Generates a write slot
+    pub fn write_bit(&mut self, delay: &mut impl DelayNs, high: bool) -> Result<(), E> {
+        if let Some(master) = self.bus_master()? {
+            return master.write_bit(high);
+        }
+        let recovery = self.write_slot(delay, high)?;
+        delay.delay_ns(recovery);
+        self.check_released()
+    }
+
+    /// The timing critical part of a write slot, returns the recovery time in
+    /// nanoseconds to wait afterwards
+    pub(crate) fn write_slot(&mut self, delay: &mut impl DelayNs, high: bool) -> Result<u32, E> {
+        if let Some(master) = self.bus_master()? {
+            master.write_bit(high)?;
+            return Ok(0);
+        }
+        let windows = self.active_timing().slot_windows();
+        self.guard.phase(if high {
+            windows.write_one_low
+        } else {
+            windows.write_zero_low
+        });
+        self.guarded(|wire| {
+            wire.write_low()?;
+            delay.delay_ns(if high {
+                wire.active_timing().write_one_low_ns()
+            } else {
+                wire.active_timing().write_zero_low_ns()
+            });
+            wire.write_high()
+        })?;
+        self.output.active_pull_up()?;
+        Ok(if high {
+            self.active_timing().write_one_recovery_ns()
+        } else {
+            self.active_timing().write_zero_recovery_ns()
+        })
+    }
+
+    /// Flags a collision if the line is low at the end of a time slot, see
+    /// [`OneWire::with_collision_detection`]
+    pub(crate) fn check_released(&mut self) -> Result<(), E> {
+        if self.collision_detection && !self.read()? {
+            self.collision = true;
+        }
         Ok(())
     }
 
     fn disable_parasite_mode(&mut self) -> Result<(), E> {
-        // let cli = DisableInterrupts::new();
-        self.set_input()?;
-        self.write_low()
+        self.power_off()?;
+        // release the line, driving it low here would hold the bus in reset
+        self.set_input()
+    }
+
+    /// Enables the strong pull-up after a write in parasite mode
+    pub(crate) fn power_on(&mut self) -> Result<(), E> {
+        self.output.strong_pull_up(true)?;
+        self.powered = true;
+        Ok(())
+    }
+
+    /// Disables the strong pull-up if it is enabled
+    fn power_off(&mut self) -> Result<(), E> {
+        if self.powered {
+            self.output.strong_pull_up(false)?;
+            self.powered = false;
+        }
+        Ok(())
     }
 
     fn set_input(&mut self) -> Result<(), E> {
@@ -554,6 +1640,7 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
     }
 
     fn write_low(&mut self) -> Result<(), E> {
+        self.power_off()?;
         self.output.set_low()
     }
 
@@ -566,40 +1653,6 @@ impl<E: core::fmt::Debug, ODO: OpenDrainOutput<Error = E>> OneWire<ODO> {
     }
 }
 
-pub fn ensure_correct_rcr8<E: Debug>(
-    device: &Device,
-    data: &[u8],
-    crc8: u8,
-) -> Result<(), Error<E>> {
-    let computed = compute_crc8(device, data);
-    if computed != crc8 {
-        Err(Error::CrcMismatch(computed, crc8))
-    } else {
-        Ok(())
-    }
-}
-
-pub fn compute_crc8(device: &Device, data: &[u8]) -> u8 {
-    let crc = compute_partial_crc8(0u8, &device.address[..]);
-    compute_partial_crc8(crc, data)
-}
-
-pub fn compute_partial_crc8(crc: u8, data: &[u8]) -> u8 {
-    let mut crc = crc;
-    for byte in data.iter() {
-        let mut byte = *byte;
-        for _ in 0..8 {
-            let mix = (crc ^ byte) & 0x01;
-            crc >>= 1;
-            if mix != 0x00 {
-                crc ^= 0x8C;
-            }
-            byte >>= 1;
-        }
-    }
-    crc
-}
-
 impl Display for Device {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         write!(
@@ -617,26 +1670,169 @@ impl Display for Device {
     }
 }
 
-pub trait Sensor {
-    fn family_code() -> u8;
-
-    /// returns the milliseconds required to wait until the measurement finished
-    fn start_measurement<O: OpenDrainOutput>(
-        &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
-    ) -> Result<u16, Error<O::Error>>;
-
-    /// returns the measured value
-    fn read_measurement<O: OpenDrainOutput>(
-        &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
-    ) -> Result<f32, Error<O::Error>>;
-
-    fn read_measurement_raw<O: OpenDrainOutput>(
-        &self,
-        wire: &mut OneWire<O>,
-        delay: &mut impl DelayUs<u16>,
-    ) -> Result<u16, Error<O::Error>>;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{NoDelay, SearchBus};
+    use core::convert::Infallible;
+
+    /// A device answering every read slot with the same bit
+    struct Constant(bool);
+
+    impl BusMaster for Constant {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0)
+        }
+
+        fn write_bit(&mut self, _high: bool) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn write_byte(&mut self, _byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_touch_block() {
+        let mut delay = NoDelay;
+        let mut block = [0xFF, 0xA5, 0x00];
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        wire.touch_block(&mut delay, &mut block).unwrap();
+        assert_eq!(block, [0xFF, 0xA5, 0x00]);
+
+        let mut wire = OneWire::new(MasterPin(Constant(false)), false);
+        wire.touch_block(&mut delay, &mut block).unwrap();
+        assert_eq!(block, [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_reset_with_info_master() {
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        assert_eq!(
+            wire.reset_with_info(&mut NoDelay).unwrap(),
+            ResetInfo {
+                presence: true,
+                presence_start_us: None,
+                presence_duration_us: 0,
+            }
+        );
+        assert_eq!(wire.stats().resets, 1);
+    }
+
+    #[test]
+    fn test_released_pin_within_transaction() {
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        let result = wire.transaction(|wire| wire.with_released_pin(|_| ()));
+        assert!(matches!(result, Err(Error::Unsupported)));
+        assert!(wire.with_released_pin(|_| ()).is_ok());
+    }
+
+    #[test]
+    fn test_overdrive_unsupported() {
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        assert!(matches!(
+            wire.enter_overdrive(&mut NoDelay),
+            Err(Error::Unsupported)
+        ));
+        assert!(!wire.is_overdrive());
+    }
+
+    /// Claims every CRC8 to be 0xFF, what a released line reads
+    struct ReleasedLineCrc8;
+
+    impl Crc8Provider for ReleasedLineCrc8 {
+        fn crc8(_crc: u8, _data: &[u8]) -> u8 {
+            0xFF
+        }
+    }
+
+    #[test]
+    fn test_crc8_provider() {
+        let mut read = [0u8; 2];
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        assert!(wire.read_bytes_crc8(&mut NoDelay, &mut read).is_err());
+        let mut wire = wire.with_crc8::<ReleasedLineCrc8>();
+        wire.read_bytes_crc8(&mut NoDelay, &mut read).unwrap();
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut delay = NoDelay;
+        let mut wire = OneWire::new(MasterPin(Constant(true)), false);
+        assert!(wire.reset(&mut delay).unwrap());
+        wire.write_bytes(&mut delay, &[0xCC, 0xBE]).unwrap();
+        let mut read = [0u8; 2];
+        assert!(matches!(
+            wire.read_bytes_crc8(&mut delay, &mut read),
+            Err(Error::CrcMismatch(..))
+        ));
+        wire.record_retry();
+        assert_eq!(
+            wire.take_stats(),
+            BusStats {
+                resets: 1,
+                presence_failures: 0,
+                crc_mismatches: 1,
+                retries: 1,
+                bytes_transferred: 5,
+            }
+        );
+        assert_eq!(wire.stats(), &BusStats::default());
+    }
+
+    #[test]
+    fn test_search_triplets() {
+        let devices = [
+            Device {
+                address: [0x28, 0x01, 0, 0, 0, 0, 0, 0],
+            },
+            Device {
+                address: [0x28, 0x02, 0, 0, 0, 0, 0, 0],
+            },
+            Device {
+                address: [0x10, 0x01, 0, 0, 0, 0, 0, 0],
+            },
+        ];
+        let mut wire = OneWire::new(MasterPin(SearchBus::new(&devices)), false);
+        let mut delay = NoDelay;
+        let mut found = [false; 3];
+        let mut search = DeviceSearch::new();
+        while let Some(device) = wire.search_next(&mut search, &mut delay).unwrap() {
+            let index = devices.iter().position(|d| *d == device).unwrap();
+            assert!(!found[index]);
+            found[index] = true;
+        }
+        assert_eq!(found, [true; 3]);
+
+        let mut search = DeviceSearch::new_for_family(0x10);
+        let device = wire.search_next(&mut search, &mut delay).unwrap();
+        assert_eq!(device.as_ref(), Some(&devices[2]));
+        assert_eq!(wire.search_next(&mut search, &mut delay).unwrap(), None);
+    }
+
+    #[test]
+    fn test_short_id() {
+        let sensor = Device {
+            address: [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2],
+        };
+        let other = Device {
+            address: [0x28, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2],
+        };
+        assert_eq!(sensor.short_id(), ShortId(0x19FB));
+        assert_eq!(
+            find_short_id_collision(&[sensor.clone(), other.clone()]),
+            None
+        );
+        assert_eq!(
+            find_short_id_collision(&[sensor.clone(), other, sensor]),
+            Some((0, 2))
+        );
+    }
 }
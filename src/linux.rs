@@ -0,0 +1,170 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::compute_partial_crc8;
+use crate::AsDevice;
+use crate::Device;
+use crate::Error;
+use crate::DS18B20;
+
+/// Where the kernel lists the devices found by its bus masters
+pub const DEVICES_PATH: &str = "/sys/bus/w1/devices";
+
+/// Size of the scratchpad the w1_therm driver prints
+const SCRATCHPAD_SIZE: usize = 9;
+
+#[derive(Debug)]
+pub enum SysfsError {
+    Io(io::Error),
+    /// A sysfs file does not have the format printed by the kernel driver
+    Malformed,
+}
+
+impl From<io::Error> for SysfsError {
+    fn from(e: io::Error) -> Self {
+        SysfsError::Io(e)
+    }
+}
+
+/// The devices of the Linux kernel 1-Wire subsystem, e.g. on a Raspberry Pi with the
+/// w1-gpio overlay. The kernel runs the bus master and the search, the devices are
+/// accessed through their sysfs files instead of a [`OneWire`](crate::OneWire).
+///
+/// This is not a [`BusMaster`](crate::BusMaster): the kernel only offers complete
+/// transactions through its driver files, a reset followed by the selection and a block
+/// of bytes, not the individual resets and time slots a [`OneWire`](crate::OneWire) is
+/// built from. Only what the drivers expose is supported, the listing of the found
+/// devices and the temperature sensors of the w1_therm driver.
+#[derive(Debug, Clone)]
+pub struct W1Sysfs {
+    root: PathBuf,
+}
+
+impl Default for W1Sysfs {
+    fn default() -> Self {
+        W1Sysfs::new()
+    }
+}
+
+impl W1Sysfs {
+    pub fn new() -> Self {
+        W1Sysfs::with_root(DEVICES_PATH)
+    }
+
+    /// Uses another devices directory, e.g. of a mounted sysfs
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        W1Sysfs { root: root.into() }
+    }
+
+    /// Lists the devices the kernel found on all of its buses
+    pub fn devices(&self) -> io::Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            if let Some(device) = entry?.file_name().to_str().and_then(parse_name) {
+                devices.push(device);
+            }
+        }
+        Ok(devices)
+    }
+
+    /// Lets the w1_therm driver read the scratchpad of the sensor, which starts a
+    /// conversion and blocks until it finished. The CRC is verified.
+    pub fn read_scratchpad(
+        &self,
+        device: &Device,
+    ) -> Result<[u8; SCRATCHPAD_SIZE], Error<SysfsError>> {
+        let path = self.root.join(device_name(device)).join("w1_slave");
+        let content = fs::read_to_string(path).map_err(SysfsError::from)?;
+        // e.g. "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES"
+        let mut scratchpad = [0u8; SCRATCHPAD_SIZE];
+        let mut bytes = content.split_whitespace();
+        for byte in &mut scratchpad {
+            *byte = bytes
+                .next()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(SysfsError::Malformed)?;
+        }
+        let computed = compute_partial_crc8(0, &scratchpad[..SCRATCHPAD_SIZE - 1]);
+        if computed != scratchpad[SCRATCHPAD_SIZE - 1] {
+            return Err(Error::CrcMismatch(
+                computed,
+                scratchpad[SCRATCHPAD_SIZE - 1],
+            ));
+        }
+        Ok(scratchpad)
+    }
+
+    /// Measures the temperature of the sensor in degrees celsius, see
+    /// [`W1Sysfs::read_scratchpad`]. The kernel uses the resolution configured in the
+    /// sensor, the calibration offset of the sensor is applied.
+    pub fn read_temperature(&self, sensor: &DS18B20) -> Result<f32, Error<SysfsError>> {
+        let scratchpad = self.read_scratchpad(sensor.as_device())?;
+        Ok(sensor.to_celsius(DS18B20::read_temperature_from_scratchpad(&scratchpad)))
+    }
+}
+
+/// The name of the sysfs directory of a device, the family code and the serial number
+/// in hex, e.g. `28-0000012345ab`
+pub fn device_name(device: &Device) -> String {
+    let mut serial = [0u8; 8];
+    serial[..6].copy_from_slice(&device.address[1..7]);
+    std::format!(
+        "{:02x}-{:012x}",
+        device.family_code(),
+        u64::from_le_bytes(serial)
+    )
+}
+
+/// Parses the name of a sysfs device directory, the CRC of the address is recomputed
+pub fn parse_name(name: &str) -> Option<Device> {
+    let (family, serial) = name.split_at(name.find('-')?);
+    let serial = &serial[1..];
+    if family.len() != 2 || serial.len() != 12 {
+        return None;
+    }
+    let family = u8::from_str_radix(family, 16).ok()?;
+    let serial = u64::from_str_radix(serial, 16).ok()?.to_le_bytes();
+
+    let mut address = [0u8; 8];
+    address[0] = family;
+    address[1..7].copy_from_slice(&serial[..6]);
+    address[7] = compute_partial_crc8(0, &address[..7]);
+    Some(Device { address })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysfs_devices() {
+        let root = std::env::temp_dir().join(std::format!("w1-{}", std::process::id()));
+        let name = "28-0000012345ab";
+        fs::create_dir_all(root.join(name)).unwrap();
+        fs::create_dir_all(root.join("w1_bus_master1")).unwrap();
+        fs::write(
+            root.join(name).join("w1_slave"),
+            "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n",
+        )
+        .unwrap();
+
+        let sysfs = W1Sysfs::with_root(&root);
+        let devices = sysfs.devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(device_name(&devices[0]), name);
+        assert_eq!(devices[0].address[1], 0xAB);
+
+        let sensor = DS18B20::new(devices[0].clone()).unwrap();
+        assert_eq!(sysfs.read_temperature(&sensor).unwrap(), 23.125);
+
+        fs::write(root.join(name).join("w1_slave"), "72 01 4b\n").unwrap();
+        assert!(matches!(
+            sysfs.read_temperature(&sensor),
+            Err(Error::PortError(SysfsError::Malformed))
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
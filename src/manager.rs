@@ -0,0 +1,166 @@
+use hal1::delay::DelayNs;
+
+use crate::clock::is_due;
+use crate::Clock;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::Sensor;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SlotState {
+    /// Waiting until the next conversion may be started, `None` means as soon as possible
+    Idle(Option<u32>),
+    /// Conversion started, result can be read at the given tick
+    Converting(u32),
+}
+
+struct Slot<S> {
+    sensor: S,
+    state: SlotState,
+}
+
+/// Owns up to `N` sensors of the same kind and schedules their conversions.
+///
+/// The manager is meant to be driven from a superloop: call [`SensorManager::poll`]
+/// as often as possible with a millisecond tick, and it will start conversions
+/// once their interval elapsed and read them back once they are finished. At most
+/// `max_concurrent` conversions are in flight at the same time, which staggers the
+/// conversions and keeps parasite powered buses from browning out.
+pub struct SensorManager<S: Sensor, const N: usize> {
+    slots: [Option<Slot<S>>; N],
+    interval_ms: u32,
+    max_concurrent: usize,
+}
+
+impl<S: Sensor, const N: usize> SensorManager<S, N> {
+    /// Creates a manager that measures every sensor once every `interval_ms`,
+    /// converting only one sensor at a time
+    pub fn new(interval_ms: u32) -> Self {
+        SensorManager {
+            slots: [(); N].map(|_| None),
+            interval_ms,
+            max_concurrent: 1,
+        }
+    }
+
+    /// Limits how many conversions may be in flight at the same time.
+    /// A limit of `0` is treated as `1`.
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Adds a sensor and returns its index, or gives it back if the manager is full
+    pub fn add(&mut self, sensor: S) -> Result<usize, S> {
+        match self.slots.iter().position(Option::is_none) {
+            Some(index) => {
+                self.slots[index] = Some(Slot {
+                    sensor,
+                    state: SlotState::Idle(None),
+                });
+                Ok(index)
+            }
+            None => Err(sensor),
+        }
+    }
+
+    /// Removes the sensor at the given index
+    pub fn remove(&mut self, index: usize) -> Option<S> {
+        self.slots
+            .get_mut(index)
+            .and_then(Option::take)
+            .map(|slot| slot.sensor)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&S> {
+        self.slots
+            .get(index)
+            .and_then(Option::as_ref)
+            .map(|slot| &slot.sensor)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of conversions currently in flight
+    pub fn converting(&self) -> usize {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|slot| matches!(slot.state, SlotState::Converting(_)))
+            .count()
+    }
+
+    /// Advances the schedule and returns at most one result.
+    ///
+    /// Finished conversions are read back first. Otherwise, due sensors are started as
+    /// long as the concurrency limit permits. `now_ms` is a free running millisecond
+    /// tick, wrapping around is handled. Errors are reported together with the index
+    /// of the affected sensor, which is then rescheduled for the next interval.
+    #[allow(clippy::type_complexity)]
+    pub fn poll<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        now_ms: u32,
+    ) -> Option<(usize, Result<S::Measurement, Error<O::Error>>)> {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if let Some(slot) = slot {
+                if let SlotState::Converting(ready_at) = slot.state {
+                    if is_due(now_ms, ready_at) {
+                        slot.state = SlotState::Idle(Some(now_ms.wrapping_add(self.interval_ms)));
+                        return Some((index, slot.sensor.read_measurement(wire, delay)));
+                    }
+                }
+            }
+        }
+
+        let mut converting = self.converting();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if converting >= self.max_concurrent {
+                break;
+            }
+            if let Some(slot) = slot {
+                if let SlotState::Idle(next_start) = slot.state {
+                    if next_start.is_none_or(|at| is_due(now_ms, at)) {
+                        match slot.sensor.start_measurement(wire, delay) {
+                            Ok(wait_ms) => {
+                                slot.state =
+                                    SlotState::Converting(now_ms.wrapping_add(u32::from(wait_ms)));
+                                converting += 1;
+                            }
+                            Err(e) => {
+                                slot.state =
+                                    SlotState::Idle(Some(now_ms.wrapping_add(self.interval_ms)));
+                                return Some((index, Err(e)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Same as [`SensorManager::poll`], but takes the tick from the given clock and
+    /// returns each measurement together with the tick at which reading it completed
+    #[allow(clippy::type_complexity)]
+    pub fn poll_timestamped<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        clock: &mut impl Clock,
+    ) -> Option<(usize, Result<(S::Measurement, u32), Error<O::Error>>)> {
+        let now_ms = clock.now_ms();
+        self.poll(wire, delay, now_ms)
+            .map(|(index, result)| (index, result.map(|value| (value, clock.now_ms()))))
+    }
+}
@@ -0,0 +1,98 @@
+use core::fmt::Debug;
+
+use crate::Error;
+use crate::OpenDrainOutput;
+use crate::Triplet;
+
+/// A bus master that generates the resets and time slots on its own, like an I2C bridge.
+///
+/// Such a master is used as the "pin" of a [`OneWire`](crate::OneWire) by returning it
+/// from [`OpenDrainOutput::bus_master`]. The bus then
+/// forwards its resets, bits and bytes instead of bit-banging them, so all device drivers
+/// work unchanged. The timing profile and the delay are not used for these, a delay
+/// still needs to be passed for conversions and EEPROM copies.
+/// [`OneWire::reset_with_info`](crate::OneWire::reset_with_info) only reports the presence
+/// for such a master, as it cannot measure the presence pulse.
+pub trait BusMaster {
+    type Error: Sized + Debug;
+
+    /// Generates a reset and returns whether a presence pulse has been received.
+    /// Returns [`Error::WireNotHigh`] if the master detected a short.
+    fn reset(&mut self) -> Result<bool, Error<Self::Error>>;
+
+    /// Generates a read slot and returns the sampled bit
+    fn read_bit(&mut self) -> Result<bool, Self::Error>;
+
+    /// Generates a write slot
+    fn write_bit(&mut self, high: bool) -> Result<(), Self::Error>;
+
+    /// Reads a byte, least significant bit first
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        let mut byte = 0_u8;
+        for _ in 0..8 {
+            byte >>= 1;
+            if self.read_bit()? {
+                byte |= 0x80;
+            }
+        }
+        Ok(byte)
+    }
+
+    /// Writes a byte, least significant bit first. With `strong_pull_up` the bus is
+    /// actively powered after the byte for parasite powered devices, until the next
+    /// reset or transfer.
+    fn write_byte(&mut self, byte: u8, strong_pull_up: bool) -> Result<(), Self::Error>;
+
+    /// Reads an address bit and its complement during a search and writes the direction
+    /// to continue in, see [`Triplet::new`]
+    fn triplet(&mut self, direction: bool) -> Result<Triplet, Self::Error> {
+        let bit = self.read_bit()?;
+        let complement = self.read_bit()?;
+        let triplet = Triplet::new(bit, complement, direction);
+        self.write_bit(triplet.direction)?;
+        Ok(triplet)
+    }
+
+    /// Whether the master can generate resets and time slots at overdrive speed. The bus
+    /// refuses to switch the devices to overdrive with [`Error::Unsupported`] otherwise.
+    fn supports_overdrive(&self) -> bool {
+        false
+    }
+
+    /// Switches the following resets and time slots between standard and overdrive speed.
+    /// Called before each of them, so the master should only act on changes. Does nothing
+    /// by default, for masters not capable of overdrive.
+    fn set_overdrive(&mut self, _overdrive: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Makes a [`BusMaster`] usable as the pin of a [`OneWire`](crate::OneWire), for masters
+/// that don't implement [`OpenDrainOutput`] themselves
+#[derive(Debug)]
+pub struct MasterPin<M>(pub M);
+
+/// The pin methods are not used by the bus
+impl<M: BusMaster> OpenDrainOutput for MasterPin<M> {
+    type Error = M::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        Some(&mut self.0)
+    }
+}
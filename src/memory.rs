@@ -0,0 +1,164 @@
+use hal1::delay::DelayNs;
+
+use crate::crc::Crc16;
+use crate::Device;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// A device with a memory that is read with the Read Memory command followed by a two
+/// byte target address, like the DS2431 or the DS28EC20 EEPROMs.
+///
+/// Reads may start at any target address. The device keeps sending the following bytes
+/// until the end of its memory, so a read can span pages.
+pub trait MemoryDevice {
+    fn device(&self) -> &Device;
+
+    /// The device to address, `None` to address the only device on the bus with Skip ROM,
+    /// see [`SingleDrop`](crate::SingleDrop)
+    fn target(&self) -> Option<&Device> {
+        Some(self.device())
+    }
+
+    /// Size of the memory in bytes
+    fn memory_size() -> u16;
+
+    /// Size of a memory page in bytes
+    fn page_size() -> u16;
+
+    fn read_memory_command() -> u8 {
+        0xF0
+    }
+
+    /// The command that reads the memory like Read Memory, but ends every page with an
+    /// inverted CRC16, if the device supports it. The CRC of the first page covers the
+    /// command and the target address as well.
+    fn read_memory_crc16_command() -> Option<u8> {
+        None
+    }
+
+    /// Reads `dst.len()` bytes starting at the given target address.
+    /// Panics if the read exceeds the memory.
+    fn read_memory<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        address: u16,
+        dst: &mut [u8],
+    ) -> Result<(), Error<O::Error>> {
+        assert!(usize::from(address) + dst.len() <= usize::from(Self::memory_size()));
+        let [low, high] = address.to_le_bytes();
+        wire.reset_select_or_skip_write_read(
+            delay,
+            self.target(),
+            &[Self::read_memory_command(), low, high],
+            dst,
+        )
+    }
+
+    /// Reads `len` bytes starting at the given target address in a single transaction and
+    /// hands them to `f` chunk by chunk together with their target address, so large
+    /// memories can be copied without a buffer of their size.
+    ///
+    /// Chunks are `buf.len()` bytes long. If the device supports reading with CRC16, the
+    /// chunks end at the page boundaries instead and each one is validated before it is
+    /// handed out, which requires `buf` to hold a full page. Panics if the read exceeds
+    /// the memory or `buf` is too short.
+    fn read_memory_chunked<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        address: u16,
+        len: u16,
+        buf: &mut [u8],
+        mut f: impl FnMut(u16, &[u8]),
+    ) -> Result<(), Error<O::Error>> {
+        let end = usize::from(address) + usize::from(len);
+        assert!(end <= usize::from(Self::memory_size()));
+        assert!(!buf.is_empty());
+        let crc16_command = Self::read_memory_crc16_command();
+        let command = crc16_command.unwrap_or_else(Self::read_memory_command);
+        let [low, high] = address.to_le_bytes();
+
+        wire.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, self.target())?;
+            wire.write_bytes(delay, &[command, low, high])?;
+
+            let page_size = usize::from(Self::page_size());
+            let mut crc = Crc16::new();
+            crc.update(&[command, low, high]);
+            let mut address = usize::from(address);
+            while address < end {
+                if crc16_command.is_some() {
+                    // the device sends the remainder of the page before the CRC
+                    let chunk = page_size - address % page_size;
+                    assert!(buf.len() >= chunk);
+                    wire.read_bytes(delay, &mut buf[..chunk])?;
+                    let mut crc16 = [0u8; 2];
+                    wire.read_bytes(delay, &mut crc16)?;
+                    crc.update(&buf[..chunk]);
+                    wire.stats.check_crc(crc.verify_inverted(crc16))?;
+                    crc = Crc16::new();
+                    f(address as u16, &buf[..chunk.min(end - address)]);
+                    address += chunk;
+                } else {
+                    let chunk = buf.len().min(end - address);
+                    wire.read_bytes(delay, &mut buf[..chunk])?;
+                    f(address as u16, &buf[..chunk]);
+                    address += chunk;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A cursor over the memory of a [`MemoryDevice`].
+///
+/// The position only advances after a successful read, so a read that failed, e.g. due
+/// to a CRC error on a later transaction or a disconnected probe, is simply repeated by
+/// calling [`MemoryReader::read`] again, instead of starting over at address zero.
+pub struct MemoryReader<'a, M> {
+    device: &'a M,
+    address: u16,
+}
+
+impl<'a, M: MemoryDevice> MemoryReader<'a, M> {
+    pub fn new(device: &'a M, address: u16) -> Self {
+        MemoryReader { device, address }
+    }
+
+    /// The target address of the next read
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    pub fn seek(&mut self, address: u16) {
+        self.address = address;
+    }
+
+    /// Number of bytes left until the end of the memory
+    pub fn remaining(&self) -> u16 {
+        M::memory_size().saturating_sub(self.address)
+    }
+
+    /// Reads the next bytes into `dst` and returns how many have been read, which is
+    /// less than `dst.len()` at the end of the memory
+    pub fn read<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        dst: &mut [u8],
+    ) -> Result<usize, Error<O::Error>> {
+        let len = dst.len().min(usize::from(self.remaining()));
+        if len > 0 {
+            self.device
+                .read_memory(wire, delay, self.address, &mut dst[..len])?;
+            self.address += len as u16;
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,125 @@
+use hal1::delay::DelayNs;
+
+use crate::clock::is_due;
+use crate::Clock;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::Sensor;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Tracks a pending long operation, like a conversion, on each of `N` buses, so a gateway
+/// with several 1-Wire ports can service one bus while the others are busy.
+#[derive(Debug)]
+pub struct Pipeline<const N: usize> {
+    /// The tick at which the operation of each bus finishes
+    ready_at: [Option<u32>; N],
+}
+
+impl<const N: usize> Pipeline<N> {
+    pub fn new() -> Self {
+        Pipeline {
+            ready_at: [None; N],
+        }
+    }
+
+    /// Records that the operation on the given bus finishes at the given tick, replacing
+    /// a pending one. Panics if `bus` is not below `N`.
+    pub fn schedule(&mut self, bus: usize, ready_at: u32) {
+        self.ready_at[bus] = Some(ready_at);
+    }
+
+    pub fn is_pending(&self, bus: usize) -> bool {
+        self.ready_at.get(bus).is_some_and(Option::is_some)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ready_at.iter().all(Option::is_none)
+    }
+
+    /// The tick at which the next pending operation finishes
+    pub fn next_ready_at(&self, now_ms: u32) -> Option<u32> {
+        self.ready_at
+            .iter()
+            .flatten()
+            .min_by_key(|at| at.wrapping_sub(now_ms) as i32)
+            .copied()
+    }
+
+    /// Returns the bus whose operation finished first and is no longer pending, `None`
+    /// if none of them finished yet
+    pub fn take_due(&mut self, now_ms: u32) -> Option<usize> {
+        let (bus, _) = self
+            .ready_at
+            .iter()
+            .enumerate()
+            .filter_map(|(bus, at)| Some((bus, (*at)?)))
+            .filter(|(_, at)| is_due(now_ms, *at))
+            .min_by_key(|(_, at)| at.wrapping_sub(now_ms) as i32)?;
+        self.ready_at[bus] = None;
+        Some(bus)
+    }
+}
+
+impl<const N: usize> Default for Pipeline<N> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+/// Starts a measurement on all sensors of type `S` on each bus, then calls `read` for each
+/// bus as soon as its conversions finished, so the conversions of all buses overlap
+/// instead of adding up. The idle hook is called while no bus is ready.
+pub fn measure_all<S, O, T, G, D, const N: usize>(
+    buses: &mut [OneWire<O, T, G>; N],
+    delay: &mut D,
+    clock: &mut impl Clock,
+    idle: &mut impl Idle,
+    mut read: impl FnMut(usize, &mut OneWire<O, T, G>, &mut D) -> Result<(), Error<O::Error>>,
+) -> Result<(), Error<O::Error>>
+where
+    S: Sensor,
+    O: OpenDrainOutput,
+    T: TimingProfile,
+    G: TimingGuard,
+    D: DelayNs,
+{
+    let mut pipeline = Pipeline::<N>::new();
+    for (index, bus) in buses.iter_mut().enumerate() {
+        let wait_ms = S::start_measurement_all(bus, delay)?;
+        pipeline.schedule(index, clock.now_ms().wrapping_add(u32::from(wait_ms)));
+    }
+    while !pipeline.is_empty() {
+        let now = clock.now_ms();
+        match pipeline.take_due(now) {
+            Some(index) => read(index, &mut buses[index], delay)?,
+            None => {
+                if let Some(at) = pipeline.next_ready_at(now) {
+                    let wait_ms = at.wrapping_sub(now).min(u32::from(u16::MAX));
+                    idle.idle(wait_ms as u16, delay);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::<3>::new();
+        pipeline.schedule(0, 750);
+        pipeline.schedule(2, 100);
+        assert_eq!(pipeline.next_ready_at(0), Some(100));
+        assert_eq!(pipeline.take_due(50), None);
+        assert_eq!(pipeline.take_due(800), Some(2));
+        assert!(!pipeline.is_pending(2));
+        assert_eq!(pipeline.take_due(800), Some(0));
+        assert!(pipeline.is_empty());
+    }
+}
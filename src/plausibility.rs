@@ -0,0 +1,143 @@
+use core::ops::Sub;
+
+/// Why a reading has been rejected by a [`PlausibilityCheck`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Implausible {
+    /// The reading is outside of the configured range
+    OutOfRange,
+    /// The reading differs from the previous accepted one by more than the configured step,
+    /// and has not been confirmed by enough consistent readings yet
+    Jump,
+}
+
+/// Rejects readings outside of a range or changing faster than physically possible,
+/// which catches artifacts like the 85°C power on value of a DS18B20 before they reach
+/// the control logic.
+///
+/// A genuine step change, e.g. a sensor moved into another medium, is accepted as the
+/// new level once it has been confirmed by a number of consecutive readings within the
+/// step of each other, see [`PlausibilityCheck::with_settle_readings`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlausibilityCheck<M> {
+    min: M,
+    max: M,
+    max_step: Option<M>,
+    last: Option<M>,
+    settle_readings: u8,
+    /// The rejected level and the number of consecutive readings confirming it
+    pending: Option<(M, u8)>,
+}
+
+/// Default number of consistent readings to accept a new level, see
+/// [`PlausibilityCheck::with_settle_readings`]
+pub const DEFAULT_SETTLE_READINGS: u8 = 3;
+
+impl<M: Copy + PartialOrd + Sub<Output = M>> PlausibilityCheck<M> {
+    /// Accepts readings between `min` and `max`, both inclusive
+    pub fn new(min: M, max: M) -> Self {
+        PlausibilityCheck {
+            min,
+            max,
+            max_step: None,
+            last: None,
+            settle_readings: DEFAULT_SETTLE_READINGS,
+            pending: None,
+        }
+    }
+
+    /// Also rejects readings differing from the previous accepted one by more than
+    /// `max_step`
+    pub fn with_max_step(mut self, max_step: M) -> Self {
+        self.max_step = Some(max_step);
+        self
+    }
+
+    /// Accepts a jump as the new level after `readings` consecutive readings within the
+    /// step of each other, including the first one of the new level. `0` never accepts a
+    /// jump.
+    pub fn with_settle_readings(mut self, readings: u8) -> Self {
+        self.settle_readings = readings;
+        self
+    }
+
+    /// The last accepted reading
+    pub fn last(&self) -> Option<M> {
+        self.last
+    }
+
+    /// Forgets the last accepted reading, e.g. after a long pause
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.pending = None;
+    }
+
+    /// Returns the reading if it is plausible and remembers it for the next check
+    pub fn check(&mut self, value: M) -> Result<M, Implausible> {
+        if value < self.min || value > self.max {
+            return Err(Implausible::OutOfRange);
+        }
+        if let (Some(max_step), Some(last)) = (self.max_step, self.last) {
+            if Self::step(value, last) > max_step {
+                let readings = match self.pending {
+                    Some((pending, readings)) if Self::step(value, pending) <= max_step => {
+                        readings.saturating_add(1)
+                    }
+                    _ => 1,
+                };
+                if self.settle_readings == 0 || readings < self.settle_readings {
+                    self.pending = Some((value, readings));
+                    return Err(Implausible::Jump);
+                }
+            }
+        }
+        self.last = Some(value);
+        self.pending = None;
+        Ok(value)
+    }
+
+    fn step(a: M, b: M) -> M {
+        if a > b {
+            a - b
+        } else {
+            b - a
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plausibility_check() {
+        let mut check = PlausibilityCheck::new(-55.0, 125.0).with_max_step(5.0);
+        assert_eq!(check.check(21.5), Ok(21.5));
+        assert_eq!(check.check(85.0), Err(Implausible::Jump));
+        assert_eq!(check.check(-127.0), Err(Implausible::OutOfRange));
+        assert_eq!(check.check(22.0), Ok(22.0));
+        check.reset();
+        assert_eq!(check.check(85.0), Ok(85.0));
+    }
+
+    #[test]
+    fn test_step_change() {
+        let mut check = PlausibilityCheck::new(-55.0, 125.0).with_max_step(5.0);
+        assert_eq!(check.check(21.5), Ok(21.5));
+        assert_eq!(check.check(60.0), Err(Implausible::Jump));
+        // an outlier in between restarts the confirmation
+        assert_eq!(check.check(85.0), Err(Implausible::Jump));
+        assert_eq!(check.check(60.5), Err(Implausible::Jump));
+        assert_eq!(check.check(61.0), Err(Implausible::Jump));
+        assert_eq!(check.check(61.5), Ok(61.5));
+        assert_eq!(check.last(), Some(61.5));
+        assert_eq!(check.check(62.0), Ok(62.0));
+
+        let mut check = PlausibilityCheck::new(-55.0, 125.0)
+            .with_max_step(5.0)
+            .with_settle_readings(0);
+        assert_eq!(check.check(21.5), Ok(21.5));
+        for _ in 0..10 {
+            assert_eq!(check.check(60.0), Err(Implausible::Jump));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use hal1::delay::DelayNs;
+
+use crate::DefaultTiming;
+use crate::Error;
+use crate::NoGuard;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimestampSource;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    ResetLow {
+        started: u32,
+    },
+    ResetRecovery {
+        started: u32,
+        presence: bool,
+    },
+    Write {
+        byte: u8,
+        bit: u8,
+        started: u32,
+        recovery: u32,
+    },
+    Read {
+        byte: u8,
+        bit: u8,
+        started: u32,
+    },
+}
+
+/// A bus for superloops, whose operations return [`nb::Error::WouldBlock`] instead of
+/// waiting and advance each time they are polled again, e.g. on a timer compare.
+///
+/// The reset pulse and the recovery time of each slot are measured with the timestamp
+/// source and don't block. The timing critical phases of the slots and the presence
+/// detection, which last up to about 70µs, are still performed with the blocking delay.
+///
+/// An operation has to be polled until it completes before another one is started,
+/// polling a different operation in between panics.
+#[derive(Debug)]
+pub struct NbOneWire<
+    ODO: OpenDrainOutput,
+    D,
+    C,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
+    wire: OneWire<ODO, T, G>,
+    delay: D,
+    clock: C,
+    state: State,
+}
+
+impl<E, ODO, D, C, T, G> NbOneWire<ODO, D, C, T, G>
+where
+    E: core::fmt::Debug,
+    ODO: OpenDrainOutput<Error = E>,
+    D: DelayNs,
+    C: TimestampSource,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    pub fn new(wire: OneWire<ODO, T, G>, delay: D, clock: C) -> Self {
+        NbOneWire {
+            wire,
+            delay,
+            clock,
+            state: State::Idle,
+        }
+    }
+
+    pub fn into_parts(self) -> (OneWire<ODO, T, G>, D, C) {
+        (self.wire, self.delay, self.clock)
+    }
+
+    /// Whether no operation is in progress
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
+
+    /// Same as [`OneWire::reset`]
+    pub fn reset(&mut self) -> nb::Result<bool, Error<E>> {
+        match self.state {
+            State::Idle => {
+                let master = match self.wire.bus_master() {
+                    Ok(master) => master,
+                    Err(e) => {
+                        self.wire.stats.record_reset(false);
+                        return Err(Error::from(e).into());
+                    }
+                };
+                if let Some(master) = master {
+                    // the master blocks for the whole reset
+                    let result = master.reset();
+                    self.wire.stats.record_reset(matches!(result, Ok(true)));
+                    return result.map_err(nb::Error::Other);
+                }
+                if let Err(e) = self.wire.begin_reset_pulse(&mut self.delay) {
+                    self.wire.stats.record_reset(false);
+                    return Err(e.into());
+                }
+                self.state = State::ResetLow {
+                    started: self.clock.now_us(),
+                };
+                Err(nb::Error::WouldBlock)
+            }
+            State::ResetLow { started } => {
+                self.wait(started, self.wire.active_timing().reset_low_ns())?;
+                self.wire.set_input().map_err(Error::from)?;
+                let presence = self
+                    .wire
+                    .sample_presence(&mut self.delay)
+                    .map_err(Error::from)?;
+                self.state = State::ResetRecovery {
+                    started: self.clock.now_us(),
+                    presence,
+                };
+                Err(nb::Error::WouldBlock)
+            }
+            State::ResetRecovery { started, presence } => {
+                self.wait(started, self.wire.active_timing().reset_recovery_ns())?;
+                self.state = State::Idle;
+                self.wire.stats.record_reset(presence);
+                Ok(presence)
+            }
+            state => panic!("reset polled during {:?}", state),
+        }
+    }
+
+    /// Writes a byte, the line is released afterwards unless the bus is in parasite mode, which
+    /// enables the strong pull-up instead
+    pub fn write_byte(&mut self, byte: u8) -> nb::Result<(), Error<E>> {
+        match self.state {
+            State::Idle => self.write_slot(byte, 0),
+            State::Write {
+                byte: current,
+                bit,
+                started,
+                recovery,
+            } if current == byte => {
+                self.wait(started, recovery)?;
+                self.wire.check_released().map_err(Error::from)?;
+                if bit < 7 {
+                    return self.write_slot(byte, bit + 1);
+                }
+                self.state = State::Idle;
+                self.wire.stats.record_byte();
+                if self.wire.parasite_mode {
+                    self.wire.power_on().map_err(Error::from)?;
+                } else {
+                    self.wire.disable_parasite_mode().map_err(Error::from)?;
+                }
+                Ok(())
+            }
+            state => panic!("write_byte polled during {:?}", state),
+        }
+    }
+
+    pub fn read_byte(&mut self) -> nb::Result<u8, Error<E>> {
+        match self.state {
+            State::Idle => self.read_slot(0, 0),
+            State::Read { byte, bit, started } => {
+                self.wait(started, self.wire.active_timing().read_recovery_ns())?;
+                self.wire.check_released().map_err(Error::from)?;
+                if bit < 7 {
+                    return self.read_slot(byte, bit + 1);
+                }
+                self.state = State::Idle;
+                self.wire.stats.record_byte();
+                Ok(byte)
+            }
+            state => panic!("read_byte polled during {:?}", state),
+        }
+    }
+
+    fn write_slot<R>(&mut self, byte: u8, bit: u8) -> nb::Result<R, Error<E>> {
+        let high = byte & (1 << bit) != 0;
+        let recovery = self
+            .wire
+            .write_slot(&mut self.delay, high)
+            .map_err(Error::from)?;
+        self.state = State::Write {
+            byte,
+            bit,
+            started: self.clock.now_us(),
+            recovery,
+        };
+        Err(nb::Error::WouldBlock)
+    }
+
+    fn read_slot<R>(&mut self, mut byte: u8, bit: u8) -> nb::Result<R, Error<E>> {
+        if self.wire.read_slot(&mut self.delay).map_err(Error::from)? {
+            byte |= 1 << bit;
+        }
+        self.state = State::Read {
+            byte,
+            bit,
+            started: self.clock.now_us(),
+        };
+        Err(nb::Error::WouldBlock)
+    }
+
+    /// Blocks the operation until `us` elapsed since `started`
+    fn wait(&mut self, started: u32, ns: u32) -> nb::Result<(), Error<E>> {
+        if self.clock.now_us().wrapping_sub(started) < ns.div_ceil(1000) {
+            Err(nb::Error::WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{IdleLine, NoDelay};
+    use core::cell::Cell;
+
+    #[test]
+    fn test_poll_until_complete() {
+        let now = Cell::new(0u32);
+        let clock = || {
+            now.set(now.get().wrapping_add(10));
+            now.get()
+        };
+        let mut wire = NbOneWire::new(OneWire::new(IdleLine, false), NoDelay, clock);
+
+        assert!(!nb::block!(wire.reset()).unwrap());
+        nb::block!(wire.write_byte(0xA5)).unwrap();
+        assert!(wire.is_idle());
+        assert_eq!(nb::block!(wire.read_byte()).unwrap(), 0xFF);
+        assert!(wire.is_idle());
+    }
+}
@@ -0,0 +1,193 @@
+use hal1::delay::DelayNs;
+
+use crate::clock::is_due;
+use crate::Clock;
+use crate::ConversionHandle;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::Sensor;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Tracks the conversions in flight on a parasite powered bus and limits how many run at
+/// the same time, since the strong pull-up can only supply a few converting devices.
+///
+/// A broadcast Convert T to 20 parasite powered DS18B20 browns out most buses. Accounting
+/// for broadcasts with [`PowerBudget::try_reserve_n`] defers the conversions exceeding the
+/// limit until earlier ones finished, while [`PowerBudget::start_conversion`] runs the
+/// conversions of single devices one at a time.
+#[derive(Debug)]
+pub struct PowerBudget<const N: usize> {
+    /// The ticks at which the conversions in flight finish
+    ready_at: [Option<u32>; N],
+    limit: usize,
+}
+
+impl<const N: usize> PowerBudget<N> {
+    /// Creates a budget allowing up to `N` conversions at the same time
+    pub fn new() -> Self {
+        const { assert!(N > 0, "a power budget needs room for a conversion") };
+        PowerBudget {
+            ready_at: [None; N],
+            limit: N,
+        }
+    }
+
+    /// Lowers the number of conversions allowed at the same time, at most `N` and at
+    /// least one
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.clamp(1, N);
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of conversions in flight at the given tick
+    pub fn active(&mut self, now_ms: u32) -> usize {
+        self.expire(now_ms);
+        self.ready_at.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Whether another conversion may be started at the given tick
+    pub fn is_available(&mut self, now_ms: u32) -> bool {
+        self.active(now_ms) < self.limit
+    }
+
+    /// The tick at which the next conversion in flight finishes, `None` if there is none
+    pub fn next_ready_at(&mut self, now_ms: u32) -> Option<u32> {
+        self.expire(now_ms);
+        self.ready_at
+            .iter()
+            .flatten()
+            .min_by_key(|at| at.wrapping_sub(now_ms))
+            .copied()
+    }
+
+    /// Accounts for a conversion taking `wait_ms` started at `now_ms`. Returns `false`
+    /// without accounting for it if the limit is reached.
+    pub fn try_reserve(&mut self, now_ms: u32, wait_ms: u16) -> bool {
+        self.try_reserve_n(now_ms, wait_ms, 1)
+    }
+
+    /// Accounts for `n` conversions taking `wait_ms` started at `now_ms`, e.g. by a
+    /// broadcast Convert T to `n` devices. Returns `false` without accounting for any of
+    /// them if they would exceed the limit.
+    pub fn try_reserve_n(&mut self, now_ms: u32, wait_ms: u16, n: usize) -> bool {
+        if self.active(now_ms) + n > self.limit {
+            return false;
+        }
+        let ready_at = now_ms.wrapping_add(u32::from(wait_ms));
+        for slot in self
+            .ready_at
+            .iter_mut()
+            .filter(|slot| slot.is_none())
+            .take(n)
+        {
+            *slot = Some(ready_at);
+        }
+        true
+    }
+
+    /// Starts a conversion of the sensor if no other conversion is in flight, returns
+    /// `None` otherwise.
+    ///
+    /// On a parasite powered bus, the strong pull-up after the Convert T of a device only
+    /// lasts until the next reset, so conversions started one after the other can't
+    /// overlap, the next one cuts the power of the previous. These are therefore limited
+    /// to one at a time regardless of [`PowerBudget::limit`]. Conversions meant to run at
+    /// the same time have to be started with a single broadcast Convert T, which is
+    /// accounted for with [`PowerBudget::try_reserve_n`].
+    pub fn start_conversion<'s, S: Sensor, O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        sensor: &'s S,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        clock: &mut impl Clock,
+    ) -> Result<Option<ConversionHandle<'s, S>>, Error<O::Error>> {
+        if self.active(clock.now_ms()) > 0 {
+            return Ok(None);
+        }
+        let handle = sensor.start_conversion(wire, delay, clock)?;
+        self.try_reserve(handle.started_at(), handle.wait_ms());
+        Ok(Some(handle))
+    }
+
+    fn expire(&mut self, now_ms: u32) {
+        for slot in self.ready_at.iter_mut() {
+            if slot.is_some_and(|at| is_due(now_ms, at)) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for PowerBudget<N> {
+    fn default() -> Self {
+        PowerBudget::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{AddressGenerator, NoDelay, SearchBus};
+    use crate::{MasterPin, DS18B20};
+    use core::cell::Cell;
+
+    #[test]
+    fn test_power_budget() {
+        let mut budget = PowerBudget::<4>::new().with_limit(2);
+        let start = u32::MAX - 100;
+        assert!(budget.try_reserve(start, 750));
+        assert!(budget.try_reserve(start + 50, 750));
+        assert!(!budget.try_reserve(start + 60, 750));
+        assert_eq!(
+            budget.next_ready_at(start + 60),
+            Some(start.wrapping_add(750))
+        );
+
+        // wraps around the tick counter
+        let first_ready = start.wrapping_add(750);
+        assert_eq!(budget.active(first_ready), 1);
+        assert!(budget.try_reserve(first_ready, 750));
+        assert_eq!(budget.active(first_ready.wrapping_add(800)), 0);
+    }
+
+    #[test]
+    fn test_reserve_broadcast() {
+        let mut budget = PowerBudget::<4>::new().with_limit(3);
+        assert!(budget.try_reserve(0, 750));
+        assert!(!budget.try_reserve_n(10, 750, 3));
+        assert_eq!(budget.active(10), 1);
+        assert!(budget.try_reserve_n(10, 750, 2));
+        assert_eq!(budget.active(10), 3);
+        assert_eq!(budget.active(750), 2);
+        assert_eq!(budget.active(760), 0);
+    }
+
+    #[test]
+    fn test_start_conversions_one_at_a_time() {
+        let devices = [
+            AddressGenerator::for_family(1, 0x28).next_address(),
+            AddressGenerator::for_family(2, 0x28).next_address(),
+        ];
+        let first = DS18B20::new(devices[0].clone()).unwrap();
+        let second = DS18B20::new(devices[1].clone()).unwrap();
+        let mut wire = OneWire::new(MasterPin(SearchBus::new(&devices)), true);
+        let now = Cell::new(0u32);
+        let mut clock = || now.get();
+        let mut budget = PowerBudget::<4>::new().with_limit(2);
+
+        let handle = budget.start_conversion(&first, &mut wire, &mut NoDelay, &mut clock);
+        let wait_ms = handle.unwrap().unwrap().wait_ms();
+        // the reset of the next conversion would cut the power of the first one
+        let handle = budget.start_conversion(&second, &mut wire, &mut NoDelay, &mut clock);
+        assert!(handle.unwrap().is_none());
+        now.set(u32::from(wait_ms));
+        let handle = budget.start_conversion(&second, &mut wire, &mut NoDelay, &mut clock);
+        assert!(handle.unwrap().is_some());
+    }
+}
@@ -0,0 +1,141 @@
+use hal::digital::v2::OutputPin;
+
+use crate::BusMaster;
+use crate::OpenDrainOutput;
+
+/// A pin with a second output switching a strong pull-up, e.g. the gate of a MOSFET
+/// between the line and the supply. Parasite powered devices draw more current than the
+/// pull-up resistor provides while converting a temperature or copying a scratchpad
+/// into EEPROM, so a bus in parasite mode enables the strong pull-up after its writes
+/// until the next reset or slot.
+#[derive(Debug)]
+pub struct StrongPullUp<P, S> {
+    pin: P,
+    pull_up: S,
+    active_low: bool,
+}
+
+impl<E, P: OpenDrainOutput<Error = E>, S: OutputPin<Error = E>> StrongPullUp<P, S> {
+    /// Sets the `pull_up` output high to enable the strong pull-up
+    pub fn new(pin: P, pull_up: S) -> Self {
+        StrongPullUp {
+            pin,
+            pull_up,
+            active_low: false,
+        }
+    }
+
+    /// Sets the output low instead to enable the strong pull-up, e.g. for the gate of a
+    /// P-channel MOSFET. The output should be initialized high.
+    pub fn active_low(mut self) -> Self {
+        self.active_low = true;
+        self
+    }
+
+    pub fn into_parts(self) -> (P, S) {
+        (self.pin, self.pull_up)
+    }
+}
+
+impl<E: core::fmt::Debug, P: OpenDrainOutput<Error = E>, S: OutputPin<Error = E>> OpenDrainOutput
+    for StrongPullUp<P, S>
+{
+    type Error = E;
+
+    fn is_high(&self) -> Result<bool, E> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, E> {
+        self.pin.is_low()
+    }
+
+    fn set_low(&mut self) -> Result<(), E> {
+        self.pin.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), E> {
+        self.pin.set_high()
+    }
+
+    fn acquire(&mut self) -> Result<(), E> {
+        self.pin.acquire()
+    }
+
+    fn release(&mut self) -> Result<(), E> {
+        self.pin.release()
+    }
+
+    fn active_pull_up(&mut self) -> Result<(), E> {
+        self.pin.active_pull_up()
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = E>> {
+        self.pin.bus_master()
+    }
+
+    fn strong_pull_up(&mut self, enabled: bool) -> Result<(), E> {
+        if enabled != self.active_low {
+            self.pull_up.set_high()
+        } else {
+            self.pull_up.set_low()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{IdleLine, NoDelay};
+    use crate::Device;
+    use crate::OneWire;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /// Records the state of the gate and how often it has been switched on
+    struct Gate<'a>(&'a Cell<bool>, &'a Cell<u8>);
+
+    impl OutputPin for Gate<'_> {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.set(true);
+            self.1.set(self.1.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_powered_until_next_reset() {
+        let gate = Cell::new(false);
+        let switched_on = Cell::new(0);
+        let mut wire = OneWire::new(StrongPullUp::new(IdleLine, Gate(&gate, &switched_on)), true);
+        let mut delay = NoDelay;
+        wire.write_bytes(&mut delay, &[0xCC, 0x44]).unwrap();
+        assert!(gate.get());
+        assert_eq!(switched_on.get(), 1);
+        wire.reset(&mut delay).unwrap();
+        assert!(!gate.get());
+    }
+
+    #[test]
+    fn test_powered_after_last_address_byte() {
+        let gate = Cell::new(false);
+        let switched_on = Cell::new(0);
+        let mut wire = OneWire::new(StrongPullUp::new(IdleLine, Gate(&gate, &switched_on)), true);
+        let mut delay = NoDelay;
+        let device = Device {
+            address: [0x28, 0x01, 0, 0, 0, 0, 0, 0],
+        };
+        wire.select(&mut delay, &device).unwrap();
+        assert!(gate.get());
+        assert_eq!(switched_on.get(), 1);
+        wire.suspend().unwrap();
+        assert!(!gate.get());
+    }
+}
@@ -0,0 +1,420 @@
+use hal1::delay::DelayNs;
+
+use crate::compute_partial_crc8;
+use crate::EepromDevice;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Size of a record in bytes, which matches the scratchpad rows of the DS2431
+pub const RECORD_SIZE: u16 = 8;
+
+/// Largest number of records, so the wrapping sequence number identifies the newest one
+pub const MAX_RECORDS: u16 = 255;
+
+/// Size of the chunks the log is recovered in, the pages of 1-Wire memories are at most
+/// this long
+const PAGE_BUFFER_SIZE: usize = 32;
+
+/// Mixed into the CRC of each record, so erased or zeroed memory is not mistaken for it
+const RECORD_MAGIC: u8 = 0xA5;
+
+/// A timestamped reading stored in a [`RingLog`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record {
+    /// Tick at which the reading was taken, e.g. seconds since an epoch
+    pub timestamp: u32,
+    /// The raw reading, e.g. [`Sensor::read_measurement_raw`](crate::Sensor::read_measurement_raw)
+    pub value: i16,
+}
+
+impl Record {
+    fn encode(&self, sequence: u8) -> [u8; RECORD_SIZE as usize] {
+        let mut bytes = [0u8; RECORD_SIZE as usize];
+        bytes[..4].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.value.to_le_bytes());
+        bytes[6] = sequence;
+        bytes[7] = compute_partial_crc8(compute_partial_crc8(0, &[RECORD_MAGIC]), &bytes[..7]);
+        bytes
+    }
+
+    /// Returns the record and its sequence number if the CRC is valid
+    fn decode(bytes: &[u8]) -> Option<(Record, u8)> {
+        let crc = compute_partial_crc8(compute_partial_crc8(0, &[RECORD_MAGIC]), &bytes[..7]);
+        if crc != bytes[7] {
+            return None;
+        }
+        let record = Record {
+            timestamp: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            value: i16::from_le_bytes([bytes[4], bytes[5]]),
+        };
+        Some((record, bytes[6]))
+    }
+}
+
+/// Appends timestamped readings to a region of a 1-Wire EEPROM, overwriting the oldest
+/// ones once it is full, so the history of a sensor pod travels with it.
+///
+/// Each record occupies [`RECORD_SIZE`] bytes and carries a wrapping sequence number and
+/// a CRC8. Records are only written one at a time, so a power loss while writing loses
+/// at most the record being written. The log is recovered from the memory on
+/// [`RingLog::open`], no separate header is needed.
+pub struct RingLog<'a, D> {
+    device: &'a D,
+    start: u16,
+    capacity: u16,
+    len: u16,
+    /// Slot of the next record
+    next: u16,
+    sequence: u8,
+}
+
+impl<'a, D: EepromDevice> RingLog<'a, D> {
+    /// Recovers the log stored in the `len` bytes starting at `start`. The region is used
+    /// for at most [`MAX_RECORDS`] records. Panics if it exceeds the memory or holds no
+    /// record at all.
+    pub fn open<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        device: &'a D,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        start: u16,
+        len: u16,
+    ) -> Result<Self, Error<O::Error>> {
+        let capacity = (len / RECORD_SIZE).min(MAX_RECORDS);
+        assert!(capacity > 0);
+
+        let mut count = 0;
+        let mut previous: Option<(u16, u8)> = None;
+        let mut newest = None;
+        let mut visit = |slot: u16, bytes: &[u8]| {
+            let sequence = Record::decode(bytes).map(|(_, sequence)| sequence);
+            if sequence.is_some() {
+                count += 1;
+            }
+            if let Some((previous_slot, previous_sequence)) = previous {
+                if newest.is_none() && sequence != Some(previous_sequence.wrapping_add(1)) {
+                    newest = Some((previous_slot, previous_sequence));
+                }
+            }
+            previous = sequence.map(|sequence| (slot, sequence));
+        };
+
+        // devices reading with CRC16 hand out whole pages, which don't need to line up
+        // with the records
+        let mut buf = [0u8; PAGE_BUFFER_SIZE];
+        let mut record = [0u8; RECORD_SIZE as usize];
+        let mut filled = 0;
+        let mut slot = 0;
+        D::read_memory_chunked(
+            device,
+            wire,
+            delay,
+            start,
+            capacity * RECORD_SIZE,
+            &mut buf,
+            |_, bytes| {
+                for byte in bytes {
+                    record[filled] = *byte;
+                    filled += 1;
+                    if filled == record.len() {
+                        visit(slot, &record);
+                        filled = 0;
+                        slot += 1;
+                    }
+                }
+            },
+        )?;
+        // the records are contiguous and there are fewer of them than sequence numbers,
+        // so the newest one is the only one not followed by its successor, which is the
+        // last slot if no other slot is
+        let newest = newest.or(previous);
+
+        let (next, sequence) = match newest {
+            Some((slot, sequence)) => ((slot + 1) % capacity, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+        Ok(RingLog {
+            device,
+            start,
+            capacity,
+            len: count,
+            next,
+            sequence,
+        })
+    }
+
+    /// Maximum number of records
+    pub fn capacity(&self) -> u16 {
+        self.capacity
+    }
+
+    /// Number of records stored
+    pub fn len(&self) -> u16 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a record, overwriting the oldest one if the log is full
+    pub fn append<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &mut self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        record: &Record,
+    ) -> Result<(), Error<O::Error>> {
+        let bytes = record.encode(self.sequence);
+        let address = self.start + self.next * RECORD_SIZE;
+        self.device
+            .write_memory(wire, delay, idle, address, &bytes)?;
+        self.next = (self.next + 1) % self.capacity;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.len = (self.len + 1).min(self.capacity);
+        Ok(())
+    }
+
+    /// Reads the record at the given position, `0` being the oldest one
+    pub fn get<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        index: u16,
+    ) -> Result<Option<Record>, Error<O::Error>> {
+        if index >= self.len {
+            return Ok(None);
+        }
+        let slot = (self.next + self.capacity - self.len + index) % self.capacity;
+        let mut bytes = [0u8; RECORD_SIZE as usize];
+        self.device
+            .read_memory(wire, delay, self.start + slot * RECORD_SIZE, &mut bytes)?;
+        Ok(Record::decode(&bytes).map(|(record, _)| record))
+    }
+
+    /// Calls `f` for each record from the oldest to the newest one
+    pub fn for_each<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        mut f: impl FnMut(Record),
+    ) -> Result<(), Error<O::Error>> {
+        for index in 0..self.len {
+            if let Some(record) = self.get(wire, delay, index)? {
+                f(record);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::Crc16;
+    use crate::testing::NoDelay;
+    use crate::{BusMaster, Device, MasterPin, MemoryDevice, Spin};
+    use core::convert::Infallible;
+
+    const READ_MEMORY: u8 = 0xF0;
+    const READ_MEMORY_CRC16: u8 = 0xA5;
+
+    /// A single EEPROM with 32 byte pages and an 8 byte scratchpad, simulated byte by byte
+    struct FakeEeprom {
+        memory: [u8; 64],
+        scratchpad: [u8; 8],
+        target: u16,
+        end_offset: u8,
+        /// The bytes written since the last reset
+        written: [u8; 16],
+        written_len: usize,
+        /// The bytes read since the last reset
+        read: usize,
+        /// The next address read and the CRC16 over the page so far
+        cursor: u16,
+        crc: Crc16,
+        crc_bytes: [u8; 2],
+        crc_len: usize,
+    }
+
+    impl FakeEeprom {
+        fn new() -> Self {
+            FakeEeprom {
+                memory: [0xFF; 64],
+                scratchpad: [0; 8],
+                target: 0,
+                end_offset: 0,
+                written: [0; 16],
+                written_len: 0,
+                read: 0,
+                cursor: 0,
+                crc: Crc16::new(),
+                crc_bytes: [0; 2],
+                crc_len: 0,
+            }
+        }
+
+        fn written_address(&self) -> u16 {
+            u16::from_le_bytes([self.written[2], self.written[3]])
+        }
+
+        fn read_memory(&mut self, crc16: bool) -> u8 {
+            if self.crc_len > 0 {
+                self.crc_len -= 1;
+                return self.crc_bytes[1 - self.crc_len];
+            }
+            let byte = self.memory[usize::from(self.cursor)];
+            self.crc.update(&[byte]);
+            self.cursor += 1;
+            if crc16 && self.cursor & 0x1F == 0 {
+                // the inverted CRC16 at the end of each page
+                self.crc_bytes = (!self.crc.finish()).to_le_bytes();
+                self.crc_len = 2;
+                self.crc = Crc16::new();
+            }
+            byte
+        }
+    }
+
+    impl BusMaster for FakeEeprom {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+            // Skip ROM, Write Scratchpad, the target address and the data
+            if self.written_len > 4 && self.written[1] == 0x0F {
+                self.target = self.written_address();
+                let offset = usize::from(self.target % 8);
+                let data = &self.written[4..self.written_len];
+                self.scratchpad[offset..offset + data.len()].copy_from_slice(data);
+                self.end_offset = (offset + data.len() - 1) as u8;
+            }
+            self.written_len = 0;
+            self.read = 0;
+            self.crc_len = 0;
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            unreachable!()
+        }
+
+        fn write_bit(&mut self, _high: bool) -> Result<(), Infallible> {
+            unreachable!()
+        }
+
+        fn read_byte(&mut self) -> Result<u8, Infallible> {
+            if self.read == 0 {
+                self.cursor = self.written_address();
+                self.crc = Crc16::new();
+                self.crc.update(&self.written[1..4]);
+            }
+            self.read += 1;
+            let offset = usize::from(self.target % 8);
+            Ok(match self.written[1] {
+                READ_MEMORY => self.read_memory(false),
+                READ_MEMORY_CRC16 => self.read_memory(true),
+                // Read Scratchpad
+                0xAA => match self.read {
+                    1 => self.target.to_le_bytes()[0],
+                    2 => self.target.to_le_bytes()[1],
+                    3 => self.end_offset,
+                    n => self.scratchpad[offset + n - 4],
+                },
+                // Copy Scratchpad
+                0x55 => {
+                    let end = usize::from(self.end_offset) + 1;
+                    let target = usize::from(self.target);
+                    self.memory[target..target + end - offset]
+                        .copy_from_slice(&self.scratchpad[offset..end]);
+                    0xAA
+                }
+                _ => 0xFF,
+            })
+        }
+
+        fn write_byte(&mut self, byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+            self.written[self.written_len] = byte;
+            self.written_len += 1;
+            Ok(())
+        }
+    }
+
+    struct Eeprom(Device);
+
+    impl MemoryDevice for Eeprom {
+        fn device(&self) -> &Device {
+            &self.0
+        }
+
+        fn target(&self) -> Option<&Device> {
+            None
+        }
+
+        fn memory_size() -> u16 {
+            64
+        }
+
+        fn page_size() -> u16 {
+            32
+        }
+
+        fn read_memory_crc16_command() -> Option<u8> {
+            Some(READ_MEMORY_CRC16)
+        }
+    }
+
+    impl EepromDevice for Eeprom {
+        fn scratchpad_size() -> u16 {
+            8
+        }
+
+        fn copy_time_ms() -> u16 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_open_after_wrap_around() {
+        let eeprom = Eeprom(Device { address: [0; 8] });
+        let mut wire = OneWire::new(MasterPin(FakeEeprom::new()), false);
+        let mut delay = NoDelay;
+
+        // five records crossing the page boundary at 32
+        let mut log = RingLog::open(&eeprom, &mut wire, &mut delay, 4, 40).unwrap();
+        assert_eq!(log.capacity(), 5);
+        assert!(log.is_empty());
+        for timestamp in 0..7 {
+            let record = Record {
+                timestamp,
+                value: -(timestamp as i16),
+            };
+            log.append(&mut wire, &mut delay, &mut Spin, &record)
+                .unwrap();
+        }
+
+        let log = RingLog::open(&eeprom, &mut wire, &mut delay, 4, 40).unwrap();
+        assert_eq!(log.len(), 5);
+        let mut expected = 2;
+        log.for_each(&mut wire, &mut delay, |record| {
+            assert_eq!(record.timestamp, expected);
+            expected += 1;
+        })
+        .unwrap();
+        assert_eq!(expected, 7);
+    }
+
+    #[test]
+    fn test_record_encoding() {
+        let record = Record {
+            timestamp: 0x1234_5678,
+            value: -42,
+        };
+        let bytes = record.encode(7);
+        assert_eq!(Record::decode(&bytes), Some((record, 7)));
+        assert_eq!(Record::decode(&[0x00; 8]), None);
+        assert_eq!(Record::decode(&[0xFF; 8]), None);
+    }
+}
@@ -0,0 +1,131 @@
+use core::fmt::Debug;
+use hal1::delay::DelayNs;
+
+use crate::compute_partial_crc8;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Discoverable;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::Sensor;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// The failures of a [`scan_and_read_all`], keeping up to `N` of them with the address
+/// of the failed device
+#[derive(Debug)]
+pub struct ScanReport<E: Sized + Debug, const N: usize> {
+    errors: [Option<(Device, Error<E>)>; N],
+    /// Number of failed devices, including those that did not fit into `errors`
+    failed: usize,
+    read: usize,
+}
+
+impl<E: Sized + Debug, const N: usize> ScanReport<E, N> {
+    fn new() -> Self {
+        ScanReport {
+            errors: [(); N].map(|_| None),
+            failed: 0,
+            read: 0,
+        }
+    }
+
+    fn push_error(&mut self, device: Device, error: Error<E>) {
+        if let Some(slot) = self.errors.get_mut(self.failed) {
+            *slot = Some((device, error));
+        }
+        self.failed += 1;
+    }
+
+    /// Number of devices read successfully
+    pub fn read(&self) -> usize {
+        self.read
+    }
+
+    /// Number of devices that failed, which may exceed the number of kept errors
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Whether all devices found have been read
+    pub fn is_complete(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// The kept errors in the order the devices have been found
+    pub fn errors(&self) -> impl Iterator<Item = (&Device, &Error<E>)> {
+        self.errors
+            .iter()
+            .flatten()
+            .map(|(device, error)| (device, error))
+    }
+}
+
+/// Starts a measurement on all sensors of type `S` at once, waits for it with the idle
+/// hook and reads every sensor found, passing each reading to `on_reading`.
+///
+/// Unlike a loop over [`OneWire::discover`] that stops at the first error, a device that
+/// fails to be read, e.g. with a [`Error::CrcMismatch`], or is found with an invalid
+/// address is recorded in the returned report and the scan continues with the next one.
+/// Only failures of the bus itself, like starting the measurement or the search, abort
+/// the scan.
+pub fn scan_and_read_all<S, O, T, G, const N: usize>(
+    wire: &mut OneWire<O, T, G>,
+    delay: &mut impl DelayNs,
+    idle: &mut impl Idle,
+    mut on_reading: impl FnMut(&S, S::Measurement),
+) -> Result<ScanReport<O::Error, N>, Error<O::Error>>
+where
+    S: Sensor + Discoverable,
+    O: OpenDrainOutput,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    let wait_ms = S::start_measurement_all(wire, delay)?;
+    idle.idle(wait_ms, delay);
+
+    let mut report = ScanReport::new();
+    for family in S::family_codes() {
+        let mut search = DeviceSearch::new_for_family(*family);
+        while let Some(device) = wire.search_next(&mut search, delay)? {
+            if !device.is_crc_valid() {
+                let computed = compute_partial_crc8(0, &device.address[..7]);
+                let expected = device.address[7];
+                report.push_error(device, Error::CrcMismatch(computed, expected));
+                continue;
+            }
+            let sensor = match S::from_device(device.clone()) {
+                Some(sensor) => sensor,
+                None => continue,
+            };
+            match sensor.read_measurement(wire, delay) {
+                Ok(value) => {
+                    on_reading(&sensor, value);
+                    report.read += 1;
+                }
+                Err(error) => report.push_error(device, error),
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_overflow() {
+        let mut report = ScanReport::<(), 1>::new();
+        let device = Device {
+            address: [0x28, 0, 0, 0, 0, 0, 0, 0],
+        };
+        report.push_error(device.clone(), Error::CrcMismatch(1, 2));
+        report.push_error(device, Error::WireNotHigh);
+        assert_eq!(report.failed(), 2);
+        assert_eq!(report.errors().count(), 1);
+        assert!(!report.is_complete());
+    }
+}
@@ -0,0 +1,169 @@
+use hal1::delay::DelayNs;
+
+use crate::crc::Crc16;
+use crate::Device;
+use crate::Error;
+use crate::Idle;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// How the data read from a scratchpad is protected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScratchpadCrc {
+    /// No CRC follows the scratchpad
+    None,
+    /// A CRC8 over the scratchpad follows it
+    Crc8,
+    /// An inverted CRC16 over the read command and the scratchpad follows it
+    Crc16,
+}
+
+/// The result of [`ScratchpadDevice::check_unique`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    /// No device answers to the address
+    Missing,
+    /// A single device answers to the address, as far as the check can tell
+    Unique,
+    /// The address is found, but the scratchpad read from it is consistently garbled,
+    /// which happens if several devices share the address, e.g. cloned iButtons or
+    /// counterfeit sensors
+    Duplicated,
+}
+
+/// A device with a scratchpad that is read, written and copied into its non-volatile
+/// memory with dedicated commands, like the DS18B20 or the DS2438.
+///
+/// The provided methods implement the bus transactions and validate the CRC, so devices
+/// only need to describe their scratchpad.
+pub trait ScratchpadDevice {
+    fn device(&self) -> &Device;
+
+    /// The device to address, `None` to address the only device on the bus with Skip ROM,
+    /// see [`SingleDrop`](crate::SingleDrop)
+    fn target(&self) -> Option<&Device> {
+        Some(self.device())
+    }
+
+    /// Number of scratchpad bytes read by the read command, excluding the CRC
+    fn scratchpad_len() -> usize;
+
+    fn scratchpad_crc() -> ScratchpadCrc;
+
+    fn read_scratchpad_command() -> u8;
+
+    fn write_scratchpad_command() -> u8;
+
+    fn copy_scratchpad_command() -> u8;
+
+    /// Milliseconds required to wait until copying the scratchpad finished
+    fn copy_scratchpad_time_ms() -> u16;
+
+    /// Reads the scratchpad into the first [`ScratchpadDevice::scratchpad_len`] bytes of
+    /// `dst` and validates its CRC. Panics if `dst` is shorter than that.
+    fn read_scratchpad<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        dst: &mut [u8],
+    ) -> Result<(), Error<O::Error>> {
+        let command = Self::read_scratchpad_command();
+        let dst = &mut dst[..Self::scratchpad_len()];
+        wire.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, self.target())?;
+            wire.write_bytes(delay, &[command])?;
+            match Self::scratchpad_crc() {
+                ScratchpadCrc::None => Ok(wire.read_bytes(delay, dst)?),
+                ScratchpadCrc::Crc8 => wire.read_bytes_crc8(delay, dst),
+                ScratchpadCrc::Crc16 => {
+                    wire.read_bytes(delay, dst)?;
+                    let mut crc16 = [0u8; 2];
+                    wire.read_bytes(delay, &mut crc16)?;
+                    let mut crc = Crc16::new();
+                    crc.update(&[command]);
+                    crc.update(dst);
+                    wire.stats.check_crc(crc.verify_inverted(crc16))
+                }
+            }
+        })
+    }
+
+    /// Writes the given bytes into the scratchpad
+    fn write_scratchpad<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        src: &[u8],
+    ) -> Result<(), Error<O::Error>> {
+        wire.transaction(|wire| {
+            wire.reset(delay)?;
+            wire.select_or_skip(delay, self.target())?;
+            wire.write_bytes(delay, &[Self::write_scratchpad_command()])?;
+            wire.write_bytes(delay, src)?;
+            Ok(())
+        })
+    }
+
+    /// Starts copying the scratchpad into the non-volatile memory and returns the
+    /// milliseconds required to wait until it finished
+    fn copy_scratchpad<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>> {
+        wire.reset_select_or_skip_write_only(
+            delay,
+            self.target(),
+            &[Self::copy_scratchpad_command()],
+        )?;
+        Ok(Self::copy_scratchpad_time_ms())
+    }
+
+    /// Copies the scratchpad into the non-volatile memory and waits until it finished
+    /// using the given idle hook
+    fn copy_scratchpad_and_wait<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+    ) -> Result<(), Error<O::Error>> {
+        let wait_ms = self.copy_scratchpad(wire, delay)?;
+        idle.idle(wait_ms, delay);
+        Ok(())
+    }
+
+    /// Checks whether the address is shared by several devices. `buf` receives the
+    /// scratchpad and has to be as long as for [`ScratchpadDevice::read_scratchpad`].
+    ///
+    /// Devices with identical addresses can not be told apart by a search, so the address
+    /// is first verified with a targeted search and then the scratchpad is read twice.
+    /// Several devices answering at once garble the scratchpad, unless their contents are
+    /// identical, so two CRC mismatches in a row are reported as [`Presence::Duplicated`].
+    /// Without a scratchpad CRC, a found device is always reported as unique.
+    fn check_unique<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        buf: &mut [u8],
+    ) -> Result<Presence, Error<O::Error>> {
+        if let Some(device) = self.target() {
+            if !wire.verify(delay, device)? {
+                return Ok(Presence::Missing);
+            }
+        }
+        for attempt in 0..2 {
+            if attempt > 0 {
+                wire.stats.record_retry();
+            }
+            match self.read_scratchpad(wire, delay, buf) {
+                Err(Error::CrcMismatch(..)) | Err(Error::Crc16Mismatch(..)) => {}
+                Err(e) => return Err(e),
+                Ok(()) => return Ok(Presence::Unique),
+            }
+        }
+        Ok(Presence::Duplicated)
+    }
+}
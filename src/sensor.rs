@@ -0,0 +1,414 @@
+use core::cmp::Ordering;
+use core::ops::Sub;
+#[cfg(feature = "async")]
+use embedded_hal_async::delay::DelayNs as AsyncDelayNs;
+use hal1::delay::DelayNs;
+
+#[cfg(feature = "async")]
+use crate::asynch::AsyncOneWire;
+use crate::clock::is_due;
+use crate::Clock;
+use crate::DefaultTiming;
+use crate::Error;
+use crate::Idle;
+use crate::NoGuard;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::PlausibilityCheck;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+pub trait Sensor {
+    /// The value produced by a measurement, e.g. `f32` for a temperature
+    type Measurement;
+
+    fn family_code() -> u8;
+
+    /// returns the milliseconds required to wait until the measurement finished
+    fn start_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>>;
+
+    /// starts the measurement on all devices of this family at once and returns the
+    /// milliseconds required to wait until all of them finished, assuming the worst case.
+    ///
+    /// The command is sent with Skip ROM and thus received by every device on the bus,
+    /// regardless of its family.
+    fn start_measurement_all<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>>;
+
+    /// returns the measured value
+    fn read_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<Self::Measurement, Error<O::Error>>;
+
+    fn read_measurement_raw<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>>;
+
+    /// returns the measured value together with the tick at which reading it completed
+    #[allow(clippy::type_complexity)]
+    fn read_measurement_timestamped<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        clock: &mut impl Clock,
+    ) -> Result<(Self::Measurement, u32), Error<O::Error>> {
+        let value = self.read_measurement(wire, delay)?;
+        Ok((value, clock.now_ms()))
+    }
+
+    /// starts a measurement, waits for it to finish using the given idle hook and returns
+    /// the measured value
+    fn measure<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+    ) -> Result<Self::Measurement, Error<O::Error>> {
+        let wait_ms = self.start_measurement(wire, delay)?;
+        idle.idle(wait_ms, delay);
+        self.read_measurement(wire, delay)
+    }
+
+    /// returns the measured value if it passes the given plausibility check
+    fn read_measurement_checked<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        check: &mut PlausibilityCheck<Self::Measurement>,
+    ) -> Result<Self::Measurement, Error<O::Error>>
+    where
+        Self::Measurement: Copy + PartialOrd + Sub<Output = Self::Measurement>,
+    {
+        let value = self.read_measurement(wire, delay)?;
+        check.check(value).map_err(Error::Implausible)
+    }
+
+    /// performs `N` measurements and returns their median, which masks single glitches
+    /// like the power on value of a temperature sensor. Samples deviating from the median
+    /// by more than `tolerance` are counted as outliers. For an even `N`, the upper of
+    /// the two middle samples is returned.
+    fn measure_median<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard, const N: usize>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        idle: &mut impl Idle,
+        tolerance: Self::Measurement,
+    ) -> Result<Median<Self::Measurement>, Error<O::Error>>
+    where
+        Self::Measurement: Copy + Default + PartialOrd + Sub<Output = Self::Measurement>,
+    {
+        const { assert!(N > 0, "a median needs at least one sample") };
+        let mut samples = [Self::Measurement::default(); N];
+        for sample in samples.iter_mut() {
+            *sample = self.measure(wire, delay, idle)?;
+        }
+        samples.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let value = samples[N / 2];
+        let outliers = samples
+            .iter()
+            .filter(|&&sample| {
+                let deviation = if sample > value {
+                    sample - value
+                } else {
+                    value - sample
+                };
+                deviation > tolerance
+            })
+            .count();
+        Ok(Median { value, outliers })
+    }
+
+    /// starts a measurement and returns a handle to track and finish it, so many
+    /// overlapping conversions can be managed without further bookkeeping
+    fn start_conversion<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        clock: &mut impl Clock,
+    ) -> Result<ConversionHandle<'_, Self>, Error<O::Error>>
+    where
+        Self: Sized,
+    {
+        let wait_ms = self.start_measurement(wire, delay)?;
+        Ok(ConversionHandle {
+            sensor: self,
+            wait_ms,
+            started_at: clock.now_ms(),
+        })
+    }
+}
+
+/// The result of [`Sensor::measure_median`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Median<M> {
+    pub value: M,
+    /// Number of samples deviating from the median by more than the tolerance
+    pub outliers: usize,
+}
+
+/// A measurement in flight, created by [`Sensor::start_conversion`]
+#[derive(Debug)]
+pub struct ConversionHandle<'a, S> {
+    sensor: &'a S,
+    wait_ms: u16,
+    started_at: u32,
+}
+
+impl<'a, S: Sensor> ConversionHandle<'a, S> {
+    /// The sensor performing the measurement
+    pub fn sensor(&self) -> &'a S {
+        self.sensor
+    }
+
+    /// Milliseconds the conversion takes
+    pub fn wait_ms(&self) -> u16 {
+        self.wait_ms
+    }
+
+    /// The tick at which the conversion was started
+    pub fn started_at(&self) -> u32 {
+        self.started_at
+    }
+
+    /// Whether the conversion finished according to the given clock
+    pub fn is_ready(&self, clock: &mut impl Clock) -> bool {
+        is_due(clock.now_ms(), self.ready_at())
+    }
+
+    /// The tick at which the conversion will be finished
+    pub fn ready_at(&self) -> u32 {
+        self.started_at.wrapping_add(u32::from(self.wait_ms))
+    }
+
+    /// Reads the measured value. Only meaningful once [`ConversionHandle::is_ready`].
+    pub fn finish<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<S::Measurement, Error<O::Error>> {
+        self.sensor.read_measurement(wire, delay)
+    }
+}
+
+/// A device that measures more than one quantity, like the DS2438
+/// (temperature, two voltages and current) or the DS2450 (four ADC channels).
+///
+/// Generic polling code can iterate [`MultiSensor::channels`] and start and
+/// read each channel the same way it would a single [`Sensor`].
+pub trait MultiSensor {
+    /// Identifies one channel of the device
+    type Channel: Copy;
+
+    /// The value produced by a measurement on any channel
+    type Measurement;
+
+    fn family_code() -> u8;
+
+    /// returns all channels provided by the device
+    fn channels() -> &'static [Self::Channel];
+
+    /// returns the milliseconds required to wait until the measurement finished
+    fn start_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        channel: Self::Channel,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<u16, Error<O::Error>>;
+
+    /// returns the measured value of the given channel
+    fn read_measurement<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        channel: Self::Channel,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<Self::Measurement, Error<O::Error>>;
+}
+
+/// Async counterpart of [`Sensor`], which awaits the conversion time instead of
+/// blocking on it.
+///
+/// On a blocking [`OneWire`] only the conversion wait, which takes up to hundreds of
+/// milliseconds, yields to the executor, since the timing of the bus transfers is too
+/// tight to be interrupted. On an [`AsyncOneWire`] the reset pulses and the recovery
+/// times are awaited as well, if the driver implements the transfers with it. The
+/// defaults perform them with the blocking bus, so an empty impl is enough for any
+/// [`Sensor`].
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait AsyncSensor: Sensor {
+    /// returns the milliseconds required to wait until the measurement finished
+    async fn start_measurement_async<O, T, G, D>(
+        &self,
+        wire: &mut AsyncOneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<u16, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        self.start_measurement(wire.wire_mut(), delay)
+    }
+
+    /// returns the measured value
+    async fn read_measurement_async<O, T, G, D>(
+        &self,
+        wire: &mut AsyncOneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<Self::Measurement, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        self.read_measurement(wire.wire_mut(), delay)
+    }
+
+    /// starts a measurement, awaits its completion and returns the measured value
+    async fn measure_async<O, T, G, D>(
+        &self,
+        wire: &mut AsyncOneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<Self::Measurement, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        let wait_ms = self.start_measurement_async(wire, delay).await?;
+        AsyncDelayNs::delay_ms(delay, u32::from(wait_ms)).await;
+        self.read_measurement_async(wire, delay).await
+    }
+
+    /// starts a measurement on a blocking bus, awaits its completion and returns the
+    /// measured value. Named apart from [`Sensor::measure`], so both traits can be in
+    /// scope at once.
+    async fn measure_async_delay<O, T, G, D>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<Self::Measurement, Error<O::Error>>
+    where
+        O: OpenDrainOutput,
+        T: TimingProfile,
+        G: TimingGuard,
+        D: DelayNs + AsyncDelayNs,
+    {
+        let wait_ms = self.start_measurement(wire, delay)?;
+        AsyncDelayNs::delay_ms(delay, u32::from(wait_ms)).await;
+        self.read_measurement(wire, delay)
+    }
+}
+
+/// A sensor that compares its measurements against a low and a high threshold.
+///
+/// Alarmed devices respond to [`OneWire::search_next_alarmed`], so event driven code can
+/// configure the thresholds once and then only search for alarmed devices instead of
+/// reading every sensor.
+pub trait AlarmSensor: Sensor {
+    /// The unit the thresholds are expressed in
+    type Threshold;
+
+    fn set_alarm_thresholds<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+        low: Self::Threshold,
+        high: Self::Threshold,
+    ) -> Result<(), Error<O::Error>>;
+
+    /// returns the currently configured `(low, high)` thresholds
+    #[allow(clippy::type_complexity)]
+    fn read_alarm_thresholds<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(Self::Threshold, Self::Threshold), Error<O::Error>>;
+
+    /// returns whether the last measurement exceeded one of the thresholds
+    fn is_alarmed<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<bool, Error<O::Error>>;
+
+    /// disables the alarm by setting the thresholds to the widest possible range
+    fn clear_alarm<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut impl DelayNs,
+    ) -> Result<(), Error<O::Error>>;
+}
+
+/// Object safe view of a [`Sensor`] bound to a concrete wire and delay type.
+///
+/// Allows heterogeneous sensors to be stored in a single array, e.g.
+/// `[&dyn ErasedSensor<Pin, Delay, f32>; 4]`, and polled in a loop. It is implemented
+/// for every [`Sensor`] whose measurement converts into `M`.
+pub trait ErasedSensor<
+    O: OpenDrainOutput,
+    D: DelayNs,
+    M,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+>
+{
+    fn family_code(&self) -> u8;
+
+    /// returns the milliseconds required to wait until the measurement finished
+    fn start_measurement(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<u16, Error<O::Error>>;
+
+    /// returns the measured value
+    fn read_measurement(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<M, Error<O::Error>>;
+}
+
+impl<S, O, D, M, T, G> ErasedSensor<O, D, M, T, G> for S
+where
+    S: Sensor,
+    S::Measurement: Into<M>,
+    O: OpenDrainOutput,
+    D: DelayNs,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    fn family_code(&self) -> u8 {
+        S::family_code()
+    }
+
+    fn start_measurement(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<u16, Error<O::Error>> {
+        Sensor::start_measurement(self, wire, delay)
+    }
+
+    fn read_measurement(
+        &self,
+        wire: &mut OneWire<O, T, G>,
+        delay: &mut D,
+    ) -> Result<M, Error<O::Error>> {
+        Sensor::read_measurement(self, wire, delay).map(Into::into)
+    }
+}
@@ -0,0 +1,234 @@
+#[cfg(feature = "critical-section")]
+use core::cell::Cell;
+use core::cell::{RefCell, RefMut};
+#[cfg(feature = "critical-section")]
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "critical-section")]
+use critical_section::Mutex;
+
+use crate::DefaultTiming;
+use crate::Error;
+use crate::NoGuard;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+#[cfg(feature = "critical-section")]
+/// A [`OneWire`] bus that can be shared between interrupt handlers or cores, e.g. in a
+/// `static`.
+///
+/// The bus is taken out of the shared slot for the duration of a lock, so the critical
+/// section only guards taking and returning it and interrupts stay enabled during the
+/// transfers. A second user trying to lock a bus that is in use gets `None` instead of
+/// blocking, since blocking inside an interrupt handler would dead lock.
+pub struct SharedOneWire<
+    ODO: OpenDrainOutput,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
+    wire: Mutex<Cell<Option<OneWire<ODO, T, G>>>>,
+    locked: Mutex<Cell<bool>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<E, ODO, T, G> SharedOneWire<ODO, T, G>
+where
+    E: core::fmt::Debug,
+    ODO: OpenDrainOutput<Error = E>,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    /// Creates a slot without a bus, to be placed in a `static` and filled with
+    /// [`SharedOneWire::set`] once the pin is available
+    pub const fn empty() -> Self {
+        SharedOneWire {
+            wire: Mutex::new(Cell::new(None)),
+            locked: Mutex::new(Cell::new(false)),
+        }
+    }
+
+    pub const fn new(wire: OneWire<ODO, T, G>) -> Self {
+        SharedOneWire {
+            wire: Mutex::new(Cell::new(Some(wire))),
+            locked: Mutex::new(Cell::new(false)),
+        }
+    }
+
+    /// Places the bus into the slot and returns the previous one, which is `None` if the
+    /// slot was empty. Fails with the given bus while the previous one is locked, since
+    /// the guard returns it to the slot when dropped.
+    #[allow(clippy::type_complexity)]
+    pub fn set(
+        &self,
+        wire: OneWire<ODO, T, G>,
+    ) -> Result<Option<OneWire<ODO, T, G>>, OneWire<ODO, T, G>> {
+        critical_section::with(|cs| {
+            if self.locked.borrow(cs).get() {
+                Err(wire)
+            } else {
+                Ok(self.wire.borrow(cs).replace(Some(wire)))
+            }
+        })
+    }
+
+    /// Locks the bus until the returned guard is dropped, `None` if it is locked already
+    /// or the slot is empty
+    pub fn try_lock(&self) -> Option<SharedOneWireGuard<'_, ODO, T, G>> {
+        critical_section::with(|cs| {
+            let wire = self.wire.borrow(cs).take()?;
+            self.locked.borrow(cs).set(true);
+            Some(wire)
+        })
+        .map(|wire| SharedOneWireGuard {
+            shared: self,
+            wire: Some(wire),
+        })
+    }
+
+    /// Runs `f` as a single bus transaction while holding the lock, see
+    /// [`OneWire::transaction`]. Returns `None` if the bus could not be locked.
+    pub fn transaction<R>(
+        &self,
+        f: impl FnOnce(&mut OneWire<ODO, T, G>) -> Result<R, Error<E>>,
+    ) -> Option<Result<R, Error<E>>> {
+        self.try_lock().map(|mut wire| wire.transaction(f))
+    }
+}
+
+#[cfg(feature = "critical-section")]
+/// Exclusive access to a [`SharedOneWire`], returns the bus when dropped
+pub struct SharedOneWireGuard<'a, ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> {
+    shared: &'a SharedOneWire<ODO, T, G>,
+    wire: Option<OneWire<ODO, T, G>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> Deref
+    for SharedOneWireGuard<'_, ODO, T, G>
+{
+    type Target = OneWire<ODO, T, G>;
+
+    fn deref(&self) -> &Self::Target {
+        self.wire.as_ref().unwrap()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> DerefMut
+    for SharedOneWireGuard<'_, ODO, T, G>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.wire.as_mut().unwrap()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> Drop
+    for SharedOneWireGuard<'_, ODO, T, G>
+{
+    fn drop(&mut self) {
+        let wire = self.wire.take();
+        critical_section::with(|cs| {
+            self.shared.wire.borrow(cs).set(wire);
+            self.shared.locked.borrow(cs).set(false);
+        });
+    }
+}
+
+/// A handle to a [`OneWire`] bus shared by several owners within the same execution
+/// context, e.g. the drivers of a temperature sensor, an EEPROM and an iButton reader
+/// polled from the same loop or executor. The handle is `Copy`, so each owner can keep
+/// its own.
+///
+/// The bus is borrowed from the `RefCell` for the duration of a lock. Use the
+/// `SharedOneWire` of the `critical-section` feature to share a bus between interrupt
+/// handlers or cores instead.
+pub struct RefCellOneWire<
+    'a,
+    ODO: OpenDrainOutput,
+    T: TimingProfile = DefaultTiming,
+    G: TimingGuard = NoGuard,
+> {
+    wire: &'a RefCell<OneWire<ODO, T, G>>,
+}
+
+impl<ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> Clone
+    for RefCellOneWire<'_, ODO, T, G>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<ODO: OpenDrainOutput, T: TimingProfile, G: TimingGuard> Copy
+    for RefCellOneWire<'_, ODO, T, G>
+{
+}
+
+impl<'a, E, ODO, T, G> RefCellOneWire<'a, ODO, T, G>
+where
+    E: core::fmt::Debug,
+    ODO: OpenDrainOutput<Error = E>,
+    T: TimingProfile,
+    G: TimingGuard,
+{
+    pub fn new(wire: &'a RefCell<OneWire<ODO, T, G>>) -> Self {
+        RefCellOneWire { wire }
+    }
+
+    /// Borrows the bus until the returned guard is dropped. Panics if it is borrowed
+    /// already, e.g. by a lock held across an await point of another task.
+    pub fn lock(&self) -> RefMut<'a, OneWire<ODO, T, G>> {
+        self.wire.borrow_mut()
+    }
+
+    /// Borrows the bus until the returned guard is dropped, `None` if it is borrowed
+    /// already
+    pub fn try_lock(&self) -> Option<RefMut<'a, OneWire<ODO, T, G>>> {
+        self.wire.try_borrow_mut().ok()
+    }
+
+    /// Runs `f` as a single bus transaction while holding the lock, see
+    /// [`OneWire::transaction`]. Returns `None` if the bus could not be locked.
+    pub fn transaction<R>(
+        &self,
+        f: impl FnOnce(&mut OneWire<ODO, T, G>) -> Result<R, Error<E>>,
+    ) -> Option<Result<R, Error<E>>> {
+        self.try_lock().map(|mut wire| wire.transaction(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::IdleLine;
+
+    #[test]
+    fn test_ref_cell_handles() {
+        let wire = RefCell::new(OneWire::new(IdleLine, false));
+        let sensor = RefCellOneWire::new(&wire);
+        let eeprom = sensor;
+
+        let locked = sensor.lock();
+        assert!(eeprom.try_lock().is_none());
+        assert!(eeprom.transaction(|_| Ok(())).is_none());
+        drop(locked);
+        assert_eq!(eeprom.transaction(|_| Ok(1)).unwrap().unwrap(), 1);
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[test]
+    fn test_set_while_locked() {
+        let shared = SharedOneWire::new(OneWire::new(IdleLine, false));
+        let locked = shared.try_lock().unwrap();
+        assert!(shared.try_lock().is_none());
+        let replacement = shared.set(OneWire::new(IdleLine, true)).unwrap_err();
+        assert!(replacement.parasite_mode);
+        drop(locked);
+
+        let previous = shared.set(replacement).ok().unwrap().unwrap();
+        assert!(!previous.parasite_mode);
+        assert!(shared.try_lock().unwrap().parasite_mode);
+    }
+}
@@ -0,0 +1,68 @@
+use core::fmt::Debug;
+
+use crate::Error;
+
+/// Counters about the traffic and the failures on a bus, see [`OneWire::stats`].
+///
+/// Rising presence failures and CRC mismatches across all devices point to the cabling
+/// or the pull-up, while errors concentrated on a single device point to the device.
+/// The counters saturate instead of wrapping around.
+///
+/// [`OneWire::stats`]: crate::OneWire::stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct BusStats {
+    /// Number of resets issued
+    pub resets: u32,
+    /// Number of resets without a presence pulse or with the line held low
+    pub presence_failures: u32,
+    /// Number of CRC8 and CRC16 mismatches of the data read from the devices
+    pub crc_mismatches: u32,
+    /// Number of operations repeated after a failure, by the drivers or as recorded with
+    /// [`OneWire::record_retry`](crate::OneWire::record_retry)
+    pub retries: u32,
+    /// Number of bytes written and read
+    pub bytes_transferred: u32,
+}
+
+impl BusStats {
+    pub(crate) fn record_reset(&mut self, presence: bool) {
+        self.resets = self.resets.saturating_add(1);
+        if !presence {
+            self.presence_failures = self.presence_failures.saturating_add(1);
+        }
+    }
+
+    /// Counts the result if it is a CRC mismatch and passes it on
+    pub(crate) fn check_crc<R, E: Debug>(
+        &mut self,
+        result: Result<R, Error<E>>,
+    ) -> Result<R, Error<E>> {
+        if let Err(Error::CrcMismatch(..)) | Err(Error::Crc16Mismatch(..)) = result {
+            self.crc_mismatches = self.crc_mismatches.saturating_add(1);
+        }
+        result
+    }
+
+    pub(crate) fn record_retry(&mut self) {
+        self.retries = self.retries.saturating_add(1);
+    }
+
+    pub(crate) fn record_byte(&mut self) {
+        self.bytes_transferred = self.bytes_transferred.saturating_add(1);
+    }
+
+    /// Adds the counters of `other`, e.g. of a bus that only lived for a single operation
+    #[cfg(feature = "ffi")]
+    pub(crate) fn add(&mut self, other: &BusStats) {
+        self.resets = self.resets.saturating_add(other.resets);
+        self.presence_failures = self
+            .presence_failures
+            .saturating_add(other.presence_failures);
+        self.crc_mismatches = self.crc_mismatches.saturating_add(other.crc_mismatches);
+        self.retries = self.retries.saturating_add(other.retries);
+        self.bytes_transferred = self
+            .bytes_transferred
+            .saturating_add(other.bytes_transferred);
+    }
+}
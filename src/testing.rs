@@ -0,0 +1,225 @@
+use core::convert::Infallible;
+
+#[cfg(feature = "async")]
+use embedded_hal_async::delay::DelayNs as AsyncDelayNs;
+use hal1::delay::DelayNs;
+
+use crate::compute_partial_crc8;
+use crate::BusMaster;
+use crate::Device;
+use crate::Error;
+use crate::OpenDrainOutput;
+use crate::ADDRESS_BITS;
+
+/// Generates pseudo random addresses with a valid CRC from a seed, so test runs are
+/// reproducible. Not suitable for anything but tests.
+#[derive(Debug, Clone)]
+pub struct AddressGenerator {
+    state: u64,
+    family: Option<u8>,
+}
+
+impl AddressGenerator {
+    pub fn new(seed: u64) -> Self {
+        AddressGenerator {
+            // xorshift gets stuck at zero
+            state: seed | 1,
+            family: None,
+        }
+    }
+
+    /// Generates addresses of the given family only
+    pub fn for_family(seed: u64, family: u8) -> Self {
+        AddressGenerator {
+            family: Some(family),
+            ..AddressGenerator::new(seed)
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    pub fn next_address(&mut self) -> Device {
+        let mut address = self.next_u64().to_le_bytes();
+        if let Some(family) = self.family {
+            address[0] = family;
+        }
+        address[7] = compute_partial_crc8(0, &address[..7]);
+        Device { address }
+    }
+}
+
+impl Iterator for AddressGenerator {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        Some(self.next_address())
+    }
+}
+
+/// A delay that returns immediately, for buses on simulated lines and masters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl AsyncDelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// A released line without devices, which always reads high
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdleLine;
+
+impl OpenDrainOutput for IdleLine {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A bus master simulating up to four devices at the bit level, they only understand
+/// Search ROM and leave the line released after the address. Use it as the pin of a bus
+/// with [`MasterPin`](crate::MasterPin).
+pub struct SearchBus<'a> {
+    devices: &'a [Device],
+    /// Bits of the command received since the last reset
+    command_bits: u8,
+    /// Devices that haven't dropped out of the search
+    active: [bool; 4],
+    /// The address bit of the search and whether its complement is read next
+    bit: u8,
+    complement: bool,
+}
+
+impl<'a> SearchBus<'a> {
+    /// Panics with more than four devices
+    pub fn new(devices: &'a [Device]) -> Self {
+        assert!(devices.len() <= 4, "at most four devices are simulated");
+        SearchBus {
+            devices,
+            command_bits: 0,
+            active: [false; 4],
+            bit: 0,
+            complement: false,
+        }
+    }
+
+    fn address_bit(device: &Device, bit: u8) -> bool {
+        device.address[usize::from(bit / 8)] & (1 << (bit % 8)) != 0
+    }
+}
+
+impl BusMaster for SearchBus<'_> {
+    type Error = Infallible;
+
+    fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+        self.command_bits = 0;
+        self.bit = 0;
+        self.complement = false;
+        for (i, active) in self.active.iter_mut().enumerate() {
+            *active = i < self.devices.len();
+        }
+        Ok(!self.devices.is_empty())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Infallible> {
+        if self.bit >= ADDRESS_BITS {
+            return Ok(true);
+        }
+        // the line is low if any active device pulls it low
+        let complement = self.complement;
+        let value = self
+            .devices
+            .iter()
+            .zip(&self.active)
+            .filter(|(_, active)| **active)
+            .all(|(device, _)| Self::address_bit(device, self.bit) != complement);
+        self.complement = !complement;
+        Ok(value)
+    }
+
+    fn write_bit(&mut self, high: bool) -> Result<(), Infallible> {
+        if self.command_bits < 8 {
+            self.command_bits += 1;
+            return Ok(());
+        }
+        // the bits following the address are not part of the search
+        if self.bit >= ADDRESS_BITS {
+            return Ok(());
+        }
+        for (device, active) in self.devices.iter().zip(self.active.iter_mut()) {
+            *active &= Self::address_bit(device, self.bit) == high;
+        }
+        self.bit += 1;
+        self.complement = false;
+        Ok(())
+    }
+
+    fn write_byte(&mut self, mut byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+        for _ in 0..8 {
+            self.write_bit(byte & 0x01 == 0x01)?;
+            byte >>= 1;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a future on a simulated bus to completion, which never has to wait
+#[cfg(all(test, feature = "async"))]
+pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut future = core::pin::pin!(future);
+    match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("the future waits on a simulated bus"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_addresses() {
+        let mut generator = AddressGenerator::for_family(42, 0x28);
+        let first = generator.next_address();
+        for device in generator.take(100) {
+            assert!(device.is_crc_valid());
+            assert_eq!(device.family_code(), 0x28);
+            assert_ne!(device, first);
+        }
+        assert_eq!(AddressGenerator::for_family(42, 0x28).next_address(), first);
+    }
+}
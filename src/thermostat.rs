@@ -0,0 +1,121 @@
+use core::ops::{Add, Sub};
+
+use crate::clock::is_due;
+
+/// Whether the controlled output raises or lowers the measured value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThermostatMode {
+    Heating,
+    Cooling,
+}
+
+/// Two point controller deciding whether a heater or cooler should run, based on
+/// readings of a [`Sensor`](crate::Sensor).
+///
+/// When heating, the output is turned on once the reading drops to `setpoint - hysteresis`
+/// and turned off once it reaches the setpoint again; cooling works the other way round.
+/// Minimum on and off times protect e.g. compressors from switching too often.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thermostat<M> {
+    setpoint: M,
+    hysteresis: M,
+    mode: ThermostatMode,
+    min_on_ms: u32,
+    min_off_ms: u32,
+    on: bool,
+    switched_at: Option<u32>,
+}
+
+impl<M: Copy + PartialOrd + Add<Output = M> + Sub<Output = M>> Thermostat<M> {
+    /// Creates a heating thermostat without minimum on and off times, initially off
+    pub fn new(setpoint: M, hysteresis: M) -> Self {
+        Thermostat {
+            setpoint,
+            hysteresis,
+            mode: ThermostatMode::Heating,
+            min_on_ms: 0,
+            min_off_ms: 0,
+            on: false,
+            switched_at: None,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ThermostatMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Keeps the output on for at least `min_on_ms` and off for at least `min_off_ms`
+    pub fn with_min_times(mut self, min_on_ms: u32, min_off_ms: u32) -> Self {
+        self.min_on_ms = min_on_ms;
+        self.min_off_ms = min_off_ms;
+        self
+    }
+
+    pub fn setpoint(&self) -> M {
+        self.setpoint
+    }
+
+    pub fn set_setpoint(&mut self, setpoint: M) {
+        self.setpoint = setpoint;
+    }
+
+    /// Whether the output should currently be on
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Feeds a reading taken at the given millisecond tick and returns whether the output
+    /// should be on. Wrapping around of the tick is handled.
+    pub fn update(&mut self, value: M, now_ms: u32) -> bool {
+        let (turn_on, turn_off) = match self.mode {
+            ThermostatMode::Heating => (
+                value <= self.setpoint - self.hysteresis,
+                value >= self.setpoint,
+            ),
+            ThermostatMode::Cooling => (
+                value >= self.setpoint + self.hysteresis,
+                value <= self.setpoint,
+            ),
+        };
+        let min_ms = if self.on {
+            self.min_on_ms
+        } else {
+            self.min_off_ms
+        };
+        let may_switch = self
+            .switched_at
+            .is_none_or(|at| is_due(now_ms, at.wrapping_add(min_ms)));
+
+        if may_switch && ((self.on && turn_off) || (!self.on && turn_on)) {
+            self.on = !self.on;
+            self.switched_at = Some(now_ms);
+        }
+        self.on
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heating() {
+        let mut thermostat = Thermostat::new(20.0, 1.0).with_min_times(0, 1000);
+        assert!(!thermostat.update(19.5, 0));
+        assert!(thermostat.update(19.0, 10));
+        assert!(thermostat.update(19.9, 20));
+        assert!(!thermostat.update(20.0, 30));
+        // too early to turn on again
+        assert!(!thermostat.update(18.0, 500));
+        assert!(thermostat.update(18.0, 1030));
+    }
+
+    #[test]
+    fn test_cooling() {
+        let mut thermostat = Thermostat::new(5.0, 2.0).with_mode(ThermostatMode::Cooling);
+        assert!(!thermostat.update(6.0, 0));
+        assert!(thermostat.update(7.0, 10));
+        assert!(!thermostat.update(5.0, 20));
+    }
+}
@@ -0,0 +1,1141 @@
+use hal::blocking::delay::DelayUs;
+use hal1::delay::DelayNs;
+
+use crate::TimestampSource;
+
+/// Durations of the phases of the 1-Wire time slots in nanoseconds.
+///
+/// [`Standard`] is a zero sized profile whose durations are constants, so the delay calls
+/// of a [`OneWire`](crate::OneWire) using it compile down to immediate values. [`Timing`]
+/// holds the durations at runtime for setups that need to tune them, e.g. long buses,
+/// [`ConstTiming`] defines custom constant durations. The durations are passed to a
+/// `DelayNs` provider as they are, which may round them up to its resolution.
+pub trait TimingProfile {
+    /// How long the line is held low to reset the bus
+    fn reset_low_ns(&self) -> u32;
+
+    /// Interval between the samples of the line while waiting for a presence pulse
+    fn presence_sample_interval_ns(&self) -> u32;
+
+    /// Number of samples taken while waiting for a presence pulse
+    fn presence_samples(&self) -> u16;
+
+    /// Time to wait after sampling for presence pulses until the reset is complete
+    fn reset_recovery_ns(&self) -> u32;
+
+    /// How long the line is held low to write a 1 bit
+    fn write_one_low_ns(&self) -> u32;
+
+    /// Time to wait after writing a 1 bit until the slot is complete
+    fn write_one_recovery_ns(&self) -> u32;
+
+    /// How long the line is held low to write a 0 bit
+    fn write_zero_low_ns(&self) -> u32;
+
+    /// Time to wait after writing a 0 bit until the slot is complete
+    fn write_zero_recovery_ns(&self) -> u32;
+
+    /// How long the line is held low to initiate a read slot
+    fn read_low_ns(&self) -> u32;
+
+    /// Time between releasing the line and sampling it in a read slot.
+    ///
+    /// Devices hold a 0 bit for at least 15µs after the start of the slot, the released
+    /// line then needs time to rise back to high. Long or heavily loaded buses rise slowly
+    /// and need a later sample point, while some fast clones release a 0 bit early and
+    /// need an earlier one. The latency of the pin access adds to the sample point.
+    fn read_sample_ns(&self) -> u32;
+
+    /// Time to wait after sampling until the read slot is complete
+    fn read_recovery_ns(&self) -> u32;
+
+    /// Time from the start of a read slot until the line is sampled, ignoring the latency
+    /// of the pin access
+    fn read_sample_point_ns(&self) -> u32 {
+        self.read_low_ns() + self.read_sample_ns()
+    }
+
+    /// The windows the specification allows for the phases of the time slots at the
+    /// speed of the profile, which a [`SlotCheck`] checks the measured phases against
+    fn slot_windows(&self) -> SlotWindows {
+        SlotWindows::STANDARD
+    }
+}
+
+/// The window the duration of a timing critical phase of a time slot has to stay in, see
+/// [`TimingGuard::phase`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseWindow {
+    pub min_ns: u32,
+    pub max_ns: u32,
+    /// The recovery time required after the slot before the next one may begin
+    pub min_recovery_ns: u32,
+}
+
+/// The windows the specification allows for the phases of the time slots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlotWindows {
+    /// tLOW1, how long the line is held low to write a 1 bit
+    pub write_one_low: PhaseWindow,
+    /// tLOW0, how long the line is held low to write a 0 bit
+    pub write_zero_low: PhaseWindow,
+    /// tMSR, the time from the start of a read slot until the line is sampled
+    pub read_sample_point: PhaseWindow,
+}
+
+impl SlotWindows {
+    pub const STANDARD: SlotWindows = SlotWindows {
+        write_one_low: PhaseWindow {
+            min_ns: 1000,
+            max_ns: 15_000,
+            min_recovery_ns: 1000,
+        },
+        write_zero_low: PhaseWindow {
+            min_ns: 60_000,
+            max_ns: 120_000,
+            min_recovery_ns: 1000,
+        },
+        read_sample_point: PhaseWindow {
+            min_ns: 1000,
+            max_ns: 15_000,
+            min_recovery_ns: 1000,
+        },
+    };
+
+    pub const OVERDRIVE: SlotWindows = SlotWindows {
+        write_one_low: PhaseWindow {
+            min_ns: 1000,
+            max_ns: 2000,
+            min_recovery_ns: 1000,
+        },
+        write_zero_low: PhaseWindow {
+            min_ns: 7500,
+            max_ns: 16_000,
+            min_recovery_ns: 1000,
+        },
+        read_sample_point: PhaseWindow {
+            min_ns: 1000,
+            max_ns: 2000,
+            min_recovery_ns: 1000,
+        },
+    };
+}
+
+/// The standard speed timing with constant durations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Standard;
+
+impl TimingProfile for Standard {
+    #[inline(always)]
+    fn reset_low_ns(&self) -> u32 {
+        480_000
+    }
+
+    #[inline(always)]
+    fn presence_sample_interval_ns(&self) -> u32 {
+        10_000
+    }
+
+    #[inline(always)]
+    fn presence_samples(&self) -> u16 {
+        7
+    }
+
+    #[inline(always)]
+    fn reset_recovery_ns(&self) -> u32 {
+        410_000
+    }
+
+    #[inline(always)]
+    fn write_one_low_ns(&self) -> u32 {
+        10_000
+    }
+
+    #[inline(always)]
+    fn write_one_recovery_ns(&self) -> u32 {
+        55_000
+    }
+
+    #[inline(always)]
+    fn write_zero_low_ns(&self) -> u32 {
+        65_000
+    }
+
+    #[inline(always)]
+    fn write_zero_recovery_ns(&self) -> u32 {
+        5000
+    }
+
+    #[inline(always)]
+    fn read_low_ns(&self) -> u32 {
+        3000
+    }
+
+    #[inline(always)]
+    fn read_sample_ns(&self) -> u32 {
+        2000
+    }
+
+    #[inline(always)]
+    fn read_recovery_ns(&self) -> u32 {
+        61_000
+    }
+}
+
+/// Standard speed timing for high latency pin access, like pins of I2C GPIO expanders or
+/// HALs that take several microseconds per pin operation.
+///
+/// The slots are lengthened and the sample points moved to the maximum the specification
+/// allows: a read slot is sampled 15µs after its start, the write 0 low time is extended
+/// to 90µs, within the 60-120µs window even if the pin adds 30µs, and the recovery times
+/// and the presence detection window cover the whole range of slow devices and lines.
+/// The latency of the pin access still adds to the read sample point, so it has to stay
+/// well below the 15µs a device holds a 0 bit at the least. Enable the `relaxed-timing`
+/// feature to make it the default profile of [`OneWire::new`](crate::OneWire::new).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Relaxed;
+
+impl TimingProfile for Relaxed {
+    #[inline(always)]
+    fn reset_low_ns(&self) -> u32 {
+        500_000
+    }
+
+    #[inline(always)]
+    fn presence_sample_interval_ns(&self) -> u32 {
+        10_000
+    }
+
+    #[inline(always)]
+    fn presence_samples(&self) -> u16 {
+        24
+    }
+
+    #[inline(always)]
+    fn reset_recovery_ns(&self) -> u32 {
+        250_000
+    }
+
+    #[inline(always)]
+    fn write_one_low_ns(&self) -> u32 {
+        1000
+    }
+
+    #[inline(always)]
+    fn write_one_recovery_ns(&self) -> u32 {
+        70_000
+    }
+
+    #[inline(always)]
+    fn write_zero_low_ns(&self) -> u32 {
+        90_000
+    }
+
+    #[inline(always)]
+    fn write_zero_recovery_ns(&self) -> u32 {
+        15_000
+    }
+
+    #[inline(always)]
+    fn read_low_ns(&self) -> u32 {
+        1000
+    }
+
+    #[inline(always)]
+    fn read_sample_ns(&self) -> u32 {
+        14_000
+    }
+
+    #[inline(always)]
+    fn read_recovery_ns(&self) -> u32 {
+        70_000
+    }
+}
+
+/// The overdrive speed timing, used after an Overdrive Skip ROM or Overdrive Match ROM
+/// command, see [`OneWire::overdrive_select`](crate::OneWire::overdrive_select).
+///
+/// The slots are about ten times shorter than at standard speed, which requires a delay
+/// provider and pin access with a latency well below a microsecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Overdrive;
+
+impl TimingProfile for Overdrive {
+    #[inline(always)]
+    fn reset_low_ns(&self) -> u32 {
+        70_000
+    }
+
+    #[inline(always)]
+    fn presence_sample_interval_ns(&self) -> u32 {
+        2000
+    }
+
+    #[inline(always)]
+    fn presence_samples(&self) -> u16 {
+        5
+    }
+
+    #[inline(always)]
+    fn reset_recovery_ns(&self) -> u32 {
+        40_000
+    }
+
+    #[inline(always)]
+    fn write_one_low_ns(&self) -> u32 {
+        1000
+    }
+
+    #[inline(always)]
+    fn write_one_recovery_ns(&self) -> u32 {
+        8000
+    }
+
+    #[inline(always)]
+    fn write_zero_low_ns(&self) -> u32 {
+        8000
+    }
+
+    #[inline(always)]
+    fn write_zero_recovery_ns(&self) -> u32 {
+        3000
+    }
+
+    #[inline(always)]
+    fn read_low_ns(&self) -> u32 {
+        1000
+    }
+
+    #[inline(always)]
+    fn read_sample_ns(&self) -> u32 {
+        1000
+    }
+
+    #[inline(always)]
+    fn read_recovery_ns(&self) -> u32 {
+        7000
+    }
+
+    #[inline(always)]
+    fn slot_windows(&self) -> SlotWindows {
+        SlotWindows::OVERDRIVE
+    }
+}
+
+/// A zero sized profile with the durations given as associated constants, which default
+/// to the [`Standard`] ones. Like the built-in profiles, the delays compile down to
+/// immediate values, for custom timings on small targets where a runtime [`Timing`]
+/// costs too much. Only the differing durations need to be given:
+///
+/// ```
+/// use onewire::timing::ConstTiming;
+///
+/// struct LongBus;
+///
+/// impl ConstTiming for LongBus {
+///     const RESET_LOW_NS: u32 = 500_000;
+///     const READ_SAMPLE_NS: u32 = 8000;
+///     const READ_RECOVERY_NS: u32 = 55_000;
+/// }
+/// ```
+pub trait ConstTiming {
+    const RESET_LOW_NS: u32 = 480_000;
+    const PRESENCE_SAMPLE_INTERVAL_NS: u32 = 10_000;
+    const PRESENCE_SAMPLES: u16 = 7;
+    const RESET_RECOVERY_NS: u32 = 410_000;
+    const WRITE_ONE_LOW_NS: u32 = 10_000;
+    const WRITE_ONE_RECOVERY_NS: u32 = 55_000;
+    const WRITE_ZERO_LOW_NS: u32 = 65_000;
+    const WRITE_ZERO_RECOVERY_NS: u32 = 5000;
+    const READ_LOW_NS: u32 = 3000;
+    const READ_SAMPLE_NS: u32 = 2000;
+    const READ_RECOVERY_NS: u32 = 61_000;
+}
+
+impl<C: ConstTiming> TimingProfile for C {
+    #[inline(always)]
+    fn reset_low_ns(&self) -> u32 {
+        C::RESET_LOW_NS
+    }
+
+    #[inline(always)]
+    fn presence_sample_interval_ns(&self) -> u32 {
+        C::PRESENCE_SAMPLE_INTERVAL_NS
+    }
+
+    #[inline(always)]
+    fn presence_samples(&self) -> u16 {
+        C::PRESENCE_SAMPLES
+    }
+
+    #[inline(always)]
+    fn reset_recovery_ns(&self) -> u32 {
+        C::RESET_RECOVERY_NS
+    }
+
+    #[inline(always)]
+    fn write_one_low_ns(&self) -> u32 {
+        C::WRITE_ONE_LOW_NS
+    }
+
+    #[inline(always)]
+    fn write_one_recovery_ns(&self) -> u32 {
+        C::WRITE_ONE_RECOVERY_NS
+    }
+
+    #[inline(always)]
+    fn write_zero_low_ns(&self) -> u32 {
+        C::WRITE_ZERO_LOW_NS
+    }
+
+    #[inline(always)]
+    fn write_zero_recovery_ns(&self) -> u32 {
+        C::WRITE_ZERO_RECOVERY_NS
+    }
+
+    #[inline(always)]
+    fn read_low_ns(&self) -> u32 {
+        C::READ_LOW_NS
+    }
+
+    #[inline(always)]
+    fn read_sample_ns(&self) -> u32 {
+        C::READ_SAMPLE_NS
+    }
+
+    #[inline(always)]
+    fn read_recovery_ns(&self) -> u32 {
+        C::READ_RECOVERY_NS
+    }
+}
+
+/// The profile used by [`OneWire::new`](crate::OneWire::new), [`Relaxed`] if the
+/// `relaxed-timing` feature is enabled, [`Standard`] otherwise
+#[cfg(not(feature = "relaxed-timing"))]
+pub type DefaultTiming = Standard;
+
+/// The profile used by [`OneWire::new`](crate::OneWire::new), [`Relaxed`] if the
+/// `relaxed-timing` feature is enabled, [`Standard`] otherwise
+#[cfg(feature = "relaxed-timing")]
+pub type DefaultTiming = Relaxed;
+
+/// Timing profile with durations configurable at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    pub reset_low_ns: u32,
+    pub presence_sample_interval_ns: u32,
+    pub presence_samples: u16,
+    pub reset_recovery_ns: u32,
+    pub write_one_low_ns: u32,
+    pub write_one_recovery_ns: u32,
+    pub write_zero_low_ns: u32,
+    pub write_zero_recovery_ns: u32,
+    pub read_low_ns: u32,
+    pub read_sample_ns: u32,
+    pub read_recovery_ns: u32,
+}
+
+impl Timing {
+    /// Copies the durations of the given profile
+    pub fn from_profile(profile: &impl TimingProfile) -> Self {
+        Timing {
+            reset_low_ns: profile.reset_low_ns(),
+            presence_sample_interval_ns: profile.presence_sample_interval_ns(),
+            presence_samples: profile.presence_samples(),
+            reset_recovery_ns: profile.reset_recovery_ns(),
+            write_one_low_ns: profile.write_one_low_ns(),
+            write_one_recovery_ns: profile.write_one_recovery_ns(),
+            write_zero_low_ns: profile.write_zero_low_ns(),
+            write_zero_recovery_ns: profile.write_zero_recovery_ns(),
+            read_low_ns: profile.read_low_ns(),
+            read_sample_ns: profile.read_sample_ns(),
+            read_recovery_ns: profile.read_recovery_ns(),
+        }
+    }
+
+    /// Moves the sample point of the read slots to `ns` after the start of the slot,
+    /// see [`TimingProfile::read_sample_ns`]. The recovery time is adjusted to keep the
+    /// length of the slots. Sample points before the end of the low time are clamped.
+    pub fn with_read_sample_point_ns(mut self, ns: u32) -> Self {
+        let slot = self.read_sample_point_ns() + self.read_recovery_ns;
+        self.read_sample_ns = ns.saturating_sub(self.read_low_ns);
+        self.read_recovery_ns = slot.saturating_sub(self.read_sample_point_ns());
+        self
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::from_profile(&Standard)
+    }
+}
+
+impl TimingProfile for Timing {
+    fn reset_low_ns(&self) -> u32 {
+        self.reset_low_ns
+    }
+
+    fn presence_sample_interval_ns(&self) -> u32 {
+        self.presence_sample_interval_ns
+    }
+
+    fn presence_samples(&self) -> u16 {
+        self.presence_samples
+    }
+
+    fn reset_recovery_ns(&self) -> u32 {
+        self.reset_recovery_ns
+    }
+
+    fn write_one_low_ns(&self) -> u32 {
+        self.write_one_low_ns
+    }
+
+    fn write_one_recovery_ns(&self) -> u32 {
+        self.write_one_recovery_ns
+    }
+
+    fn write_zero_low_ns(&self) -> u32 {
+        self.write_zero_low_ns
+    }
+
+    fn write_zero_recovery_ns(&self) -> u32 {
+        self.write_zero_recovery_ns
+    }
+
+    fn read_low_ns(&self) -> u32 {
+        self.read_low_ns
+    }
+
+    fn read_sample_ns(&self) -> u32 {
+        self.read_sample_ns
+    }
+
+    fn read_recovery_ns(&self) -> u32 {
+        self.read_recovery_ns
+    }
+}
+
+/// The profile of the current bus speed, the configured one or [`Overdrive`]
+pub(crate) struct ActiveTiming<'a, T> {
+    pub(crate) timing: &'a T,
+    pub(crate) overdrive: bool,
+}
+
+impl<T: TimingProfile> TimingProfile for ActiveTiming<'_, T> {
+    #[inline(always)]
+    fn reset_low_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.reset_low_ns()
+        } else {
+            self.timing.reset_low_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn presence_sample_interval_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.presence_sample_interval_ns()
+        } else {
+            self.timing.presence_sample_interval_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn presence_samples(&self) -> u16 {
+        if self.overdrive {
+            Overdrive.presence_samples()
+        } else {
+            self.timing.presence_samples()
+        }
+    }
+
+    #[inline(always)]
+    fn reset_recovery_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.reset_recovery_ns()
+        } else {
+            self.timing.reset_recovery_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn write_one_low_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.write_one_low_ns()
+        } else {
+            self.timing.write_one_low_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn write_one_recovery_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.write_one_recovery_ns()
+        } else {
+            self.timing.write_one_recovery_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn write_zero_low_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.write_zero_low_ns()
+        } else {
+            self.timing.write_zero_low_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn write_zero_recovery_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.write_zero_recovery_ns()
+        } else {
+            self.timing.write_zero_recovery_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn read_low_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.read_low_ns()
+        } else {
+            self.timing.read_low_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn read_sample_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.read_sample_ns()
+        } else {
+            self.timing.read_sample_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn read_recovery_ns(&self) -> u32 {
+        if self.overdrive {
+            Overdrive.read_recovery_ns()
+        } else {
+            self.timing.read_recovery_ns()
+        }
+    }
+
+    #[inline(always)]
+    fn slot_windows(&self) -> SlotWindows {
+        if self.overdrive {
+            Overdrive.slot_windows()
+        } else {
+            self.timing.slot_windows()
+        }
+    }
+}
+
+/// Protects the timing critical phases of the time slots, e.g. from the low phase of a
+/// slot until the line is released or sampled, against being stretched by interrupts.
+///
+/// Implement it to plug in a bespoke interrupt masking scheme, see
+/// [`OneWire::with_guard`](crate::OneWire::with_guard), or enable the `critical-section`
+/// feature for a portable `CriticalSectionGuard`. The phases are a few
+/// microseconds short, longer waits like the reset pulse and the presence window are not
+/// guarded, only the individual presence samples are.
+pub trait TimingGuard {
+    /// Called when a timing critical phase begins
+    fn enter(&mut self);
+
+    /// Called when a timing critical phase ends
+    fn exit(&mut self);
+
+    /// Called before [`TimingGuard::enter`] for the phases of the time slots, with the
+    /// window the specification allows for their duration. Guards measuring the phases
+    /// can check them, see [`SlotCheck`].
+    #[inline(always)]
+    fn phase(&mut self, _window: PhaseWindow) {}
+}
+
+/// A guard that does nothing, the default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoGuard;
+
+impl TimingGuard for NoGuard {
+    #[inline(always)]
+    fn enter(&mut self) {}
+
+    #[inline(always)]
+    fn exit(&mut self) {}
+}
+
+/// A guard entering a critical section from the `critical-section` crate for each timing
+/// critical phase, which masks interrupts on single core targets in a portable way
+#[cfg(feature = "critical-section")]
+#[derive(Debug, Default)]
+pub struct CriticalSectionGuard {
+    restore_state: Option<critical_section::RestoreState>,
+}
+
+#[cfg(feature = "critical-section")]
+impl TimingGuard for CriticalSectionGuard {
+    #[inline(always)]
+    fn enter(&mut self) {
+        // the phases are never nested and always exited before the next one is entered
+        self.restore_state = Some(unsafe { critical_section::acquire() });
+    }
+
+    #[inline(always)]
+    fn exit(&mut self) {
+        if let Some(restore_state) = self.restore_state.take() {
+            unsafe { critical_section::release(restore_state) }
+        }
+    }
+}
+
+/// Statistics about delays that took longer than requested, collected by [`CheckedDelay`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OvershootStats {
+    /// Number of delays performed
+    pub delays: u32,
+    /// Number of delays that exceeded the requested duration by more than the tolerance
+    pub overshoots: u32,
+    /// The largest excess duration in microseconds
+    pub max_overshoot_us: u32,
+}
+
+impl OvershootStats {
+    /// Whether any delay overshot, in which case the bus operation performed while
+    /// collecting these statistics likely violated the slot timing
+    pub fn is_suspect(&self) -> bool {
+        self.overshoots > 0
+    }
+}
+
+/// Wraps a delay provider and measures each delay with a [`TimestampSource`] to detect
+/// delays that overshot their budget, e.g. because an interrupt fired. Use a [`SlotCheck`]
+/// to check the resulting slots against the specification.
+///
+/// Take the statistics before and after a bus operation to find out whether its timing
+/// is suspect, which allows to attribute sporadic CRC errors to scheduling instead of
+/// wiring:
+///
+/// ```ignore
+/// delay.take_stats();
+/// let result = sensor.read_measurement(&mut wire, &mut delay);
+/// if delay.take_stats().is_suspect() {
+///     // retry instead of blaming the sensor
+/// }
+/// ```
+pub struct CheckedDelay<D, S> {
+    delay: D,
+    source: S,
+    tolerance_us: u16,
+    stats: OvershootStats,
+    panic_on_overshoot: bool,
+}
+
+impl<D: DelayNs, S: TimestampSource> CheckedDelay<D, S> {
+    /// Delays exceeding the requested duration by more than `tolerance_us` are counted
+    /// as overshoots
+    pub fn new(delay: D, source: S, tolerance_us: u16) -> Self {
+        CheckedDelay {
+            delay,
+            source,
+            tolerance_us,
+            stats: OvershootStats::default(),
+            panic_on_overshoot: false,
+        }
+    }
+
+    /// Panics on the first overshoot in debug builds, which turns silent timing
+    /// violations into an immediate error during development. Release builds only
+    /// collect the statistics.
+    pub fn panic_on_overshoot(mut self) -> Self {
+        self.panic_on_overshoot = true;
+        self
+    }
+
+    pub fn stats(&self) -> &OvershootStats {
+        &self.stats
+    }
+
+    /// Returns the statistics collected so far and resets them
+    pub fn take_stats(&mut self) -> OvershootStats {
+        core::mem::take(&mut self.stats)
+    }
+
+    pub fn into_inner(self) -> (D, S) {
+        (self.delay, self.source)
+    }
+}
+
+impl<D: DelayNs, S: TimestampSource> DelayNs for CheckedDelay<D, S> {
+    fn delay_ns(&mut self, ns: u32) {
+        let start = self.source.now_us();
+        self.delay.delay_ns(ns);
+        let elapsed = self.source.now_us().wrapping_sub(start);
+        // the timestamps have a resolution of a microsecond
+        let us = ns.div_ceil(1000);
+        let budget = us + u32::from(self.tolerance_us);
+
+        self.stats.delays = self.stats.delays.saturating_add(1);
+        if elapsed > budget {
+            let overshoot = elapsed - us;
+            self.stats.overshoots = self.stats.overshoots.saturating_add(1);
+            self.stats.max_overshoot_us = self.stats.max_overshoot_us.max(overshoot);
+            debug_assert!(
+                !self.panic_on_overshoot,
+                "1-Wire delay of {}us took {}us, exceeding the tolerance of {}us",
+                us, elapsed, self.tolerance_us
+            );
+        }
+    }
+}
+
+/// Statistics about the time slots checked by a [`SlotCheck`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SlotStats {
+    /// Number of slots measured
+    pub slots: u32,
+    /// Number of slots whose low phase, sample point or recovery time was outside of the
+    /// window of the specification
+    pub violations: u32,
+}
+
+impl SlotStats {
+    /// Whether any slot violated the specification
+    pub fn is_suspect(&self) -> bool {
+        self.violations > 0
+    }
+}
+
+/// Wraps a [`TimingGuard`] and measures the phases of each time slot with a
+/// [`TimestampSource`], checking them against the windows of the specification, see
+/// [`TimingProfile::slot_windows`]: the low time of write slots, the sample point of
+/// read slots and the recovery time until the next slot.
+///
+/// Unlike [`CheckedDelay`], which only notices delays that took longer than requested,
+/// this also catches profiles and pin latencies that add up to an invalid slot. The
+/// timestamps have a resolution of a microsecond, so only durations that are certainly
+/// outside of a window are counted.
+///
+/// ```ignore
+/// let check = SlotCheck::new(NoGuard, timer).panic_on_violation();
+/// let mut wire = OneWire::new(pin, false).with_guard(check);
+/// wire.reset(&mut delay)?;
+/// assert!(!wire.guard_mut().take_stats().is_suspect());
+/// ```
+pub struct SlotCheck<G, S> {
+    guard: G,
+    source: S,
+    window: Option<PhaseWindow>,
+    started_at: Option<u32>,
+    /// The end of the last slot and the recovery time required after it
+    recovery: Option<(u32, u32)>,
+    stats: SlotStats,
+    panic_on_violation: bool,
+}
+
+impl<G: TimingGuard, S: TimestampSource> SlotCheck<G, S> {
+    pub fn new(guard: G, source: S) -> Self {
+        SlotCheck {
+            guard,
+            source,
+            window: None,
+            started_at: None,
+            recovery: None,
+            stats: SlotStats::default(),
+            panic_on_violation: false,
+        }
+    }
+
+    /// Panics on the first violation in debug builds, which turns silent timing
+    /// violations into an immediate error during development. Release builds only
+    /// collect the statistics.
+    pub fn panic_on_violation(mut self) -> Self {
+        self.panic_on_violation = true;
+        self
+    }
+
+    pub fn stats(&self) -> &SlotStats {
+        &self.stats
+    }
+
+    /// Returns the statistics collected so far and resets them
+    pub fn take_stats(&mut self) -> SlotStats {
+        core::mem::take(&mut self.stats)
+    }
+
+    pub fn into_inner(self) -> (G, S) {
+        (self.guard, self.source)
+    }
+
+    fn violation(&mut self, what: &str, us: u32) {
+        self.stats.violations = self.stats.violations.saturating_add(1);
+        debug_assert!(
+            !self.panic_on_violation,
+            "1-Wire {} of about {}us is outside of the specification",
+            what, us
+        );
+    }
+}
+
+impl<G: TimingGuard, S: TimestampSource> TimingGuard for SlotCheck<G, S> {
+    fn enter(&mut self) {
+        self.guard.enter();
+        if self.window.is_none() {
+            // not a slot, e.g. a presence sample
+            self.recovery = None;
+            return;
+        }
+        let now = self.source.now_us();
+        if let Some((end, min_recovery_ns)) = self.recovery.take() {
+            let us = now.wrapping_sub(end);
+            if us.saturating_add(1).saturating_mul(1000) <= min_recovery_ns {
+                self.violation("recovery time", us);
+            }
+        }
+        self.started_at = Some(now);
+    }
+
+    fn exit(&mut self) {
+        let now = self.source.now_us();
+        self.guard.exit();
+        if let (Some(window), Some(start)) = (self.window.take(), self.started_at.take()) {
+            let us = now.wrapping_sub(start);
+            self.stats.slots = self.stats.slots.saturating_add(1);
+            // the actual duration is less than a microsecond off the measured one
+            if us.saturating_add(1).saturating_mul(1000) <= window.min_ns
+                || us.saturating_sub(1).saturating_mul(1000) >= window.max_ns
+            {
+                self.violation("slot phase", us);
+            }
+            self.recovery = Some((now, window.min_recovery_ns));
+        }
+    }
+
+    fn phase(&mut self, window: PhaseWindow) {
+        self.window = Some(window);
+    }
+}
+
+/// Adapts a delay provider of embedded-hal 0.2 to the `DelayNs` trait the bus uses.
+/// The durations are rounded up to whole microseconds, which keeps the standard speed
+/// slots within the specification but is too coarse for overdrive speed.
+#[derive(Debug)]
+pub struct UsDelay<D>(pub D);
+
+impl<D: DelayUs<u16>> DelayNs for UsDelay<D> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, mut us: u32) {
+        while us > u32::from(u16::MAX) {
+            self.0.delay_us(u16::MAX);
+            us -= u32::from(u16::MAX);
+        }
+        self.0.delay_us(us as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{IdleLine, NoDelay};
+    use crate::OneWire;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_const_timing_defaults() {
+        struct LongReset;
+
+        impl ConstTiming for LongReset {
+            const RESET_LOW_NS: u32 = 500_000;
+        }
+
+        let timing = Timing::from_profile(&LongReset);
+        assert_eq!(timing.reset_low_ns, 500_000);
+        assert_eq!(
+            Timing {
+                reset_low_ns: 480_000,
+                ..timing
+            },
+            Timing::from_profile(&Standard)
+        );
+    }
+
+    #[test]
+    fn test_checked_delay_overshoot() {
+        // every delay appears to take 15us, regardless of the requested duration
+        let now = Cell::new(0u32);
+        let source = || {
+            let t = now.get();
+            now.set(t.wrapping_add(15));
+            t
+        };
+        let mut delay = CheckedDelay::new(NoDelay, source, 20);
+        delay.delay_us(5);
+        assert!(!delay.take_stats().is_suspect());
+
+        let mut delay = CheckedDelay::new(NoDelay, source, 5);
+        delay.delay_us(5);
+        delay.delay_us(20);
+        let stats = delay.take_stats();
+        assert_eq!(stats.delays, 2);
+        assert_eq!(stats.overshoots, 1);
+        assert_eq!(stats.max_overshoot_us, 10);
+        assert_eq!(delay.stats(), &OvershootStats::default());
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn test_checked_delay_panic_on_overshoot() {
+        let mut now = 0u32;
+        let source = move || {
+            now = now.wrapping_add(15);
+            now
+        };
+        let mut delay = CheckedDelay::new(NoDelay, source, 5).panic_on_overshoot();
+        delay.delay_us(5);
+    }
+
+    #[test]
+    fn test_us_delay_rounds_up() {
+        struct Recorded(u32);
+
+        impl DelayUs<u16> for Recorded {
+            fn delay_us(&mut self, us: u16) {
+                self.0 += u32::from(us);
+            }
+        }
+
+        let mut delay = UsDelay(Recorded(0));
+        delay.delay_ns(2500);
+        assert_eq!(delay.0 .0, 3);
+        delay.delay_ms(100);
+        assert_eq!(delay.0 .0, 100_003);
+    }
+
+    #[test]
+    fn test_relaxed_sample_points() {
+        assert_eq!(Relaxed.read_sample_point_ns(), 15_000);
+        assert!((60_000..=120_000).contains(&Relaxed.write_zero_low_ns()));
+        assert!(Relaxed.read_sample_point_ns() >= Standard.read_sample_point_ns());
+        assert!(
+            Relaxed.presence_sample_interval_ns() * u32::from(Relaxed.presence_samples())
+                >= 240_000
+        );
+    }
+
+    #[test]
+    fn test_read_sample_point() {
+        let timing = Timing::default().with_read_sample_point_ns(12_500);
+        assert_eq!(timing.read_low_ns, 3000);
+        assert_eq!(timing.read_sample_ns, 9500);
+        assert_eq!(timing.read_sample_point_ns(), 12_500);
+        assert_eq!(
+            timing.read_sample_point_ns() + timing.read_recovery_ns,
+            Standard.read_sample_point_ns() + Standard.read_recovery_ns()
+        );
+    }
+
+    #[test]
+    fn test_guard_not_held_across_presence_window() {
+        /// Accumulates the delays spent within the guard since it was entered
+        struct Guarded<'a>(&'a Cell<Option<u32>>);
+
+        impl TimingGuard for Guarded<'_> {
+            fn enter(&mut self) {
+                self.0.set(Some(0));
+            }
+
+            fn exit(&mut self) {
+                self.0.set(None);
+            }
+        }
+
+        struct MeasuredDelay<'a>(&'a Cell<Option<u32>>);
+
+        impl DelayNs for MeasuredDelay<'_> {
+            fn delay_ns(&mut self, ns: u32) {
+                if let Some(held) = self.0.get() {
+                    let held = held + ns;
+                    assert!(held <= 20_000, "guard held for {}ns", held);
+                    self.0.set(Some(held));
+                }
+            }
+        }
+
+        for relaxed in [false, true] {
+            let held = Cell::new(None);
+            let mut delay = MeasuredDelay(&held);
+            if relaxed {
+                let mut wire =
+                    OneWire::with_timing(IdleLine, false, Relaxed).with_guard(Guarded(&held));
+                assert!(!wire.reset(&mut delay).unwrap());
+                assert!(!wire.reset_with_info(&mut delay).unwrap().presence);
+            } else {
+                let mut wire = OneWire::new(IdleLine, false).with_guard(Guarded(&held));
+                assert!(!wire.reset(&mut delay).unwrap());
+                assert!(!wire.reset_with_info(&mut delay).unwrap().presence);
+            }
+        }
+    }
+
+    /// A delay advancing a simulated microsecond clock
+    struct ClockDelay<'a>(&'a Cell<u64>);
+
+    impl DelayNs for ClockDelay<'_> {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0.set(self.0.get() + u64::from(ns));
+        }
+    }
+
+    #[test]
+    fn test_slot_check() {
+        let now = Cell::new(0u64);
+        let source = || (now.get() / 1000) as u32;
+        let mut wire = OneWire::new(IdleLine, false).with_guard(SlotCheck::new(NoGuard, source));
+        wire.write_bytes(&mut ClockDelay(&now), &[0x0F, 0xCC])
+            .unwrap();
+        wire.read_bit(&mut ClockDelay(&now)).unwrap();
+        let stats = wire.guard_mut().take_stats();
+        assert_eq!(stats.slots, 17);
+        assert!(!stats.is_suspect());
+
+        // a write 0 low time beyond 120us
+        let timing = Timing {
+            write_zero_low_ns: 130_000,
+            ..Timing::default()
+        };
+        let mut wire = OneWire::with_timing(IdleLine, false, timing)
+            .with_guard(SlotCheck::new(NoGuard, source));
+        wire.write_bytes(&mut ClockDelay(&now), &[0xFE]).unwrap();
+        assert_eq!(wire.guard().stats().violations, 1);
+
+        // no recovery time between the slots of a byte
+        let timing = Timing {
+            write_one_recovery_ns: 0,
+            ..Timing::default()
+        };
+        let mut wire = OneWire::with_timing(IdleLine, false, timing)
+            .with_guard(SlotCheck::new(NoGuard, source));
+        wire.write_bytes(&mut ClockDelay(&now), &[0xFF]).unwrap();
+        assert_eq!(wire.guard().stats().violations, 7);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_slot_check_panic_on_violation() {
+        let now = Cell::new(0u64);
+        let source = || (now.get() / 1000) as u32;
+        let timing = Timing {
+            read_sample_ns: 20_000,
+            ..Timing::default()
+        };
+        let mut wire = OneWire::with_timing(IdleLine, false, timing)
+            .with_guard(SlotCheck::new(NoGuard, source).panic_on_violation());
+        wire.read_bit(&mut ClockDelay(&now)).unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use hal1::delay::DelayNs;
+
+use crate::discover::Discoverable;
+use crate::ds2409::{Branch, DS2409};
+use crate::label::AsDevice;
+use crate::Device;
+use crate::DeviceSearch;
+use crate::Error;
+use crate::OneWire;
+use crate::OpenDrainOutput;
+use crate::TimingGuard;
+use crate::TimingProfile;
+
+/// Largest number of couplers between the trunk and a device a [`BranchPath`] can hold
+pub const MAX_BRANCH_DEPTH: usize = 4;
+
+/// A branch of a coupler on the way from the trunk to a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    /// Address of the [`DS2409`]
+    pub coupler: [u8; 8],
+    pub branch: Branch,
+}
+
+/// The couplers and their branches that have to be connected to reach a device, empty
+/// for devices on the trunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchPath {
+    hops: [Hop; MAX_BRANCH_DEPTH],
+    depth: u8,
+}
+
+impl BranchPath {
+    pub const fn trunk() -> Self {
+        BranchPath {
+            hops: [Hop {
+                coupler: [0u8; 8],
+                branch: Branch::Main,
+            }; MAX_BRANCH_DEPTH],
+            depth: 0,
+        }
+    }
+
+    pub fn is_trunk(&self) -> bool {
+        self.depth == 0
+    }
+
+    /// The hops starting at the trunk
+    pub fn hops(&self) -> &[Hop] {
+        &self.hops[..usize::from(self.depth)]
+    }
+
+    /// Returns the path extended by the given branch, `None` if it is already
+    /// [`MAX_BRANCH_DEPTH`] hops long
+    pub fn push(&self, coupler: &Device, branch: Branch) -> Option<BranchPath> {
+        let mut path = *self;
+        *path.hops.get_mut(usize::from(self.depth))? = Hop {
+            coupler: coupler.address,
+            branch,
+        };
+        path.depth += 1;
+        Some(path)
+    }
+}
+
+impl Default for BranchPath {
+    fn default() -> Self {
+        BranchPath::trunk()
+    }
+}
+
+/// Discovers the devices on the trunk and behind each [`DS2409`] with one call, reporting
+/// each device with the path to reach it. The branches of couplers on a branch are
+/// walked as well, up to [`MAX_BRANCH_DEPTH`] couplers deep. Returns the number of
+/// devices reported.
+///
+/// All couplers are turned off before their segment is searched and again after their
+/// branches have been walked, so each search only sees a single segment. Couplers are
+/// reported like any other device, the branches of couplers deeper than
+/// [`MAX_BRANCH_DEPTH`] are not walked.
+pub fn enumerate<O: OpenDrainOutput, T: TimingProfile, G: TimingGuard>(
+    wire: &mut OneWire<O, T, G>,
+    delay: &mut impl DelayNs,
+    mut f: impl FnMut(&BranchPath, &Device),
+) -> Result<usize, Error<O::Error>> {
+    let mut couplers = DeviceSearch::new_for_family(crate::ds2409::FAMILY_CODE);
+    while let Some(device) = wire.search_next(&mut couplers, delay)? {
+        if let Some(coupler) = DS2409::from_device(device) {
+            coupler.all_lines_off(wire, delay)?;
+        }
+    }
+
+    let trunk = BranchPath::trunk();
+    let mut count = 0;
+    let mut search = DeviceSearch::new();
+    while let Some(device) = wire.search_next(&mut search, delay)? {
+        f(&trunk, &device);
+        count += 1;
+    }
+
+    let mut couplers = DeviceSearch::new_for_family(crate::ds2409::FAMILY_CODE);
+    while let Some(device) = wire.search_next(&mut couplers, delay)? {
+        if let Some(coupler) = DS2409::from_device(device) {
+            count += enumerate_coupler(wire, delay, &trunk, &coupler, &mut f)?;
+        }
+    }
+    Ok(count)
+}
+
+/// Reports the devices on both branches of a coupler reachable over `path` and walks the
+/// couplers on them, then turns the coupler off
+fn enumerate_coupler<O, T, G, D, F>(
+    wire: &mut OneWire<O, T, G>,
+    delay: &mut D,
+    path: &BranchPath,
+    coupler: &DS2409,
+    f: &mut F,
+) -> Result<usize, Error<O::Error>>
+where
+    O: OpenDrainOutput,
+    T: TimingProfile,
+    G: TimingGuard,
+    D: DelayNs,
+    F: FnMut(&BranchPath, &Device),
+{
+    let mut count = 0;
+    for branch in [Branch::Main, Branch::Auxiliary] {
+        let path = match path.push(coupler.as_device(), branch) {
+            Some(path) => path,
+            None => break,
+        };
+
+        // the branches of nested couplers would otherwise take part in the search
+        let mut couplers = DeviceSearch::new_for_family(crate::ds2409::FAMILY_CODE);
+        while let Some(device) = coupler.search_branch(wire, delay, branch, &mut couplers)? {
+            if let Some(nested) = DS2409::from_device(device) {
+                nested.all_lines_off(wire, delay)?;
+            }
+        }
+
+        let mut search = DeviceSearch::new();
+        while let Some(device) = coupler.search_branch(wire, delay, branch, &mut search)? {
+            f(&path, &device);
+            count += 1;
+        }
+
+        // each pass connects the branch again, which the nested couplers are reached over
+        let mut couplers = DeviceSearch::new_for_family(crate::ds2409::FAMILY_CODE);
+        while let Some(device) = coupler.search_branch(wire, delay, branch, &mut couplers)? {
+            if let Some(nested) = DS2409::from_device(device) {
+                count += enumerate_coupler(wire, delay, &path, &nested, f)?;
+            }
+        }
+    }
+    coupler.all_lines_off(wire, delay)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::NoDelay;
+    use crate::{BusMaster, MasterPin};
+    use core::convert::Infallible;
+
+    /// A device of a [`Network`] and the coupler branch it is attached to, `None` for the
+    /// trunk
+    struct Node {
+        address: [u8; 8],
+        parent: Option<(usize, Branch)>,
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Phase {
+        RomCommand,
+        MatchRom(usize),
+        Search,
+        Function,
+        Response,
+    }
+
+    /// Simulates up to eight devices and DS2409 couplers on a trunk and the branches of
+    /// the couplers, which understand Match ROM, Search ROM, All Lines Off and Smart-On
+    struct Network<'a> {
+        nodes: &'a [Node],
+        /// The branch connected by each coupler
+        connected: [Option<Branch>; 8],
+        /// The last command received by each coupler
+        last_command: [u8; 8],
+        /// Devices taking part since the last reset
+        active: [bool; 8],
+        phase: Phase,
+        matched: [u8; 8],
+        bit: u8,
+        complement: bool,
+        response: [u8; 2],
+        response_len: usize,
+    }
+
+    impl<'a> Network<'a> {
+        fn new(nodes: &'a [Node]) -> Self {
+            Network {
+                nodes,
+                connected: [None; 8],
+                last_command: [0; 8],
+                active: [false; 8],
+                phase: Phase::RomCommand,
+                matched: [0; 8],
+                bit: 0,
+                complement: false,
+                response: [0; 2],
+                response_len: 0,
+            }
+        }
+
+        fn is_connected(&self, node: usize) -> bool {
+            match self.nodes[node].parent {
+                None => true,
+                Some((coupler, branch)) => {
+                    self.connected[coupler] == Some(branch) && self.is_connected(coupler)
+                }
+            }
+        }
+
+        /// Whether the node is reached over the given branch of the coupler
+        fn is_behind(&self, node: usize, coupler: usize, branch: Branch) -> bool {
+            match self.nodes[node].parent {
+                None => false,
+                Some(parent) if parent == (coupler, branch) => true,
+                Some((parent, _)) => self.is_behind(parent, coupler, branch),
+            }
+        }
+
+        fn address_bit(&self, node: usize) -> bool {
+            self.nodes[node].address[usize::from(self.bit / 8)] & (1 << (self.bit % 8)) != 0
+        }
+
+        fn respond(&mut self, bytes: &[u8]) {
+            self.response[..bytes.len()].copy_from_slice(bytes);
+            self.response_len = bytes.len();
+            self.phase = Phase::Response;
+        }
+
+        fn function(&mut self, command: u8) {
+            let coupler = match self.active.iter().position(|active| *active) {
+                Some(coupler) => coupler,
+                None => return,
+            };
+            self.last_command[coupler] = command;
+            match command {
+                0x66 => {
+                    self.connected[coupler] = None;
+                    self.respond(&[command]);
+                }
+                0xCC | 0x33 => {
+                    let branch = if command == 0xCC {
+                        Branch::Main
+                    } else {
+                        Branch::Auxiliary
+                    };
+                    self.connected[coupler] = Some(branch);
+                    // only the freshly reset branch takes part in the following search
+                    for node in 0..self.nodes.len() {
+                        self.active[node] =
+                            self.is_behind(node, coupler, branch) && self.is_connected(node);
+                    }
+                    let presence = self.active.iter().any(|active| *active);
+                    self.respond(&[if presence { 0x00 } else { 0xFF }, command]);
+                }
+                _ => panic!("unexpected coupler command {:#04x}", command),
+            }
+        }
+    }
+
+    impl BusMaster for Network<'_> {
+        type Error = Infallible;
+
+        fn reset(&mut self) -> Result<bool, Error<Infallible>> {
+            for node in 0..self.nodes.len() {
+                self.active[node] = self.is_connected(node);
+            }
+            self.phase = Phase::RomCommand;
+            Ok(self.active.iter().any(|active| *active))
+        }
+
+        fn read_bit(&mut self) -> Result<bool, Infallible> {
+            assert!(self.phase == Phase::Search);
+            // the line is low if any active device pulls it low
+            let complement = self.complement;
+            self.complement = !complement;
+            Ok((0..self.nodes.len())
+                .filter(|node| self.active[*node])
+                .all(|node| self.address_bit(node) != complement))
+        }
+
+        fn write_bit(&mut self, high: bool) -> Result<(), Infallible> {
+            assert!(self.phase == Phase::Search);
+            for node in 0..self.nodes.len() {
+                if self.address_bit(node) != high {
+                    self.active[node] = false;
+                }
+            }
+            self.bit += 1;
+            self.complement = false;
+            Ok(())
+        }
+
+        fn read_byte(&mut self) -> Result<u8, Infallible> {
+            if self.phase != Phase::Response || self.response_len == 0 {
+                return Ok(0xFF);
+            }
+            let byte = self.response[0];
+            self.response.copy_within(1.., 0);
+            self.response_len -= 1;
+            Ok(byte)
+        }
+
+        fn write_byte(&mut self, byte: u8, _strong_pull_up: bool) -> Result<(), Infallible> {
+            match self.phase {
+                // the devices reset by a smart-on wait for a ROM command as well
+                Phase::RomCommand | Phase::Response => match byte {
+                    0x55 => self.phase = Phase::MatchRom(0),
+                    0xF0 => {
+                        self.phase = Phase::Search;
+                        self.bit = 0;
+                        self.complement = false;
+                    }
+                    _ => panic!("unexpected ROM command {:#04x}", byte),
+                },
+                Phase::MatchRom(n) => {
+                    self.matched[n] = byte;
+                    if n < 7 {
+                        self.phase = Phase::MatchRom(n + 1);
+                    } else {
+                        for node in 0..self.nodes.len() {
+                            self.active[node] &= self.nodes[node].address == self.matched;
+                        }
+                        self.phase = Phase::Function;
+                    }
+                }
+                Phase::Function => self.function(byte),
+                Phase::Search => panic!("byte written during a search"),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_enumerate_nested_coupler() {
+        const TRUNK: [u8; 8] = [0x28, 1, 0, 0, 0, 0, 0, 0];
+        const OUTER: [u8; 8] = [0x1F, 1, 0, 0, 0, 0, 0, 0];
+        const MAIN: [u8; 8] = [0x28, 2, 0, 0, 0, 0, 0, 0];
+        const INNER: [u8; 8] = [0x1F, 2, 0, 0, 0, 0, 0, 0];
+        const NESTED: [u8; 8] = [0x28, 3, 0, 0, 0, 0, 0, 0];
+        let nodes = [
+            Node {
+                address: TRUNK,
+                parent: None,
+            },
+            Node {
+                address: OUTER,
+                parent: None,
+            },
+            Node {
+                address: MAIN,
+                parent: Some((1, Branch::Main)),
+            },
+            Node {
+                address: INNER,
+                parent: Some((1, Branch::Main)),
+            },
+            Node {
+                address: NESTED,
+                parent: Some((3, Branch::Auxiliary)),
+            },
+        ];
+        let mut wire = OneWire::new(MasterPin(Network::new(&nodes)), false);
+
+        let mut reported = [None; 5];
+        let count = enumerate(&mut wire, &mut NoDelay, |path, device| {
+            let node = nodes
+                .iter()
+                .position(|node| node.address == device.address)
+                .unwrap();
+            assert!(reported[node].is_none(), "{:?} reported twice", device);
+            reported[node] = Some(*path);
+        })
+        .unwrap();
+        assert_eq!(count, 5);
+
+        let outer_main = BranchPath::trunk()
+            .push(&Device { address: OUTER }, Branch::Main)
+            .unwrap();
+        let inner_aux = outer_main
+            .push(&Device { address: INNER }, Branch::Auxiliary)
+            .unwrap();
+        assert_eq!(reported[0], Some(BranchPath::trunk()));
+        assert_eq!(reported[1], Some(BranchPath::trunk()));
+        assert_eq!(reported[2], Some(outer_main));
+        assert_eq!(reported[3], Some(outer_main));
+        assert_eq!(reported[4], Some(inner_aux));
+
+        let network = &wire.output.0;
+        for coupler in [1, 3] {
+            assert_eq!(network.connected[coupler], None);
+            assert_eq!(network.last_command[coupler], 0x66);
+        }
+    }
+
+    #[test]
+    fn test_branch_path() {
+        let coupler = Device {
+            address: [0x1F, 1, 2, 3, 4, 5, 6, 0],
+        };
+        let mut path = BranchPath::trunk();
+        assert!(path.is_trunk());
+        for _ in 0..MAX_BRANCH_DEPTH {
+            path = path.push(&coupler, Branch::Auxiliary).unwrap();
+        }
+        assert_eq!(path.hops().len(), MAX_BRANCH_DEPTH);
+        assert_eq!(path.hops()[0].coupler, coupler.address);
+        assert!(path.push(&coupler, Branch::Main).is_none());
+    }
+}
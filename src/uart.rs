@@ -0,0 +1,158 @@
+use core::fmt::Debug;
+
+use crate::BusMaster;
+use crate::Error;
+use crate::OpenDrainOutput;
+
+/// Baud rate at which a byte lasts as long as a reset and its presence detection
+const RESET_BAUD: u32 = 9600;
+
+/// Baud rate at which a byte lasts as long as a time slot
+const SLOT_BAUD: u32 = 115_200;
+
+/// Sent for a reset, the presence pulse of a device overwrites some of the high bits
+const RESET_BYTE: u8 = 0xF0;
+
+/// A UART with TX and RX tied to the bus, e.g. TX through an open drain output or a
+/// diode, so each sent byte is received back as the bus saw it
+pub trait Uart {
+    type Error: Sized + Debug;
+
+    fn set_baud_rate(&mut self, baud: u32) -> Result<(), Self::Error>;
+
+    /// Sends a byte and returns the byte received meanwhile
+    fn transfer(&mut self, byte: u8) -> Result<u8, Self::Error>;
+}
+
+/// Generates the resets and time slots with a UART: a reset is a 0xF0 sent at 9600 baud,
+/// each time slot a byte at 115200 baud whose start bit pulls the line low. 0xFF writes
+/// or reads a 1 and 0x00 writes a 0. The timing is kept by the UART hardware, which
+/// avoids the jitter of toggling a pin on busy or slow MCUs.
+///
+/// It is used as the pin of a [`OneWire`](crate::OneWire), which then forwards its
+/// transfers, see [`BusMaster`]. The UART can't power parasite devices on its own.
+#[derive(Debug)]
+pub struct UartWire<U> {
+    uart: U,
+    baud: u32,
+}
+
+impl<U: Uart> UartWire<U> {
+    /// Uses the given UART, which is switched to the required baud rates as needed
+    pub fn new(uart: U) -> Self {
+        UartWire { uart, baud: 0 }
+    }
+
+    pub fn into_inner(self) -> U {
+        self.uart
+    }
+
+    fn transfer_at(&mut self, baud: u32, byte: u8) -> Result<u8, U::Error> {
+        if self.baud != baud {
+            self.uart.set_baud_rate(baud)?;
+            self.baud = baud;
+        }
+        self.uart.transfer(byte)
+    }
+}
+
+impl<U: Uart> BusMaster for UartWire<U> {
+    type Error = U::Error;
+
+    fn reset(&mut self) -> Result<bool, Error<Self::Error>> {
+        match self.transfer_at(RESET_BAUD, RESET_BYTE)? {
+            // the line has been low the whole time
+            0x00 => Err(Error::WireNotHigh),
+            received => Ok(received != RESET_BYTE),
+        }
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.transfer_at(SLOT_BAUD, 0xFF)? == 0xFF)
+    }
+
+    fn write_bit(&mut self, high: bool) -> Result<(), Self::Error> {
+        self.transfer_at(SLOT_BAUD, if high { 0xFF } else { 0x00 })?;
+        Ok(())
+    }
+
+    fn write_byte(&mut self, mut byte: u8, _strong_pull_up: bool) -> Result<(), Self::Error> {
+        for _ in 0..8 {
+            self.write_bit(byte & 0x01 == 0x01)?;
+            byte >>= 1;
+        }
+        Ok(())
+    }
+}
+
+/// The UART only acts as a bus master, the pin methods are not used by the bus
+impl<U: Uart> OpenDrainOutput for UartWire<U> {
+    type Error = U::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bus_master(&mut self) -> Option<&mut dyn BusMaster<Error = Self::Error>> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::NoDelay;
+    use crate::OneWire;
+    use core::convert::Infallible;
+
+    /// A device that answers resets with a presence pulse and pulls every read slot low
+    #[derive(Default)]
+    struct Loopback {
+        baud: u32,
+        resets: usize,
+    }
+
+    impl Uart for Loopback {
+        type Error = Infallible;
+
+        fn set_baud_rate(&mut self, baud: u32) -> Result<(), Infallible> {
+            self.baud = baud;
+            Ok(())
+        }
+
+        fn transfer(&mut self, byte: u8) -> Result<u8, Infallible> {
+            Ok(match self.baud {
+                RESET_BAUD => {
+                    self.resets += 1;
+                    0xE0
+                }
+                SLOT_BAUD => 0xFE & byte,
+                _ => unreachable!(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_reset_and_read() {
+        let mut wire = OneWire::new(UartWire::new(Loopback::default()), false);
+        let mut delay = NoDelay;
+        assert!(wire.reset(&mut delay).unwrap());
+        let mut read = [0xFFu8; 2];
+        wire.reset_select_or_skip_write_read(&mut delay, None, &[0xBE], &mut read)
+            .unwrap();
+        assert_eq!(read, [0x00, 0x00]);
+        assert_eq!(wire.output().uart.resets, 2);
+    }
+}